@@ -0,0 +1,168 @@
+//! Benchmark the gap buffer at a range of node sizes, and recommend a node size for this machine.
+//!
+//! Run with:
+//!
+//! ```text
+//! cargo run --release --features tuning --bin tuning -- [document size in bytes]
+//! ```
+//!
+//! Each edit to a rope costs an edit within one gap buffer (which gets slower as nodes get bigger)
+//! plus a walk through the skip list to find that node (which gets shorter as nodes get bigger).
+//! This measures both halves at each size, and prints the size with the lowest total.
+
+use std::hint::black_box;
+use std::time::Instant;
+use jumprope::{GapBuffer, JumpRope};
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+
+const EDITS: usize = 200_000;
+
+// Nodes are about this full on average in a rope built by real edits.
+const FILL: f64 = 0.7;
+
+struct Row {
+    size: usize,
+    edit_ns: f64,
+    move_gap_ns: f64,
+    hop_ns: f64,
+    hops: f64,
+}
+
+impl Row {
+    fn total_ns(&self) -> f64 {
+        self.edit_ns + self.hop_ns * self.hops
+    }
+}
+
+fn ns_per(start: Instant, count: usize) -> f64 {
+    start.elapsed().as_nanos() as f64 / count as f64
+}
+
+// Round a byte offset down to the start of the character it's in.
+fn floor_char_boundary<const N: usize>(buf: &GapBuffer<N>, mut pos: usize) -> usize {
+    while !buf.is_char_boundary(pos) { pos -= 1; }
+    pos
+}
+
+fn measure<const N: usize>(doc_size: usize, edits: usize) -> Row {
+    let mut rng = SmallRng::seed_from_u64(123);
+    // Include a non-ASCII character, so moving the gap needs to count the characters it moves over.
+    let mut buf = GapBuffer::<N>::new_from_str(&("é".to_string() + &"x".repeat(N / 2 - 2)));
+
+    // Typing-style edits: mostly near the previous edit, with occasional jumps.
+    let mut pos = 0;
+    let start = Instant::now();
+    for _ in 0..edits {
+        let len = buf.len_bytes();
+        pos = if rng.gen_bool(0.05) { rng.gen_range(0..=len) } else { pos.min(len) };
+        pos = floor_char_boundary(&buf, pos);
+        if len > N / 4 && (len >= N * 3 / 4 || rng.gen_bool(0.3)) {
+            // Remove the character after pos, or before it at the end of the content.
+            if pos == len { pos = floor_char_boundary(&buf, len - 1); }
+            let mut end = pos + 1;
+            while !buf.is_char_boundary(end) { end += 1; }
+            buf.remove(pos, end - pos);
+        } else {
+            buf.try_insert(pos, "a").unwrap();
+            pos += 1;
+        }
+    }
+    black_box(&buf);
+    let edit_ns = ns_per(start, edits);
+
+    let len = buf.len_bytes();
+    let start = Instant::now();
+    for _ in 0..edits {
+        buf.move_gap(floor_char_boundary(&buf, rng.gen_range(0..=len)));
+    }
+    black_box(&buf);
+    let move_gap_ns = ns_per(start, edits);
+
+    // Chase pointers through a shuffled list of nodes, to measure the cost of visiting a node while
+    // searching the skip list. The list is capped at 64mb.
+    let num_nodes = ((doc_size as f64 / (N as f64 * FILL)) as usize).clamp(2, (64 << 20) / N);
+    let mut nodes = (0..num_nodes)
+        .map(|_| Box::new((0usize, GapBuffer::<N>::new_from_str("x"))))
+        .collect::<Vec<_>>();
+    let mut order = (0..num_nodes).collect::<Vec<_>>();
+    order.shuffle(&mut rng);
+    for i in 0..num_nodes {
+        nodes[order[i]].0 = order[(i + 1) % num_nodes];
+    }
+    let mut idx = order[0];
+    let mut total = 0;
+    let start = Instant::now();
+    for _ in 0..edits {
+        let node = &nodes[idx];
+        total += node.1.len_bytes();
+        idx = node.0;
+    }
+    black_box(total);
+    let hop_ns = ns_per(start, edits);
+
+    // A search in a skip list with n nodes walks through log_{1/p}(n) levels, visiting about 1/p
+    // nodes at each level.
+    let p = JumpRope::config().bias as f64 / 256.0;
+    let nodes_in_doc = (doc_size as f64 / (N as f64 * FILL)).max(1.0);
+    let hops = (nodes_in_doc.ln() / (1.0 / p).ln()).max(1.0) / p;
+
+    Row { size: N, edit_ns, move_gap_ns, hop_ns, hops }
+}
+
+fn main() {
+    let doc_size = std::env::args().nth(1)
+        .map(|s| s.parse().expect("Document size must be a number of bytes"))
+        .unwrap_or(100_000);
+
+    if cfg!(debug_assertions) {
+        eprintln!("Warning: this is a debug build. Run with --release for meaningful results.");
+    }
+    run(doc_size, EDITS);
+}
+
+fn run(doc_size: usize, edits: usize) {
+    println!("Measuring node sizes for a {} byte document. Current node size: {}",
+        doc_size, JumpRope::node_capacity());
+    println!();
+    println!("{:>6} {:>10} {:>10} {:>10} {:>8} {:>10}", "size", "edit ns", "move ns", "hop ns", "hops", "total ns");
+
+    let rows = [
+        measure::<64>(doc_size, edits),
+        measure::<128>(doc_size, edits),
+        measure::<256>(doc_size, edits),
+        measure::<392>(doc_size, edits),
+        measure::<512>(doc_size, edits),
+        measure::<768>(doc_size, edits),
+        measure::<1024>(doc_size, edits),
+        measure::<2048>(doc_size, edits),
+        measure::<4096>(doc_size, edits),
+    ];
+    for r in &rows {
+        println!("{:>6} {:>10.1} {:>10.1} {:>10.1} {:>8.1} {:>10.1}",
+            r.size, r.edit_ns, r.move_gap_ns, r.hop_ns, r.hops, r.total_ns());
+    }
+
+    let best = rows.iter().min_by(|a, b| a.total_ns().total_cmp(&b.total_ns())).unwrap();
+    println!();
+    println!("Recommended NODE_STR_SIZE: {}", best.size);
+
+    // The closest preset (by ratio) to the recommended size.
+    let presets = [(128, "small-nodes"), (392, "default"), (1024, "large-nodes")];
+    let (_, preset) = presets.iter()
+        .min_by(|a, b| {
+            let dist = |size: f64| (size / best.size as f64).ln().abs();
+            dist(a.0 as f64).total_cmp(&dist(b.0 as f64))
+        })
+        .unwrap();
+    println!("Closest node size preset: {}", preset);
+}
+
+#[cfg(test)]
+mod tests {
+    // Run every measurement with a tiny document, to check the benchmark itself doesn't panic.
+    #[test]
+    fn smoke() {
+        super::run(1000, 1000);
+    }
+}
@@ -0,0 +1,153 @@
+// Block (rectangular / column mode) selections.
+
+use core::ops::Range;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::JumpRope;
+use crate::utils::next_column;
+
+impl JumpRope {
+    /// Extract a rectangular block of text spanning the specified lines and display columns. For
+    /// each line, the characters which start within the column range are returned. Tabs are
+    /// expanded to multiples of `tab_width` when counting columns.
+    ///
+    /// Lines past the end of the document are omitted from the result.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("abcdef\nxy\n\t123456");
+    /// assert_eq!(rope.extract_block(0..3, 1..5, 4), &["bcde", "y", "1"]);
+    /// assert_eq!(rope.extract_block(1..10, 4..6, 4), &["", "12"]);
+    /// ```
+    pub fn extract_block(&self, lines: Range<usize>, cols: Range<usize>, tab_width: usize) -> Vec<String> {
+        let num_lines = lines.end.saturating_sub(lines.start);
+        let mut result = Vec::with_capacity(num_lines);
+        if num_lines == 0 || lines.start >= self.len_lines() { return result; }
+
+        let start = self.line_to_char(lines.start);
+        let mut col = 0;
+        let mut current = String::new();
+
+        for c in self.slice_chars(start..self.len_chars()) {
+            if c == '\n' {
+                result.push(core::mem::take(&mut current));
+                if result.len() == num_lines { return result; }
+                col = 0;
+            } else {
+                if cols.contains(&col) { current.push(c); }
+                col = next_column(col, c, tab_width);
+            }
+        }
+
+        // The last line in the document.
+        result.push(current);
+        result
+    }
+
+    /// Insert a rectangular block of text. Each item in `text_lines` is inserted at display
+    /// column `col` of successive lines, starting at `first_line`. Lines which are too short are
+    /// padded with spaces up to `col`, and new lines are appended to the document if needed.
+    /// Tabs are expanded to multiples of `tab_width` when counting columns.
+    ///
+    /// If `col` lands in the middle of a tab, the text is inserted after the tab.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("abcdef\nxy");
+    /// rope.insert_block(0, 3, &["1", "2", "3"], 4);
+    /// assert_eq!(rope, "abc1def\nxy 2\n   3");
+    /// ```
+    pub fn insert_block<S: AsRef<str>>(&mut self, first_line: usize, col: usize, text_lines: &[S], tab_width: usize) {
+        if text_lines.is_empty() { return; }
+
+        // First find where each line's content should be inserted (and how much padding it needs)
+        // in a single pass over the affected lines.
+        let existing_lines = self.len_lines().saturating_sub(first_line).min(text_lines.len());
+        let mut positions = Vec::with_capacity(existing_lines);
+        if existing_lines > 0 {
+            let mut pos = self.line_to_char(first_line);
+            let mut line_col = 0;
+            let mut done = false; // Have we found the position on the current line?
+            let mut chars = self.slice_chars(pos..self.len_chars());
+
+            while positions.len() < existing_lines {
+                let c = chars.next();
+                if !done && (line_col >= col || c.is_none() || c == Some('\n')) {
+                    positions.push((pos, col.saturating_sub(line_col)));
+                    done = true;
+                }
+                match c {
+                    None => break,
+                    Some('\n') => {
+                        line_col = 0;
+                        done = false;
+                    },
+                    Some(c) => line_col = next_column(line_col, c, tab_width),
+                }
+                pos += 1;
+            }
+        }
+        debug_assert_eq!(positions.len(), existing_lines);
+
+        // Any lines past the end of the document are appended in one go.
+        if existing_lines < text_lines.len() {
+            let mut appended = String::new();
+            let mut last_line = self.len_lines() - 1;
+            for (i, text) in text_lines.iter().enumerate().skip(existing_lines) {
+                let line = first_line + i;
+                appended.push_str(&"\n".repeat(line - last_line));
+                appended.push_str(&" ".repeat(col));
+                appended.push_str(text.as_ref());
+                last_line = line;
+            }
+            self.insert(self.len_chars(), &appended);
+        }
+
+        // Insert in reverse order so the positions we found stay valid.
+        for (&(pos, padding), text) in positions.iter().zip(text_lines).rev() {
+            if padding == 0 {
+                self.insert(pos, text.as_ref());
+            } else {
+                let mut content = " ".repeat(padding);
+                content.push_str(text.as_ref());
+                self.insert(pos, &content);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    #[test]
+    fn extract_block() {
+        let rope = JumpRope::from("κόσμε\n\n\tab\nxxxxxxxxxxxxxxxxxxxxxxxxxxx\n");
+        assert_eq!(rope.extract_block(0..5, 1..3, 2), &["όσ", "", "a", "xx", ""]);
+        assert_eq!(rope.extract_block(2..3, 2..3, 2), &["a"]);
+        assert_eq!(rope.extract_block(2..3, 1..3, 2), &["a"]);
+        assert_eq!(rope.extract_block(2..3, 0..3, 2), &["\ta"]);
+        assert!(rope.extract_block(5..10, 0..3, 2).is_empty());
+        assert!(rope.extract_block(1..1, 0..3, 2).is_empty());
+    }
+
+    #[test]
+    fn insert_block() {
+        let mut rope = JumpRope::from("κόσμε\n\n\tab");
+        rope.insert_block(0, 2, &["1", "2", "3", "4"], 4);
+        rope.check();
+        assert_eq!(rope, "κό1σμε\n  2\n\t3ab\n  4");
+
+        rope.insert_block(5, 1, &["x", "y"], 4);
+        rope.check();
+        assert_eq!(rope, "κό1σμε\n  2\n\t3ab\n  4\n\n x\n y");
+
+        let mut rope = JumpRope::new();
+        rope.insert_block(0, 0, &["a", "b"], 4);
+        assert_eq!(rope, "a\nb");
+    }
+}
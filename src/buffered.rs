@@ -0,0 +1,329 @@
+// A wrapper around a rope which merges runs of small adjacent edits before applying them.
+
+use core::cell::{Ref, RefCell};
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::Range;
+use alloc::string::String;
+use crate::JumpRope;
+use crate::utils::{count_chars, str_chars_to_bytes};
+
+// An edit which hasn't been applied to the rope yet. Positions are in the document with the edit
+// applied.
+#[derive(Debug, Clone, Default)]
+enum BufferedOp {
+    #[default]
+    None,
+    Insert { pos: usize, content: String, len_chars: usize },
+    Remove { pos: usize, len: usize },
+}
+
+/// A rope which buffers runs of adjacent edits, and applies each run to the underlying
+/// [`JumpRope`] as a single insert or remove.
+///
+/// This is designed for applying edits from event streams (like keystrokes) one character at a
+/// time. Consecutive inserts at the end of the previous insert, and consecutive deletes at either
+/// end of the previous delete, are merged together. Deleting text which is still in the buffer
+/// (like backspacing over something just typed) never touches the rope at all.
+///
+/// Pending edits are flushed whenever the content is read. [`borrow`](Self::borrow) flushes and
+/// returns the rope, so the whole read API of [`JumpRope`] is available through it.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut rope = JumpRopeBuf::from("Hello!");
+/// for (i, c) in " world".chars().enumerate() {
+///     rope.insert(5 + i, &c.to_string());
+/// }
+/// rope.remove(10..11); // Backspace
+/// assert_eq!(rope.len_chars(), 11);
+/// assert_eq!(rope.borrow().chars().filter(|c| *c == 'o').count(), 2);
+/// assert_eq!(rope, "Hello worl!");
+/// ```
+pub struct JumpRopeBuf(RefCell<(JumpRope, BufferedOp)>);
+
+impl JumpRopeBuf {
+    /// Create a new, empty rope.
+    pub fn new() -> Self {
+        Self::from(JumpRope::new())
+    }
+
+    // Apply any pending edit to the rope.
+    fn flush_inner(inner: &mut (JumpRope, BufferedOp)) {
+        let (rope, op) = inner;
+        match core::mem::take(op) {
+            BufferedOp::None => {}
+            BufferedOp::Insert { pos, content, .. } => rope.insert(pos, content),
+            BufferedOp::Remove { pos, len } => rope.remove(pos..pos + len),
+        }
+    }
+
+    /// Apply any pending edits to the underlying rope.
+    pub fn flush(&self) {
+        // Only borrow mutably when there's something to apply, so reads can overlap.
+        if matches!(self.0.borrow().1, BufferedOp::None) { return; }
+        Self::flush_inner(&mut self.0.borrow_mut());
+    }
+
+    /// Flush any pending edits, and borrow the underlying rope to read it.
+    ///
+    /// Any number of these references can be alive at once. The buffer can't be edited while
+    /// they are, so there is never anything to flush when another one is borrowed.
+    pub fn borrow(&self) -> Ref<'_, JumpRope> {
+        self.flush();
+        Ref::map(self.0.borrow(), |(rope, _)| rope)
+    }
+
+    /// Flush any pending edits, and return the underlying rope.
+    pub fn into_inner(self) -> JumpRope {
+        let mut inner = self.0.into_inner();
+        Self::flush_inner(&mut inner);
+        inner.0
+    }
+
+    /// Flush any pending edits, and get mutable access to the underlying rope. Edits made
+    /// directly to the rope aren't buffered.
+    pub fn rope_mut(&mut self) -> &mut JumpRope {
+        let inner = self.0.get_mut();
+        Self::flush_inner(inner);
+        &mut inner.0
+    }
+
+    /// The number of characters in the document, including any pending edits. This doesn't
+    /// flush the buffer.
+    pub fn len_chars(&self) -> usize {
+        let (rope, op) = &*self.0.borrow();
+        match op {
+            BufferedOp::None => rope.len_chars(),
+            BufferedOp::Insert { len_chars, .. } => rope.len_chars() + len_chars,
+            BufferedOp::Remove { len, .. } => rope.len_chars() - len,
+        }
+    }
+
+    /// The number of bytes in the document, including any pending edits.
+    pub fn len_bytes(&self) -> usize {
+        self.borrow().len_bytes()
+    }
+
+    /// Returns `true` if the document is empty. This doesn't flush the buffer.
+    pub fn is_empty(&self) -> bool {
+        self.len_chars() == 0
+    }
+
+    /// Insert content at a character position. Positions past the end of the document are
+    /// truncated. See [`JumpRope::insert`].
+    pub fn insert(&mut self, pos: usize, content: &str) {
+        if content.is_empty() { return; }
+        let pos = pos.min(self.len_chars());
+        let inner = self.0.get_mut();
+
+        if let BufferedOp::Insert { pos: start, content: buf, len_chars } = &mut inner.1 {
+            if pos >= *start && pos <= *start + *len_chars {
+                let byte_pos = str_chars_to_bytes(buf, pos - *start);
+                buf.insert_str(byte_pos, content);
+                *len_chars += count_chars(content);
+                return;
+            }
+        }
+
+        Self::flush_inner(inner);
+        inner.1 = BufferedOp::Insert { pos, content: content.into(), len_chars: count_chars(content) };
+    }
+
+    /// Remove a range of characters. The range is clamped to the document. See
+    /// [`JumpRope::remove`].
+    pub fn remove(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.len_chars());
+        if range.start >= end { return; }
+        let (start, len) = (range.start, end - range.start);
+        let inner = self.0.get_mut();
+
+        match &mut inner.1 {
+            BufferedOp::Insert { pos, content, len_chars } if start >= *pos && end <= *pos + *len_chars => {
+                // The removed content hasn't been inserted yet.
+                let start_byte = str_chars_to_bytes(content, start - *pos);
+                let end_byte = start_byte + str_chars_to_bytes(&content[start_byte..], len);
+                content.replace_range(start_byte..end_byte, "");
+                *len_chars -= len;
+                if *len_chars == 0 { inner.1 = BufferedOp::None; }
+            }
+            BufferedOp::Remove { pos, len: removed } if end == *pos || start == *pos => {
+                // Backspacing before the removed range, or deleting after it.
+                *pos = start;
+                *removed += len;
+            }
+            _ => {
+                Self::flush_inner(inner);
+                inner.1 = BufferedOp::Remove { pos: start, len };
+            }
+        }
+    }
+
+    /// Replace a range of characters with new content. See [`JumpRope::replace`].
+    pub fn replace(&mut self, range: Range<usize>, content: &str) {
+        let pos = range.start.min(self.len_chars());
+        self.remove(range);
+        self.insert(pos, content);
+    }
+}
+
+impl Default for JumpRopeBuf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<JumpRope> for JumpRopeBuf {
+    fn from(rope: JumpRope) -> Self {
+        Self(RefCell::new((rope, BufferedOp::None)))
+    }
+}
+
+impl From<&str> for JumpRopeBuf {
+    fn from(s: &str) -> Self {
+        Self::from(JumpRope::from(s))
+    }
+}
+
+impl From<String> for JumpRopeBuf {
+    fn from(s: String) -> Self {
+        Self::from(JumpRope::from(s))
+    }
+}
+
+impl From<JumpRopeBuf> for JumpRope {
+    fn from(buf: JumpRopeBuf) -> Self {
+        buf.into_inner()
+    }
+}
+
+impl Clone for JumpRopeBuf {
+    fn clone(&self) -> Self {
+        Self(RefCell::new(self.0.borrow().clone()))
+    }
+}
+
+impl Debug for JumpRopeBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Debug::fmt(&*self.borrow(), f)
+    }
+}
+
+impl Display for JumpRopeBuf {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&*self.borrow(), f)
+    }
+}
+
+impl PartialEq for JumpRopeBuf {
+    fn eq(&self, other: &Self) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
+impl Eq for JumpRopeBuf {}
+
+impl PartialEq<JumpRope> for JumpRopeBuf {
+    fn eq(&self, other: &JumpRope) -> bool {
+        *self.borrow() == *other
+    }
+}
+impl PartialEq<str> for JumpRopeBuf {
+    fn eq(&self, other: &str) -> bool {
+        *self.borrow() == *other
+    }
+}
+impl PartialEq<&str> for JumpRopeBuf {
+    fn eq(&self, other: &&str) -> bool {
+        *self.borrow() == **other
+    }
+}
+impl PartialEq<String> for JumpRopeBuf {
+    fn eq(&self, other: &String) -> bool {
+        *self.borrow() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::{JumpRope, JumpRopeBuf};
+
+    #[test]
+    fn buffered_edits() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut rope = JumpRopeBuf::from("κό𝕐𝕆😘σμε and some ascii");
+        let mut expected = rope.to_string();
+        let byte = |s: &str, pos: usize| s.char_indices().nth(pos).map_or(s.len(), |(b, _)| b);
+
+        // Mostly type and delete near the same spot, so edits are merged.
+        let mut pos: usize = 3;
+        for i in 0..2000 {
+            let len = expected.chars().count();
+            pos = (pos + rng.gen_range(0..3)).saturating_sub(1).min(len);
+            match rng.gen_range(0..6) {
+                0 | 1 => {
+                    let s = if rng.gen_bool(0.5) { "a" } else { "😘\n" };
+                    rope.insert(pos, s);
+                    expected.insert_str(byte(&expected, pos), s);
+                    pos += s.chars().count();
+                }
+                2 if pos > 0 => {
+                    rope.remove(pos - 1..pos);
+                    expected.replace_range(byte(&expected, pos - 1)..byte(&expected, pos), "");
+                    pos -= 1;
+                }
+                3 => {
+                    let end = (pos + rng.gen_range(0..3)).min(len);
+                    rope.remove(pos..end + 5 * (i % 2));
+                    expected.replace_range(byte(&expected, pos)..byte(&expected, end + 5 * (i % 2)), "");
+                }
+                4 => {
+                    let end = (pos + 1).min(len);
+                    rope.replace(pos..end, "xy");
+                    expected.replace_range(byte(&expected, pos)..byte(&expected, end), "xy");
+                }
+                _ => pos = rng.gen_range(0..=len),
+            }
+
+            assert_eq!(rope.len_chars(), expected.chars().count());
+            if i % 50 == 0 {
+                assert_eq!(rope, expected);
+                rope.borrow().check();
+            }
+        }
+
+        assert_eq!(rope.len_bytes(), expected.len());
+        assert_eq!(rope.clone(), rope);
+        let rope: JumpRope = rope.into();
+        assert_eq!(rope, expected);
+    }
+
+    #[test]
+    fn buffered_insert_past_end() {
+        let mut rope = JumpRopeBuf::new();
+        rope.insert(10, "a");
+        rope.insert(10, "b");
+        rope.remove(5..10);
+        assert_eq!(rope, "ab");
+        assert!(!rope.is_empty());
+        rope.rope_mut().remove(0..2);
+        assert!(rope.is_empty());
+        assert_eq!(rope.into_inner(), String::new());
+    }
+
+    #[test]
+    fn overlapping_borrows() {
+        let mut rope = JumpRopeBuf::from("abc");
+        rope.insert(3, "d");
+        let (a, b) = (rope.borrow(), rope.borrow());
+        assert_eq!(a.len_chars() + b.len_bytes(), 8);
+        drop((a, b));
+
+        rope.insert(4, "e");
+        assert_eq!(rope.borrow().len_chars() + rope.borrow().len_bytes(), 10);
+        rope.remove(0..1);
+        assert!(rope == rope);
+        assert_eq!(rope, "bcde");
+    }
+}
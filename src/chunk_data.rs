@@ -0,0 +1,144 @@
+// A side table for storing user data alongside each chunk in a rope.
+
+use alloc::collections::BTreeMap;
+use crate::JumpRope;
+
+/// A side table of user data (like a syntax highlighter's lexer state) for each chunk in a rope.
+///
+/// Entries are keyed by the chunk IDs from [`JumpRope::chunks_with_ids`]. Chunk IDs change
+/// whenever a chunk's content changes - including when a node is split or merged with its
+/// neighbour - so entries for edited chunks are invalidated automatically. Chunks which weren't
+/// touched by an edit keep their IDs, and their data stays valid even if the chunk moves.
+///
+/// Call [`retain_live`](Self::retain_live) now and then to free the entries for chunks which are
+/// no longer in the rope.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut rope = JumpRope::from("fn main() {}\n".repeat(100));
+/// let mut lines: ChunkData<usize> = ChunkData::new();
+///
+/// // Count the newlines in each chunk, reusing the counts from before the edit.
+/// let mut count_lines = |rope: &JumpRope, lines: &mut ChunkData<usize>| {
+///     let mut computed = 0;
+///     for (id, s) in rope.chunks_with_ids() {
+///         if lines.get(id).is_none() {
+///             lines.insert(id, s.matches('\n').count());
+///             computed += 1;
+///         }
+///     }
+///     lines.retain_live(rope);
+///     computed
+/// };
+///
+/// assert_eq!(count_lines(&rope, &mut lines), rope.chunks().count());
+/// rope.insert(5, "\n");
+/// // Only the chunks around the edit are recounted.
+/// assert!(count_lines(&rope, &mut lines) < rope.chunks().count());
+/// assert_eq!(lines.iter(&rope).map(|(_, _, n)| *n.unwrap()).sum::<usize>(), 101);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChunkData<T> {
+    entries: BTreeMap<u64, T>,
+}
+
+impl<T> Default for ChunkData<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ChunkData<T> {
+    /// Create a new, empty table.
+    pub fn new() -> Self {
+        Self { entries: BTreeMap::new() }
+    }
+
+    /// The number of entries stored in the table. This includes entries for chunks which are no
+    /// longer in the rope, until [`retain_live`](Self::retain_live) is called.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the table has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the data stored for the chunk with the given ID.
+    pub fn get(&self, id: u64) -> Option<&T> {
+        self.entries.get(&id)
+    }
+
+    /// Get a mutable reference to the data stored for the chunk with the given ID.
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut T> {
+        self.entries.get_mut(&id)
+    }
+
+    /// Store data for the chunk with the given ID, returning the previously stored data.
+    pub fn insert(&mut self, id: u64, data: T) -> Option<T> {
+        self.entries.insert(id, data)
+    }
+
+    /// Remove the data stored for the chunk with the given ID.
+    pub fn remove(&mut self, id: u64) -> Option<T> {
+        self.entries.remove(&id)
+    }
+
+    /// Remove all entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Remove the entries for chunks which are no longer in the rope. This runs in *O(n log n)*
+    /// time in the size of the rope.
+    pub fn retain_live(&mut self, rope: &JumpRope) {
+        if self.entries.is_empty() { return; }
+        let mut live = BTreeMap::new();
+        for (id, _) in rope.chunks_with_ids() {
+            if let Some(data) = self.entries.remove(&id) {
+                live.insert(id, data);
+            }
+        }
+        self.entries = live;
+    }
+
+    /// Iterate through the chunks in the rope, along with their IDs and any data stored for
+    /// them. Chunks which have been edited since their data was stored yield `None`.
+    pub fn iter<'a>(&'a self, rope: &'a JumpRope) -> impl Iterator<Item = (u64, &'a str, Option<&'a T>)> + 'a {
+        rope.chunks_with_ids().map(move |(id, s)| (id, s, self.entries.get(&id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ChunkData, JumpRope};
+
+    #[test]
+    fn edits_invalidate_chunk_data() {
+        let mut rope = JumpRope::from("hello there everyone\n".repeat(50));
+        let mut data = ChunkData::new();
+        for (id, s) in rope.chunks_with_ids() {
+            data.insert(id, s.to_string());
+        }
+
+        rope.insert(30, "xyz");
+        rope.remove(400..600);
+        rope.replace(700..705, "😘");
+
+        // Any data still attached to a chunk must describe that chunk's current content.
+        let mut stale = 0;
+        for (_, s, stored) in data.iter(&rope) {
+            match stored {
+                Some(stored) => assert_eq!(stored, s),
+                None => stale += 1,
+            }
+        }
+        assert!(stale > 0);
+
+        data.retain_live(&rope);
+        assert_eq!(data.len(), rope.chunks().count() - stale);
+    }
+}
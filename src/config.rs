@@ -0,0 +1,63 @@
+use crate::JumpRopeCustom;
+use crate::jumprope::{MAX_HEIGHT, RNG_NAME};
+
+/// The parameters jumprope was compiled with. See [`JumpRope::config`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RopeConfig {
+    /// The maximum number of bytes of text stored in each node. See
+    /// [`JumpRope::node_capacity`].
+    pub node_capacity: usize,
+    /// The probability (out of 256) that a node is one level taller than the level below.
+    pub bias: u8,
+    /// The maximum height of a node in the skip list.
+    pub max_height: usize,
+    /// The name of the RNG used to pick node heights. This depends on the `ddos_protection` and
+    /// `rand` features.
+    pub rng: &'static str,
+}
+
+impl<const NODE: usize, const BIAS: u8> JumpRopeCustom<NODE, BIAS> {
+    /// The maximum number of bytes of text stored in each node of the rope. Chunks returned by
+    /// [`chunks`](Self::chunks) are never longer than this.
+    ///
+    /// This is the `NODE` parameter of [`JumpRopeCustom`]. For [`JumpRope`](crate::JumpRope),
+    /// it is smaller in debug builds to exercise the node splitting code. In release builds it can
+    /// be changed with the `small-nodes` and `large-nodes` features.
+    pub const fn node_capacity() -> usize {
+        NODE
+    }
+
+    /// Describe the parameters jumprope was compiled with. This is useful for logging, or for
+    /// tuning your own chunking to match the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let config = JumpRope::config();
+    /// assert_eq!(config.node_capacity, JumpRope::node_capacity());
+    /// println!("{:?}", config);
+    /// ```
+    pub const fn config() -> RopeConfig {
+        RopeConfig {
+            node_capacity: NODE,
+            bias: BIAS,
+            max_height: MAX_HEIGHT,
+            rng: RNG_NAME,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    #[test]
+    fn chunks_fit_in_nodes() {
+        let rope = JumpRope::from("κό𝕐𝕆😘σμε".repeat(100));
+        for (s, _) in rope.chunks() {
+            assert!(s.len() <= JumpRope::node_capacity());
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// Conversions from a rope into other string types. These all copy the content directly into its
+// final allocation, without building an intermediate String first.
+
+use core::ops::Range;
+use alloc::string::String;
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+use crate::JumpRope;
+
+impl JumpRope {
+    /// Consume the rope, returning its content as a `String`. The string is allocated once, with
+    /// exactly enough capacity for the content.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// let s = rope.into_string();
+    /// assert_eq!(s, "κόσμε");
+    /// assert_eq!(s.capacity(), s.len());
+    /// ```
+    pub fn into_string(self) -> String {
+        self.to_string()
+    }
+
+    /// Copy the rope's content into a new `String`. The string is allocated once, with exactly
+    /// enough capacity for the content.
+    ///
+    /// This shadows [`ToString::to_string`], which (via `Display`) grows the string a chunk at a
+    /// time. Generic code calling `ToString` still gets the slower implementation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// let s = rope.to_string();
+    /// assert_eq!(s, "κόσμε");
+    /// assert_eq!(s.capacity(), s.len());
+    /// ```
+    // The result is identical to the Display implementation. It's just faster.
+    #[allow(clippy::inherent_to_string_shadow_display)]
+    pub fn to_string(&self) -> String {
+        let mut s = String::with_capacity(self.len_bytes());
+        for (chunk, _) in self.chunks() {
+            s.push_str(chunk);
+        }
+        s
+    }
+
+    /// Copy a range of characters into a new `String`. Only the nodes covering the range are read,
+    /// and the string is allocated once with exactly enough capacity for the content. The range
+    /// is clamped to the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxκόσμεxxx");
+    /// assert_eq!(rope.substring(3..8), "κόσμε");
+    /// assert_eq!(rope.substring(8..100), "xxx");
+    /// ```
+    pub fn substring(&self, range: Range<usize>) -> String {
+        let bytes = self.byte_range_of_char_range(range.clone());
+        let mut s = String::with_capacity(bytes.len());
+        for chunk in self.chunks_in_range(range) {
+            s.push_str(chunk);
+        }
+        s
+    }
+
+    /// Consume the rope, returning its content as a [`bytes::Bytes`] for handing to network code.
+    /// The content is copied once, and then owned by the returned value.
+    ///
+    /// This method is only available with the `bytes` feature enabled.
+    #[cfg(feature = "bytes")]
+    pub fn into_bytes(self) -> bytes::Bytes {
+        bytes::Bytes::from(self.into_string())
+    }
+}
+
+impl From<JumpRope> for String {
+    fn from(rope: JumpRope) -> Self {
+        rope.into_string()
+    }
+}
+
+/// Copy the rope's content into a shared string. The content is copied straight into the `Arc`'s
+/// allocation.
+#[cfg(target_has_atomic = "ptr")]
+impl From<&JumpRope> for Arc<str> {
+    fn from(rope: &JumpRope) -> Self {
+        let mut arc = Arc::<[u8]>::new_uninit_slice(rope.len_bytes());
+        let dest = Arc::get_mut(&mut arc).unwrap();
+        let mut pos = 0;
+        for (chunk, _) in rope.chunks() {
+            for (d, &b) in dest[pos..pos + chunk.len()].iter_mut().zip(chunk.as_bytes()) {
+                d.write(b);
+            }
+            pos += chunk.len();
+        }
+        assert_eq!(pos, dest.len());
+
+        unsafe {
+            // Every byte has been written, and the bytes are valid UTF-8 because they came from
+            // &strs.
+            let bytes = arc.assume_init();
+            Arc::from_raw(Arc::into_raw(bytes) as *const str)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::JumpRope;
+
+    #[test]
+    fn conversions() {
+        let s = "κό𝕐𝕆😘σμε and some ascii text which spans several nodes";
+        let rope = JumpRope::from(s);
+
+        assert_eq!(&*Arc::<str>::from(&rope), s);
+        assert_eq!(&*Arc::<str>::from(&JumpRope::new()), "");
+        #[cfg(feature = "bytes")]
+        assert_eq!(rope.clone().into_bytes(), s.as_bytes());
+
+        let string = rope.to_string();
+        assert_eq!(string, s);
+        assert_eq!(string.capacity(), s.len());
+        assert_eq!(ToString::to_string(&rope), s);
+
+        let chars = s.chars().collect::<Vec<_>>();
+        for start in 0..chars.len() {
+            let end = (start + 12).min(chars.len());
+            let sub = rope.substring(start..start + 12);
+            assert_eq!(sub, chars[start..end].iter().collect::<String>());
+            assert_eq!(sub.capacity(), sub.len());
+        }
+
+        let string = String::from(rope);
+        assert_eq!(string, s);
+        assert_eq!(string.capacity(), s.len());
+    }
+}
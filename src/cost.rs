@@ -0,0 +1,143 @@
+// Estimating how much work an edit will do, before doing it.
+
+use core::ops::Range;
+use crate::JumpRope;
+use crate::jumprope::NODE_STR_SIZE;
+
+/// An estimate of the structural work a prospective edit would do. See
+/// [`JumpRope::edit_cost`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct EditCost {
+    /// The number of nodes visited while searching the skip list for the start of the edit.
+    pub nodes_traversed: usize,
+    /// The number of nodes whose content would be modified or removed.
+    pub nodes_touched: usize,
+    /// The number of nodes which would be entirely deleted, and freed.
+    pub nodes_removed: usize,
+    /// The (approximate) number of new nodes which would be allocated for the inserted content.
+    pub nodes_created: usize,
+}
+
+impl EditCost {
+    /// The total number of nodes the edit would visit, modify, allocate or free. This is a rough
+    /// measure of how long the edit will take.
+    pub fn total(&self) -> usize {
+        self.nodes_traversed + self.nodes_touched + self.nodes_removed + self.nodes_created
+    }
+}
+
+impl JumpRope {
+    /// Estimate the work which replacing `range` with `insert_bytes` bytes of content would do,
+    /// without modifying the rope. This lets latency sensitive callers decide whether to apply a
+    /// huge edit immediately, or defer it (eg to a background copy of the document).
+    ///
+    /// The search for the start of the range takes *O(log n)* time, and then every node in the
+    /// range is visited. So this is much cheaper than the edit itself, but not free for huge
+    /// ranges.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("some text. ".repeat(1000));
+    /// let small = rope.edit_cost(10..11, 1);
+    /// let big = rope.edit_cost(10..10000, 0);
+    /// assert!(small.total() < big.total());
+    /// assert!(big.nodes_removed > 0);
+    /// ```
+    pub fn edit_cost(&self, range: Range<usize>, insert_bytes: usize) -> EditCost {
+        let end = range.end.min(self.len_chars());
+        let start = range.start.min(end);
+        let mut cost = EditCost::default();
+
+        // Find the start of the range the same way edits do, counting the nodes we visit.
+        let mut node = &self.head;
+        let mut height = self.head.height as usize - 1;
+        let mut offset = start;
+        loop {
+            let next = node.nexts()[height];
+            if offset > next.skip_chars {
+                offset -= next.skip_chars;
+                node = unsafe { &*next.node };
+                cost.nodes_traversed += 1;
+            } else if height == 0 {
+                break;
+            } else {
+                height -= 1;
+            }
+        }
+
+        // Inserted content goes into the node at the start of the range if there's room.
+        // Otherwise it's split into new nodes, and the rest of the node moves to a new node too.
+        cost.nodes_touched = 1;
+        if insert_bytes > NODE_STR_SIZE - node.str.len_bytes() {
+            let splits_node = offset < node.num_chars();
+            cost.nodes_created = insert_bytes.div_ceil(NODE_STR_SIZE) + splits_node as usize;
+        }
+
+        // Walk through the nodes covered by the removed range.
+        let mut remaining = end - start;
+        while remaining > 0 {
+            if offset == node.num_chars() {
+                node = unsafe { &*node.first_next().node };
+                offset = 0;
+                cost.nodes_touched += 1;
+                continue;
+            }
+
+            let removed = (node.num_chars() - offset).min(remaining);
+            if removed == node.num_chars() && !core::ptr::eq(node, &self.head) {
+                cost.nodes_removed += 1;
+            }
+            remaining -= removed;
+            offset += removed;
+        }
+
+        cost
+    }
+
+    /// Count the nodes whose content would be modified or removed by an edit to `range`. This is
+    /// equivalent to `rope.edit_cost(range, 0).nodes_touched`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("some text. ".repeat(1000));
+    /// assert_eq!(rope.nodes_touched_by(5..6), 1);
+    /// assert!(rope.nodes_touched_by(0..10000) > 1);
+    /// ```
+    pub fn nodes_touched_by(&self, range: Range<usize>) -> usize {
+        self.edit_cost(range, 0).nodes_touched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    #[test]
+    fn edit_cost() {
+        let rope = JumpRope::from("κό𝕐𝕆😘σμε and some text. ".repeat(50));
+        let len = rope.len_chars();
+
+        let empty = rope.edit_cost(10..10, 0);
+        assert_eq!(empty.nodes_touched, 1);
+        assert_eq!(empty.nodes_removed, 0);
+        assert_eq!(empty.nodes_created, 0);
+        assert_eq!(JumpRope::new().edit_cost(0..100, 0).nodes_touched, 1);
+
+        // Removing everything frees every node except the head.
+        let all = rope.edit_cost(0..len, 0);
+        assert_eq!(all.nodes_traversed, 0);
+        // (The head node is counted too, even though it's empty here.)
+        assert!(all.nodes_touched >= rope.chunks().count());
+        assert_eq!(all.nodes_removed + 1, all.nodes_touched);
+
+        // Costs grow with the size of the edit.
+        assert!(rope.edit_cost(len - 5..len - 4, 0).nodes_traversed > 0);
+        assert!(rope.nodes_touched_by(0..len / 2) <= rope.nodes_touched_by(0..len));
+        assert!(rope.edit_cost(10..10, 10000).nodes_created > 1);
+    }
+}
@@ -0,0 +1,394 @@
+// Cursors which hold a position in a rope between reads and edits.
+
+use crate::JumpRope;
+use crate::jumprope::{Node, RopeCursor};
+use crate::lines::PositionInfo;
+use crate::utils::count_chars;
+
+/// A read only cursor at a character position in a [`JumpRope`]. The cursor remembers where it
+/// is in the skip list, so moving it a character at a time with [`next_char`](Self::next_char)
+/// and [`prev_char`](Self::prev_char) doesn't search the rope from the top again. (Stepping back
+/// over the start of a node does, as nodes don't point back to the node before them.)
+///
+/// The cursor borrows the rope, so the rope can't be edited while the cursor exists. See
+/// [`JumpRope::cursor`].
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let rope = JumpRope::from("κόσμε");
+/// let mut cursor = rope.cursor(3);
+/// assert_eq!(cursor.next_char(), Some('μ'));
+/// assert_eq!(cursor.position(), 4);
+/// assert_eq!(cursor.prev_char(), Some('μ'));
+/// assert_eq!(cursor.prev_char(), Some('σ'));
+/// assert_eq!(cursor.chars_remaining(), 3);
+/// ```
+#[derive(Clone)]
+pub struct Cursor<'a> {
+    rope: &'a JumpRope,
+    inner: RopeCursor,
+    // The byte offset of the cursor within its node.
+    local_byte: usize,
+}
+
+impl JumpRope {
+    /// Create a read only cursor at the specified character position. Positions past the end of
+    /// the rope are clamped. See [`Cursor`].
+    pub fn cursor(&self, pos: usize) -> Cursor<'_> {
+        let mut cursor = Cursor {
+            rope: self,
+            inner: self.cursor_at_start(),
+            local_byte: 0,
+        };
+        cursor.seek_to_char(pos);
+        cursor
+    }
+}
+
+impl<'a> Cursor<'a> {
+    fn height(&self) -> usize {
+        self.rope.head.height as usize
+    }
+
+    fn here(&self) -> &'a Node {
+        unsafe { &*self.inner.here_ptr() }
+    }
+
+    /// The rope this cursor is reading.
+    pub fn rope(&self) -> &'a JumpRope {
+        self.rope
+    }
+
+    /// Move the cursor to the specified character position. Positions past the end of the rope are
+    /// clamped. This runs in *O(log n)* time, or faster if the position is in the cursor's
+    /// current node.
+    pub fn seek_to_char(&mut self, pos: usize) {
+        let pos = pos.min(self.rope.len_chars());
+        let local = self.inner.local_char_pos();
+        let node_start = self.position() - local;
+        if pos >= node_start && pos <= node_start + self.here().num_chars() {
+            self.inner.move_within_node(self.height(), pos as isize - self.position() as isize);
+        } else {
+            self.inner = self.rope.cursor_at_char(pos, true);
+        }
+        self.local_byte = self.here().str.count_bytes(self.inner.local_char_pos());
+    }
+
+    /// The character position of the cursor.
+    pub fn position(&self) -> usize {
+        self.inner.global_char_pos(self.rope.head.height)
+    }
+
+    /// The byte offset of the cursor in the UTF8 representation of the rope.
+    pub fn byte_position(&self) -> usize {
+        self.inner.global_bytes_before_node(self.rope.head.height) + self.local_byte
+    }
+
+    /// Get the character position, byte offset, line and column of the cursor in a single call.
+    /// This is useful for status bars, which need every coordinate after each caret movement.
+    ///
+    /// The byte offset and line come from the counts cached in the cursor, and the column is found
+    /// by scanning back through the cursor's node to the previous newline. The rope is only
+    /// searched again if the line starts before the cursor's node.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("abc\nκόσμε");
+    /// let mut cursor = rope.cursor(6);
+    /// cursor.next_char();
+    /// let info = cursor.position_info();
+    /// assert_eq!((info.char_pos, info.byte_pos, info.line, info.column), (7, 10, 1, 3));
+    /// ```
+    pub fn position_info(&self) -> PositionInfo {
+        let height = self.rope.head.height;
+        let here = self.here();
+        let local = self.inner.local_char_pos();
+        let char_pos = self.position();
+        let line = self.inner.global_lines_before_node(height)
+            + if here.num_lines() == 0 { 0 } else { here.str.count_newlines_in(0..local) };
+
+        // The content of the node before the cursor, in up to 2 pieces around the gap.
+        let s = &here.str;
+        let gap_bytes = s.gap_start_bytes as usize;
+        let (first, second) = if self.local_byte <= gap_bytes {
+            ("", &s.start_as_str()[..self.local_byte])
+        } else {
+            (s.start_as_str(), &s.end_as_str()[..self.local_byte - gap_bytes])
+        };
+        let column = if let Some(i) = second.rfind('\n') {
+            count_chars(&second[i + 1..])
+        } else if let Some(i) = first.rfind('\n') {
+            count_chars(&first[i + 1..]) + count_chars(second)
+        } else {
+            // The line starts in an earlier node.
+            char_pos - self.rope.line_to_char(line)
+        };
+
+        PositionInfo { char_pos, byte_pos: self.byte_position(), line, column }
+    }
+
+    /// The number of characters after the cursor.
+    pub fn chars_remaining(&self) -> usize {
+        self.rope.len_chars() - self.position()
+    }
+
+    /// The number of bytes after the cursor.
+    pub fn bytes_remaining(&self) -> usize {
+        self.rope.len_bytes() - self.byte_position()
+    }
+
+    // The node's content after the cursor, up to the gap or the end of the node.
+    fn str_after(&self) -> &'a str {
+        let s = &self.here().str;
+        let gap_bytes = s.gap_start_bytes as usize;
+        if self.local_byte < gap_bytes {
+            &s.start_as_str()[self.local_byte..]
+        } else {
+            &s.end_as_str()[self.local_byte - gap_bytes..]
+        }
+    }
+
+    // The node's content before the cursor, back to the gap or the start of the node.
+    fn str_before(&self) -> &'a str {
+        let s = &self.here().str;
+        let gap_bytes = s.gap_start_bytes as usize;
+        if self.local_byte <= gap_bytes {
+            &s.start_as_str()[..self.local_byte]
+        } else {
+            &s.end_as_str()[..self.local_byte - gap_bytes]
+        }
+    }
+
+    /// Read the character after the cursor and move the cursor past it. Returns `None` at the end
+    /// of the rope.
+    pub fn next_char(&mut self) -> Option<char> {
+        while self.inner.local_char_pos() == self.here().num_chars() {
+            if self.here().first_next().node.is_null() { return None; }
+            unsafe { self.inner.advance_node(self.height()); }
+            self.local_byte = 0;
+        }
+
+        let c = self.str_after().chars().next().unwrap();
+        self.local_byte += c.len_utf8();
+        self.inner.move_within_node(self.height(), 1);
+        Some(c)
+    }
+
+    /// Read the character before the cursor and move the cursor back before it. Returns `None` at
+    /// the start of the rope.
+    pub fn prev_char(&mut self) -> Option<char> {
+        if self.inner.local_char_pos() == 0 {
+            let pos = self.position();
+            if pos == 0 { return None; }
+            // Find the end of the previous node.
+            self.inner = self.rope.cursor_at_char(pos, true);
+            self.local_byte = self.here().str.len_bytes();
+        }
+
+        let c = self.str_before().chars().next_back().unwrap();
+        self.local_byte -= c.len_utf8();
+        self.inner.move_within_node(self.height(), -1);
+        Some(c)
+    }
+}
+
+impl core::fmt::Debug for Cursor<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Cursor")
+            .field("position", &self.position())
+            .finish()
+    }
+}
+
+/// A cursor for editing a [`JumpRope`] at a position. The cursor stays where it is in the skip
+/// list between edits, so a run of edits at (or next to) the same point - like a user typing -
+/// doesn't search the rope from the top for each one. See [`JumpRope::mut_cursor`].
+///
+/// Edits made through the cursor are recorded by [`JumpRope::with_recording`], the same as
+/// edits made directly.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut rope = JumpRope::from("Hello!");
+/// let mut cursor = rope.mut_cursor(5);
+/// cursor.insert(" worl");
+/// cursor.insert("d");
+/// cursor.backspace(1);
+/// cursor.insert("d");
+/// assert_eq!(cursor.position(), 11);
+/// cursor.delete(1);
+/// assert_eq!(rope, "Hello world");
+/// ```
+pub struct MutCursor<'a> {
+    rope: &'a mut JumpRope,
+    inner: RopeCursor,
+}
+
+impl JumpRope {
+    /// Create a cursor for editing the rope at the specified character position. Positions past
+    /// the end of the rope are clamped. See [`MutCursor`].
+    pub fn mut_cursor(&mut self, pos: usize) -> MutCursor<'_> {
+        let pos = pos.min(self.len_chars());
+        let inner = self.cursor_at_char(pos, true);
+        MutCursor { rope: self, inner }
+    }
+}
+
+impl<'a> MutCursor<'a> {
+    fn height(&self) -> usize {
+        self.rope.head.height as usize
+    }
+
+    /// The rope this cursor is editing.
+    pub fn rope(&self) -> &JumpRope {
+        self.rope
+    }
+
+    /// The character position of the cursor.
+    pub fn position(&self) -> usize {
+        self.inner.global_char_pos(self.rope.head.height)
+    }
+
+    /// Move the cursor to the specified character position. Positions past the end of the rope are
+    /// clamped. Moving within the cursor's current node is *O(1)*. Otherwise the rope is searched
+    /// from the top in *O(log n)* time.
+    pub fn seek_to_char(&mut self, pos: usize) {
+        let pos = pos.min(self.rope.len_chars());
+        let local = self.inner.local_char_pos();
+        let node_start = self.position() - local;
+        let here = unsafe { &*self.inner.here_ptr() };
+        // Positions at the very start of a node are left to cursor_at_char, so the cursor ends up
+        // at the end of the previous node like the edit functions expect.
+        if pos > node_start && pos <= node_start + here.num_chars() {
+            self.inner.move_within_node(self.height(), pos as isize - self.position() as isize);
+        } else {
+            self.inner = self.rope.cursor_at_char(pos, true);
+        }
+    }
+
+    /// Insert content at the cursor. The cursor is moved to the end of the inserted content.
+    pub fn insert(&mut self, content: &str) {
+        if content.is_empty() { return; }
+        let pos = self.position();
+        self.rope.record_insert(pos, content);
+        unsafe { self.rope.insert_at_cursor(&mut self.inner, content); }
+    }
+
+    /// Delete up to `len` characters after the cursor. The cursor doesn't move. Returns the number
+    /// of characters deleted, which is less than `len` at the end of the rope.
+    pub fn delete(&mut self, len: usize) -> usize {
+        let pos = self.position();
+        let len = len.min(self.rope.len_chars() - pos);
+        if len == 0 { return 0; }
+        self.rope.record_remove(pos..pos + len);
+        unsafe { self.rope.del_at_cursor(&mut self.inner, len); }
+        len
+    }
+
+    /// Delete up to `len` characters before the cursor, like pressing backspace. The cursor moves
+    /// back to the start of the deleted range. Returns the number of characters deleted, which is
+    /// less than `len` at the start of the rope.
+    pub fn backspace(&mut self, len: usize) -> usize {
+        let len = len.min(self.position());
+        self.seek_to_char(self.position() - len);
+        self.delete(len)
+    }
+}
+
+impl core::fmt::Debug for MutCursor<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MutCursor")
+            .field("position", &self.position())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::JumpRope;
+
+    #[test]
+    fn cursor_movement() {
+        let s = "κό𝕐𝕆😘σμε and some ascii\ntext which spans several nodes";
+        let rope = JumpRope::from(s);
+        let chars = s.chars().collect::<Vec<_>>();
+        let len = chars.len();
+
+        for start in 0..=len {
+            let mut cursor = rope.cursor(start);
+            assert_eq!(cursor.position(), start);
+            assert_eq!(cursor.byte_position(), rope.char_to_byte(start));
+            assert_eq!(cursor.chars_remaining(), len - start);
+
+            // Walk forwards to the end, and then all the way back.
+            for (i, &c) in chars.iter().enumerate().skip(start) {
+                assert_eq!(cursor.next_char(), Some(c));
+                assert_eq!(cursor.position(), i + 1);
+                assert_eq!(cursor.bytes_remaining(), s.len() - rope.char_to_byte(i + 1));
+            }
+            assert_eq!(cursor.next_char(), None);
+            for i in (0..len).rev() {
+                assert_eq!(cursor.prev_char(), Some(chars[i]));
+                assert_eq!(cursor.position(), i);
+                assert_eq!(cursor.byte_position(), rope.char_to_byte(i));
+            }
+            assert_eq!(cursor.prev_char(), None);
+
+            for pos in [start / 2, start, start + 1, len + 10] {
+                cursor.seek_to_char(pos);
+                assert_eq!(cursor.position(), pos.min(len));
+                assert_eq!(cursor.next_char(), chars.get(pos).copied());
+            }
+        }
+
+        let mut cursor = rope.cursor(3);
+        assert_eq!(core::iter::from_fn(|| cursor.next_char()).collect::<String>(), chars[3..].iter().collect::<String>());
+        assert_eq!(JumpRope::new().cursor(5).next_char(), None);
+    }
+
+    #[test]
+    fn mut_cursor_edits() {
+        let mut rng = SmallRng::seed_from_u64(12);
+        let mut rope = JumpRope::from("κό𝕐𝕆😘σμε and some ascii\ntext which spans several nodes");
+        let mut expected = rope.to_string();
+
+        let ops = rope.with_recording(|rope| {
+            let mut cursor = rope.mut_cursor(4);
+            for _ in 0..500 {
+                let pos = cursor.position();
+                let byte = |s: &str, pos: usize| s.char_indices().nth(pos).map_or(s.len(), |(b, _)| b);
+                match rng.gen_range(0..4) {
+                    0 => {
+                        cursor.insert("a𝕐\n");
+                        expected.insert_str(byte(&expected, pos), "a𝕐\n");
+                    }
+                    1 => {
+                        let n = cursor.delete(rng.gen_range(0..4));
+                        expected.replace_range(byte(&expected, pos)..byte(&expected, pos + n), "");
+                    }
+                    2 => {
+                        let n = cursor.backspace(rng.gen_range(0..4));
+                        expected.replace_range(byte(&expected, pos - n)..byte(&expected, pos), "");
+                    }
+                    _ => cursor.seek_to_char(pos.saturating_sub(3) + rng.gen_range(0..7)),
+                }
+                let pos = cursor.position();
+                assert!(pos <= cursor.rope().len_chars());
+            }
+        });
+        assert_eq!(rope, expected.as_str());
+        rope.check();
+
+        // The recorded operations replay the same edits.
+        let mut replay = JumpRope::from("κό𝕐𝕆😘σμε and some ascii\ntext which spans several nodes");
+        for op in &ops { op.apply(&mut replay); }
+        assert_eq!(replay, rope);
+    }
+}
@@ -0,0 +1,60 @@
+use core::fmt::{Display, Formatter};
+use core::ops::Range;
+
+/// The error returned when a character position is outside of a rope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PosError {
+    /// The offending character position.
+    pub pos: usize,
+    /// The length of the document (in characters) when the position was checked.
+    pub len_chars: usize,
+}
+
+impl PosError {
+    /// Create an error for position `pos` in a document `len_chars` characters long.
+    pub const fn new(pos: usize, len_chars: usize) -> Self {
+        Self { pos, len_chars }
+    }
+}
+
+impl Display for PosError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "character position {} is past the end of the document (length {})", self.pos, self.len_chars)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PosError {}
+
+/// The error returned when a character range is inverted or extends past the end of a rope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RangeError {
+    /// The start of the offending range.
+    pub start: usize,
+    /// The end of the offending range.
+    pub end: usize,
+    /// The length of the document (in characters) when the range was checked.
+    pub len_chars: usize,
+}
+
+impl RangeError {
+    /// Create an error for `range` in a document `len_chars` characters long.
+    pub const fn new(range: Range<usize>, len_chars: usize) -> Self {
+        Self { start: range.start, end: range.end, len_chars }
+    }
+}
+
+impl Display for RangeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.start > self.end {
+            write!(f, "character range {}..{} is inverted", self.start, self.end)
+        } else {
+            write!(f, "character range {}..{} extends past the end of the document (length {})", self.start, self.end, self.len_chars)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for RangeError {}
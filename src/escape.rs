@@ -0,0 +1,120 @@
+use crate::JumpRope;
+use crate::iter::StrContentIter;
+
+/// The escaping scheme used by [`JumpRope::escape_for`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Escaping {
+    /// Escape content for embedding inside a JSON string literal. Quotes, backslashes and control
+    /// characters are escaped. (The surrounding quotes are not added.)
+    Json,
+    /// Escape content for embedding in HTML text or attribute values.
+    Html,
+    /// Escape content for embedding in XML text or attribute values.
+    Xml,
+}
+
+const JSON_CONTROL: [&str; 32] = [
+    "\\u0000", "\\u0001", "\\u0002", "\\u0003", "\\u0004", "\\u0005", "\\u0006", "\\u0007",
+    "\\b", "\\t", "\\n", "\\u000b", "\\f", "\\r", "\\u000e", "\\u000f",
+    "\\u0010", "\\u0011", "\\u0012", "\\u0013", "\\u0014", "\\u0015", "\\u0016", "\\u0017",
+    "\\u0018", "\\u0019", "\\u001a", "\\u001b", "\\u001c", "\\u001d", "\\u001e", "\\u001f",
+];
+
+impl Escaping {
+    // All escaped characters are ASCII, so we can scan (and split) strings bytewise.
+    #[inline]
+    fn escape(self, b: u8) -> Option<&'static str> {
+        match (self, b) {
+            (Escaping::Json, b'"') => Some("\\\""),
+            (Escaping::Json, b'\\') => Some("\\\\"),
+            (Escaping::Json, 0..=0x1f) => Some(JSON_CONTROL[b as usize]),
+            (Escaping::Json, _) => None,
+
+            (_, b'&') => Some("&amp;"),
+            (_, b'<') => Some("&lt;"),
+            (_, b'>') => Some("&gt;"),
+            (_, b'"') => Some("&quot;"),
+            (Escaping::Html, b'\'') => Some("&#39;"),
+            (Escaping::Xml, b'\'') => Some("&apos;"),
+            _ => None,
+        }
+    }
+}
+
+/// An iterator over the escaped content of a rope. See [`JumpRope::escape_for`].
+pub struct Escaped<'a> {
+    chunks: StrContentIter<'a>,
+    current: &'a str,
+    escaping: Escaping,
+}
+
+impl<'a> Iterator for Escaped<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.current.is_empty() {
+            self.current = self.chunks.next()?;
+        }
+
+        let bytes = self.current.as_bytes();
+        if let Some(escaped) = self.escaping.escape(bytes[0]) {
+            self.current = &self.current[1..];
+            return Some(escaped);
+        }
+
+        let len = bytes.iter().position(|&b| self.escaping.escape(b).is_some())
+            .unwrap_or(bytes.len());
+        let (s, rest) = self.current.split_at(len);
+        self.current = rest;
+        Some(s)
+    }
+}
+
+impl JumpRope {
+    /// Iterate over the content of the rope with the specified escaping applied. The iterator
+    /// yields string pieces which concatenate to the escaped document. Unescaped runs of text are
+    /// borrowed directly from the rope, so the escaped document is never materialized.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("<a href=\"x\">Tom & Jerry</a>");
+    /// assert_eq!(rope.escape_for(Escaping::Html).collect::<String>(),
+    ///     "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&lt;/a&gt;");
+    ///
+    /// let rope = JumpRope::from("Say \"hi\"\n");
+    /// assert_eq!(rope.escape_for(Escaping::Json).collect::<String>(), "Say \\\"hi\\\"\\n");
+    /// ```
+    pub fn escape_for(&self, escaping: Escaping) -> Escaped<'_> {
+        Escaped {
+            chunks: self.chunks().strings(),
+            current: "",
+            escaping,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Escaping, JumpRope};
+
+    #[test]
+    fn escape_json() {
+        let rope = JumpRope::from("κό\"𝕐𝕆\\😘\u{1}\t\r\nσμε xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx'<>&");
+        assert_eq!(rope.escape_for(Escaping::Json).collect::<String>(),
+            "κό\\\"𝕐𝕆\\\\😘\\u0001\\t\\r\\nσμε xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx'<>&");
+        assert!(rope.escape_for(Escaping::Json).all(|s| !s.is_empty()));
+    }
+
+    #[test]
+    fn escape_markup() {
+        let rope = JumpRope::from("'κό' & \"σμε\" <xxxxxxxxxxxxxxxxxxxxxxxxxx>\n");
+        assert_eq!(rope.escape_for(Escaping::Html).collect::<String>(),
+            "&#39;κό&#39; &amp; &quot;σμε&quot; &lt;xxxxxxxxxxxxxxxxxxxxxxxxxx&gt;\n");
+        assert_eq!(rope.escape_for(Escaping::Xml).collect::<String>(),
+            "&apos;κό&apos; &amp; &quot;σμε&quot; &lt;xxxxxxxxxxxxxxxxxxxxxxxxxx&gt;\n");
+        assert_eq!(JumpRope::new().escape_for(Escaping::Xml).count(), 0);
+    }
+}
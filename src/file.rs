@@ -0,0 +1,705 @@
+// Loading documents from files and readers, and writing them back out. This is only compiled in with
+// the "std" feature.
+
+use std::fs::{self, File, OpenOptions};
+use std::ops::Range;
+use std::io::{self, IoSlice, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::JumpRope;
+
+// Files are read (and decoded) in blocks of this size, so we never hold a full copy of the file
+// in memory alongside the rope.
+const READ_SIZE: usize = 64 * 1024;
+
+// The maximum number of chunks passed to each write_vectored call. Most operating systems limit
+// this to 1024 (IOV_MAX).
+const MAX_IO_SLICES: usize = 1024;
+
+/// The text encoding of a loaded file. See [`JumpRope::from_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+/// The line ending style used in a loaded file. See [`JumpRope::from_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LineEnding {
+    /// Unix style `\n`.
+    Lf,
+    /// Windows style `\r\n`.
+    CrLf,
+    /// Classic Mac OS style `\r`.
+    Cr,
+    /// The file contains more than one style of line ending.
+    Mixed,
+}
+
+/// Information about the format of a loaded file, returned alongside the rope by
+/// [`JumpRope::from_file`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileInfo {
+    /// The detected encoding of the file.
+    pub encoding: Encoding,
+    /// True if the file started with a byte order mark. The BOM isn't included in the rope.
+    pub has_bom: bool,
+    /// The line endings used in the file, or `None` if the file doesn't contain any line breaks.
+    /// Line endings are loaded into the rope as-is.
+    pub line_ending: Option<LineEnding>,
+}
+
+impl FileInfo {
+    /// Describe a file with the specified format.
+    pub const fn new(encoding: Encoding, has_bom: bool, line_ending: Option<LineEnding>) -> Self {
+        Self { encoding, has_bom, line_ending }
+    }
+}
+
+#[derive(Default)]
+struct LineEndingCounter {
+    lf: usize,
+    crlf: usize,
+    cr: usize,
+    pending_cr: bool,
+}
+
+impl LineEndingCounter {
+    fn scan(&mut self, s: &str) {
+        for b in s.bytes() {
+            match b {
+                b'\r' => {
+                    if self.pending_cr { self.cr += 1; }
+                    self.pending_cr = true;
+                }
+                b'\n' if self.pending_cr => {
+                    self.crlf += 1;
+                    self.pending_cr = false;
+                }
+                b'\n' => self.lf += 1,
+                _ => {
+                    if self.pending_cr { self.cr += 1; }
+                    self.pending_cr = false;
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> Option<LineEnding> {
+        if self.pending_cr { self.cr += 1; }
+        match (self.lf > 0, self.crlf > 0, self.cr > 0) {
+            (false, false, false) => None,
+            (true, false, false) => Some(LineEnding::Lf),
+            (false, true, false) => Some(LineEnding::CrLf),
+            (false, false, true) => Some(LineEnding::Cr),
+            _ => Some(LineEnding::Mixed),
+        }
+    }
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn detect_bom(bytes: &[u8]) -> (Encoding, usize) {
+    match bytes {
+        [0xef, 0xbb, 0xbf, ..] => (Encoding::Utf8, 3),
+        [0xff, 0xfe, ..] => (Encoding::Utf16Le, 2),
+        [0xfe, 0xff, ..] => (Encoding::Utf16Be, 2),
+        _ => (Encoding::Utf8, 0),
+    }
+}
+
+fn bom_for(encoding: Encoding) -> &'static [u8] {
+    match encoding {
+        Encoding::Utf8 => &[0xef, 0xbb, 0xbf],
+        Encoding::Utf16Le => &[0xff, 0xfe],
+        Encoding::Utf16Be => &[0xfe, 0xff],
+    }
+}
+
+// Decode as much of bytes as possible into out, returning the number of bytes consumed. Unless
+// this is the end of the file, incomplete characters at the end are left for the next call.
+fn decode(encoding: Encoding, bytes: &[u8], eof: bool, out: &mut String) -> io::Result<usize> {
+    match encoding {
+        Encoding::Utf8 => match std::str::from_utf8(bytes) {
+            Ok(s) => {
+                out.push_str(s);
+                Ok(bytes.len())
+            }
+            Err(e) if e.error_len().is_none() && !eof => {
+                let valid = e.valid_up_to();
+                out.push_str(unsafe { std::str::from_utf8_unchecked(&bytes[..valid]) });
+                Ok(valid)
+            }
+            Err(_) => Err(invalid_data("file is not valid UTF-8")),
+        },
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            if eof && !bytes.len().is_multiple_of(2) { return Err(invalid_data("file is not valid UTF-16")); }
+
+            let mut units = bytes.chunks_exact(2).map(|b| match encoding {
+                Encoding::Utf16Le => u16::from_le_bytes([b[0], b[1]]),
+                _ => u16::from_be_bytes([b[0], b[1]]),
+            }).collect::<Vec<_>>();
+            // Leave a trailing high surrogate for the next block, as its pair hasn't been read yet.
+            if !eof && units.last().is_some_and(|u| (0xd800..0xdc00).contains(u)) { units.pop(); }
+
+            for c in char::decode_utf16(units.iter().copied()) {
+                out.push(c.map_err(|_| invalid_data("file is not valid UTF-16"))?);
+            }
+            Ok(units.len() * 2)
+        }
+    }
+}
+
+impl JumpRope {
+    /// Load a rope from the file at the specified path, detecting its encoding and line ending
+    /// style. See [`from_reader`](Self::from_reader) for details.
+    ///
+    /// This method is only available with the `std` feature enabled.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<(Self, FileInfo)> {
+        Self::from_reader(File::open(path)?)
+    }
+
+    /// Load a rope from the passed reader, detecting its encoding and line ending style.
+    ///
+    /// The content is read and decoded in blocks, so a full copy of the file is never held in
+    /// memory. Files starting with a UTF-16 byte order mark are decoded as UTF-16. Everything else
+    /// is loaded as UTF-8. Any byte order mark is removed. Returns an error of kind
+    /// [`InvalidData`](io::ErrorKind::InvalidData) if the content isn't valid in its encoding.
+    ///
+    /// This method is only available with the `std` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let bytes = b"\xef\xbb\xbfhi\r\nthere\r\n";
+    /// let (rope, info) = JumpRope::from_reader(&bytes[..]).unwrap();
+    /// assert_eq!(rope, "hi\r\nthere\r\n");
+    /// assert_eq!(info, FileInfo::new(Encoding::Utf8, true, Some(LineEnding::CrLf)));
+    /// ```
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<(Self, FileInfo)> {
+        let mut rope = Self::new();
+        let mut bytes = Vec::with_capacity(READ_SIZE);
+        let mut text = String::new();
+        let mut line_endings = LineEndingCounter::default();
+        let mut detected = None;
+
+        loop {
+            let eof = reader.by_ref().take(READ_SIZE as u64).read_to_end(&mut bytes)? == 0;
+
+            let (encoding, _) = *match &mut detected {
+                Some(d) => d,
+                None => {
+                    // Wait until we have enough bytes to check for a BOM.
+                    if bytes.len() < 3 && !eof { continue; }
+                    let (encoding, bom_len) = detect_bom(&bytes);
+                    bytes.drain(..bom_len);
+                    detected.insert((encoding, bom_len > 0))
+                }
+            };
+
+            let consumed = decode(encoding, &bytes, eof, &mut text)?;
+            bytes.drain(..consumed);
+            line_endings.scan(&text);
+            rope.insert(rope.len_chars(), &text);
+            text.clear();
+
+            if eof { break; }
+        }
+
+        let (encoding, has_bom) = detected.unwrap();
+        Ok((rope, FileInfo { encoding, has_bom, line_ending: line_endings.finish() }))
+    }
+
+    // Decode bytes in the specified encoding into a new rope, a block at a time.
+    #[cfg(feature = "encodings")]
+    fn from_encoded_bytes(encoding: Encoding, bytes: &[u8]) -> io::Result<Self> {
+        let mut rope = Self::new();
+        let mut cursor = rope.cursor_at_start();
+        let mut text = String::new();
+        let mut pos = 0;
+        loop {
+            let end = bytes.len().min(pos + READ_SIZE);
+            let eof = end == bytes.len();
+            pos += decode(encoding, &bytes[pos..end], eof, &mut text)?;
+            unsafe { rope.insert_at_cursor(&mut cursor, &text); }
+            text.clear();
+            if eof { return Ok(rope); }
+        }
+    }
+
+    /// Create a rope from UTF-16 little endian bytes. The content is decoded in blocks straight
+    /// into the rope, without building a transcoded copy first. A byte order mark at the start is
+    /// loaded as-is (as `'\u{feff}'`).
+    ///
+    /// Returns an error of kind [`InvalidData`](io::ErrorKind::InvalidData) if the content isn't
+    /// valid UTF-16.
+    ///
+    /// This method is only available with the `encodings` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from_utf16le_bytes(b"h\0i\0").unwrap();
+    /// assert_eq!(rope, "hi");
+    /// ```
+    #[cfg(feature = "encodings")]
+    pub fn from_utf16le_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::from_encoded_bytes(Encoding::Utf16Le, bytes)
+    }
+
+    /// Create a rope from UTF-16 big endian bytes. See
+    /// [`from_utf16le_bytes`](Self::from_utf16le_bytes).
+    ///
+    /// This method is only available with the `encodings` feature enabled.
+    #[cfg(feature = "encodings")]
+    pub fn from_utf16be_bytes(bytes: &[u8]) -> io::Result<Self> {
+        Self::from_encoded_bytes(Encoding::Utf16Be, bytes)
+    }
+
+    /// Create a rope from Latin-1 (ISO 8859-1) bytes. Every byte is a valid Latin-1 character, so
+    /// this can't fail.
+    ///
+    /// This method is only available with the `encodings` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from_latin1_bytes(b"caf\xe9");
+    /// assert_eq!(rope, "café");
+    /// ```
+    #[cfg(feature = "encodings")]
+    pub fn from_latin1_bytes(bytes: &[u8]) -> Self {
+        let mut rope = Self::new();
+        let mut cursor = rope.cursor_at_start();
+        let mut text = String::new();
+        for block in bytes.chunks(READ_SIZE) {
+            text.clear();
+            text.extend(block.iter().map(|&b| b as char));
+            unsafe { rope.insert_at_cursor(&mut cursor, &text); }
+        }
+        rope
+    }
+
+    // Encode the rope's content a chunk at a time into a buffer, and write the buffer out whenever
+    // it fills up.
+    fn write_encoded<W: Write + ?Sized, F: FnMut(&str, &mut Vec<u8>)>(&self, writer: &mut W, mut encode: F) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(READ_SIZE);
+        for (s, _) in self.chunks() {
+            encode(s, &mut buf);
+            if buf.len() >= READ_SIZE {
+                writer.write_all(&buf)?;
+                buf.clear();
+            }
+        }
+        writer.write_all(&buf)
+    }
+
+    /// Write the rope's content to the passed writer, encoded as UTF-16 little endian. No byte
+    /// order mark is written. The content is encoded a block at a time, so a full transcoded copy
+    /// of the document is never held in memory.
+    ///
+    /// This method is only available with the `encodings` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut out = Vec::new();
+    /// JumpRope::from("hi").write_as_utf16le(&mut out).unwrap();
+    /// assert_eq!(out, b"h\0i\0");
+    /// ```
+    #[cfg(feature = "encodings")]
+    pub fn write_as_utf16le<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_utf16(writer, false)
+    }
+
+    fn write_utf16<W: Write + ?Sized>(&self, writer: &mut W, big_endian: bool) -> io::Result<()> {
+        self.write_encoded(writer, |s, buf| {
+            if big_endian {
+                buf.extend(s.encode_utf16().flat_map(u16::to_be_bytes));
+            } else {
+                buf.extend(s.encode_utf16().flat_map(u16::to_le_bytes));
+            }
+        })
+    }
+
+    /// Write the rope's content to the passed writer, encoded as UTF-16 big endian. See
+    /// [`write_as_utf16le`](Self::write_as_utf16le).
+    ///
+    /// This method is only available with the `encodings` feature enabled.
+    #[cfg(feature = "encodings")]
+    pub fn write_as_utf16be<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_utf16(writer, true)
+    }
+
+    /// Write the rope's content to the passed writer, encoded as Latin-1 (ISO 8859-1). Characters
+    /// which can't be represented in Latin-1 are written as `'?'`.
+    ///
+    /// This method is only available with the `encodings` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut out = Vec::new();
+    /// JumpRope::from("café κ").write_as_latin1_lossy(&mut out).unwrap();
+    /// assert_eq!(out, b"caf\xe9 ?");
+    /// ```
+    #[cfg(feature = "encodings")]
+    pub fn write_as_latin1_lossy<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        self.write_encoded(writer, |s, buf| {
+            buf.extend(s.chars().map(|c| u8::try_from(c).unwrap_or(b'?')));
+        })
+    }
+
+    /// Save the rope's content to the file at the specified path, as UTF-8.
+    ///
+    /// The content is streamed to a temporary file in the same directory, which is flushed to
+    /// disk and then renamed over the destination. Readers (and crashes) will see either the old
+    /// file or the new file in full, never a partially written file. If the destination already
+    /// exists, its permissions are copied to the new file. On error the temporary file is
+    /// removed, and the destination is left untouched.
+    ///
+    /// This method is only available with the `std` feature enabled.
+    pub fn save_atomic<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        self.save_atomic_with_info(path, FileInfo::new(Encoding::Utf8, false, None))
+    }
+
+    /// Save the rope's content to the file at the specified path, in the format described by
+    /// `info`. This is usually the [`FileInfo`] returned when the file was loaded, so unchanged
+    /// documents are saved byte for byte identical to the original file. The file is written
+    /// atomically, like [`save_atomic`](Self::save_atomic).
+    ///
+    /// See [`write_with_info`](Self::write_with_info) for details.
+    ///
+    /// This method is only available with the `std` feature enabled.
+    pub fn save_atomic_with_info<P: AsRef<Path>>(&self, path: P, info: FileInfo) -> io::Result<()> {
+        let path = path.as_ref();
+        let tmp_path = temp_path_for(path)?;
+        let file = OpenOptions::new().write(true).create_new(true).open(&tmp_path)?;
+        // The temporary file is ours from here on, so it's removed if anything else fails.
+        let result = self.write_temp_file(path, file, info)
+            .and_then(|_| fs::rename(&tmp_path, path));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result?;
+
+        // Flush the rename itself to disk.
+        #[cfg(unix)]
+        if let Some(dir) = path.parent() {
+            let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    }
+
+    fn write_temp_file(&self, path: &Path, mut file: File, info: FileInfo) -> io::Result<()> {
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+
+        self.write_with_info(&mut file, info)?;
+        file.sync_all()
+    }
+
+    /// Write the rope's content to the passed writer in the format described by `info`. The
+    /// content is encoded with `info.encoding`, and a byte order mark is written first if
+    /// `info.has_bom` is set. Line endings are written as they are in the rope, so
+    /// `info.line_ending` is ignored.
+    ///
+    /// Passing the [`FileInfo`] returned by [`from_reader`](Self::from_reader) writes an unchanged
+    /// document back out byte for byte identical to what was read.
+    ///
+    /// This method is only available with the `std` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let bytes = b"\xef\xbb\xbfhi\r\n";
+    /// let (rope, info) = JumpRope::from_reader(&bytes[..]).unwrap();
+    /// assert!(info.has_bom);
+    ///
+    /// let mut out = Vec::new();
+    /// rope.write_with_info(&mut out, info).unwrap();
+    /// assert_eq!(out, bytes);
+    /// ```
+    pub fn write_with_info<W: Write + ?Sized>(&self, writer: &mut W, info: FileInfo) -> io::Result<()> {
+        if info.has_bom {
+            writer.write_all(bom_for(info.encoding))?;
+        }
+        match info.encoding {
+            Encoding::Utf8 => self.write_vectored_to(writer),
+            Encoding::Utf16Le => self.write_utf16(writer, false),
+            Encoding::Utf16Be => self.write_utf16(writer, true),
+        }
+    }
+
+    /// Write the rope's content to the passed writer, as UTF-8. The rope's chunks are passed to
+    /// [`Write::write_vectored`] in batches, so large documents are written with few system calls
+    /// and without copying the content into an intermediate buffer.
+    ///
+    /// Like [`Write::write_all`], this keeps writing until the whole document is written, retrying
+    /// partial writes and interrupted calls.
+    ///
+    /// This method is only available with the `std` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("Some large text document");
+    /// let mut out = Vec::new();
+    /// rope.write_vectored_to(&mut out).unwrap();
+    /// assert_eq!(out, b"Some large text document");
+    /// ```
+    pub fn write_vectored_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        write_all_vectored(writer, self.chunks().strings())
+    }
+
+    /// Write the content in a range of characters to the passed writer, as UTF-8. Only the chunks
+    /// covering the range are visited, and they are written directly (like
+    /// [`write_vectored_to`](Self::write_vectored_to)) without copying them into an intermediate
+    /// buffer. The range is clamped to the document.
+    ///
+    /// This is useful for saving a selection, or serving part of a document.
+    ///
+    /// This method is only available with the `std` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxκόσμεxxx");
+    /// let mut out = Vec::new();
+    /// rope.write_range_to(&mut out, 3..8).unwrap();
+    /// assert_eq!(out, "κόσμε".as_bytes());
+    /// ```
+    pub fn write_range_to<W: Write + ?Sized>(&self, writer: &mut W, range: Range<usize>) -> io::Result<()> {
+        write_all_vectored(writer, self.chunks_in_range(range))
+    }
+}
+
+// Write all of the passed chunks to the writer, batching them into write_vectored calls.
+fn write_all_vectored<'a, W: Write + ?Sized, I: Iterator<Item = &'a str>>(writer: &mut W, chunks: I) -> io::Result<()> {
+    let mut chunks = chunks.map(|s| IoSlice::new(s.as_bytes()));
+    let mut slices = Vec::with_capacity(MAX_IO_SLICES);
+    loop {
+        slices.clear();
+        slices.extend(chunks.by_ref().take(MAX_IO_SLICES));
+        if slices.is_empty() { return Ok(()); }
+
+        let mut bufs = &mut slices[..];
+        // Skip any empty slices at the start.
+        IoSlice::advance_slices(&mut bufs, 0);
+        while !bufs.is_empty() {
+            match writer.write_vectored(bufs) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::WriteZero, "failed to write whole document")),
+                Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+// The temporary file lives next to the destination, as rename() can't move files between
+// filesystems. The counter keeps the names unique when threads save to the same path at once.
+fn temp_path_for(path: &Path) -> io::Result<PathBuf> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "save path does not name a file")
+    })?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(format!(".{}.{}.tmp", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    Ok(path.with_file_name(tmp_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Write};
+    use crate::{Encoding, FileInfo, JumpRope, LineEnding};
+
+    // A reader which returns one byte per read, to split characters across blocks.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() { return Ok(0); }
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    fn check(bytes: &[u8], expected: &str, info: FileInfo) {
+        let (rope, i) = JumpRope::from_reader(bytes).unwrap();
+        assert_eq!(rope, expected);
+        assert_eq!(i, info);
+        rope.check();
+
+        let (rope, i) = JumpRope::from_reader(Trickle(bytes)).unwrap();
+        assert_eq!(rope, expected);
+        assert_eq!(i, info);
+
+        // Saving with the detected format round trips the file.
+        let mut out = Vec::new();
+        rope.write_with_info(&mut out, info).unwrap();
+        assert_eq!(out, bytes);
+    }
+
+    #[test]
+    fn load_encodings() {
+        let s = "κό𝕐𝕆😘σμε\nsome ascii text\r\n";
+        let mixed = FileInfo { encoding: Encoding::Utf8, has_bom: false, line_ending: Some(LineEnding::Mixed) };
+        check(s.as_bytes(), s, mixed);
+        check(b"", "", FileInfo { line_ending: None, ..mixed });
+        check(b"a\rb\r", "a\rb\r", FileInfo { line_ending: Some(LineEnding::Cr), ..mixed });
+
+        let bom = [&[0xef, 0xbb, 0xbf], s.as_bytes()].concat();
+        check(&bom, s, FileInfo { has_bom: true, ..mixed });
+
+        let le = [0xff, 0xfe].into_iter().chain(s.encode_utf16().flat_map(u16::to_le_bytes)).collect::<Vec<_>>();
+        check(&le, s, FileInfo { encoding: Encoding::Utf16Le, has_bom: true, ..mixed });
+
+        let be = [0xfe, 0xff].into_iter().chain(s.encode_utf16().flat_map(u16::to_be_bytes)).collect::<Vec<_>>();
+        check(&be, s, FileInfo { encoding: Encoding::Utf16Be, has_bom: true, ..mixed });
+    }
+
+    #[test]
+    fn invalid_content() {
+        for bytes in [&b"abc\xff"[..], b"\xce", b"\xff\xfea", b"\xff\xfe\x00\xd8"] {
+            let err = JumpRope::from_reader(bytes).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn load_file() {
+        let path = std::env::temp_dir().join(format!("jumprope-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "hi\nthere\n").unwrap();
+        let (rope, info) = JumpRope::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(rope, "hi\nthere\n");
+        assert_eq!(info.line_ending, Some(LineEnding::Lf));
+    }
+
+    // A writer which writes at most 3 bytes at a time, and fails with Interrupted every other call.
+    struct Dribble(Vec<u8>, bool);
+
+    impl Write for Dribble {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1 = !self.1;
+            if self.1 { return Err(io::ErrorKind::Interrupted.into()); }
+            let n = buf.len().min(3);
+            self.0.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn write_vectored() {
+        for s in ["", "κό𝕐𝕆😘σμε and some ascii text which spans several nodes"] {
+            let rope = JumpRope::from(s);
+            let mut out = Vec::new();
+            rope.write_vectored_to(&mut out).unwrap();
+            assert_eq!(out, s.as_bytes());
+
+            let mut out = Dribble(Vec::new(), false);
+            rope.write_vectored_to(&mut out).unwrap();
+            assert_eq!(out.0, s.as_bytes());
+
+            for start in 0..rope.len_chars() {
+                let mut out = Dribble(Vec::new(), false);
+                rope.write_range_to(&mut out, start..start + 15).unwrap();
+                assert_eq!(out.0, rope.substring(start..start + 15).as_bytes());
+            }
+        }
+    }
+
+    #[cfg(feature = "encodings")]
+    #[test]
+    fn transcoding() {
+        let s = "κό𝕐𝕆😘σμε café and some ascii text\n".repeat(3000);
+        let utf16 = s.encode_utf16().collect::<Vec<_>>();
+        let le = utf16.iter().flat_map(|u| u.to_le_bytes()).collect::<Vec<_>>();
+        let be = utf16.iter().flat_map(|u| u.to_be_bytes()).collect::<Vec<_>>();
+
+        let rope = JumpRope::from_utf16le_bytes(&le).unwrap();
+        assert_eq!(rope, s.as_str());
+        rope.check();
+        assert_eq!(JumpRope::from_utf16be_bytes(&be).unwrap(), s.as_str());
+        assert_eq!(JumpRope::from_utf16le_bytes(&le[..le.len() - 1]).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        // A lone surrogate.
+        assert!(JumpRope::from_utf16le_bytes(&[0x00, 0xd8, b'a', 0]).is_err());
+
+        let mut out = Vec::new();
+        rope.write_as_utf16le(&mut out).unwrap();
+        assert_eq!(out, le);
+        let mut out = Vec::new();
+        rope.write_as_utf16be(&mut out).unwrap();
+        assert_eq!(out, be);
+
+        let latin1 = (0..=255u8).collect::<Vec<_>>();
+        let rope = JumpRope::from_latin1_bytes(&latin1);
+        assert_eq!(rope.len_chars(), 256);
+        let mut out = Vec::new();
+        rope.write_as_latin1_lossy(&mut out).unwrap();
+        assert_eq!(out, latin1);
+    }
+
+    #[test]
+    fn save_atomic() {
+        let dir = std::env::temp_dir().join(format!("jumprope-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("doc.txt");
+        std::fs::write(&path, "old content").unwrap();
+
+        let rope = JumpRope::from("κόσμε\n".repeat(100));
+        rope.save_atomic(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), rope.to_string());
+
+        let info = FileInfo::new(Encoding::Utf16Le, true, Some(LineEnding::Lf));
+        rope.save_atomic_with_info(&path, info).unwrap();
+        assert_eq!(JumpRope::from_file(&path).unwrap(), (rope, info));
+
+        // Only the saved file is left behind.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_atomic_from_threads() {
+        let dir = std::env::temp_dir().join(format!("jumprope-save-threads-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("doc.txt");
+
+        let content = |i: usize| format!("{}\n", i).repeat(10_000);
+        std::thread::scope(|s| {
+            for i in 0..8 {
+                let path = &path;
+                s.spawn(move || JumpRope::from(content(i)).save_atomic(path).unwrap());
+            }
+        });
+
+        // One of the saves wins, and none of the temporary files are left behind.
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!((0..8).any(|i| saved == content(i)));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,16 @@
 
+use core::fmt::{Display, Formatter};
+use core::ops::Range;
+use alloc::string::{String, ToString};
 use crate::utils::*;
 
+/// A fixed capacity [gap buffer](https://en.wikipedia.org/wiki/Gap_buffer) of UTF8 text. Each
+/// node in a [`JumpRope`](crate::JumpRope) stores its content in one of these, but it can also be
+/// used standalone for small strings which are edited at a moving location.
+///
+/// The content is stored in two halves - before and after the gap. Positions are byte offsets
+/// unless otherwise noted. Like `String`, methods which take a byte offset panic if it isn't on a
+/// character boundary, so the content is always valid UTF-8.
 #[derive(Debug, Clone, Eq)]
 pub struct GapBuffer<const LEN: usize> {
     data: [u8; LEN],
@@ -11,30 +21,141 @@ pub struct GapBuffer<const LEN: usize> {
     all_ascii: bool,
 }
 
+/// The error returned when inserting content into a [`GapBuffer`] which doesn't have enough room
+/// for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CapacityError {
+    /// The number of bytes which were being inserted.
+    pub needed: usize,
+    /// The number of free bytes in the gap buffer.
+    pub available: usize,
+}
+
+impl CapacityError {
+    /// Create an error for an insert of `needed` bytes into a buffer with `available` bytes free.
+    pub const fn new(needed: usize, available: usize) -> Self {
+        Self { needed, available }
+    }
+}
+
+impl Display for CapacityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "insufficient space in gap buffer (needed {} bytes, {} available)", self.needed, self.available)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CapacityError {}
+
+// The buffer's content is only ever written from &str (or ASCII bytes in map_ascii_bytes), so it's
+// always valid UTF-8. Validating it on every read made debug builds painfully slow, so that's only
+// done with the paranoid feature. check() validates the content regardless.
 #[inline]
 unsafe fn slice_to_str(arr: &[u8]) -> &str {
-    if cfg!(debug_assertions) {
-        std::str::from_utf8(arr).unwrap()
+    if cfg!(feature = "paranoid") {
+        core::str::from_utf8(arr).unwrap()
+    } else {
+        core::str::from_utf8_unchecked(arr)
+    }
+}
+
+// The rope keeps the u16 fields of a gap buffer in range, so arithmetic on them can't overflow
+// unless there's a bug. With the checked-math feature every update is checked against the buffer's
+// capacity, so a bug panics with a description of the bad update instead of silently wrapping and
+// corrupting the buffer.
+#[inline]
+fn add_u16<const LEN: usize>(field: &str, a: u16, b: usize) -> u16 {
+    if cfg!(feature = "checked-math") {
+        match (a as usize).checked_add(b) {
+            Some(v) if v <= LEN => v as u16,
+            _ => math_error(field, LEN, a, '+', b),
+        }
+    } else {
+        a + b as u16
+    }
+}
+
+#[inline]
+fn sub_u16<const LEN: usize>(field: &str, a: u16, b: usize) -> u16 {
+    if cfg!(feature = "checked-math") {
+        match (a as usize).checked_sub(b) {
+            Some(v) => v as u16,
+            None => math_error(field, LEN, a, '-', b),
+        }
+    } else {
+        a - b as u16
+    }
+}
+
+#[inline]
+fn to_u16<const LEN: usize>(field: &str, v: usize) -> u16 {
+    if cfg!(feature = "checked-math") && v > LEN.min(u16::MAX as usize) {
+        math_error(field, LEN, 0, '+', v)
     } else {
-        std::str::from_utf8_unchecked(arr)
+        v as u16
     }
 }
 
+#[cold]
+#[inline(never)]
+fn math_error(field: &str, len: usize, a: u16, op: char, b: usize) -> ! {
+    panic!("GapBuffer<{}> {} out of range: {} {} {}", len, field, a, op, b)
+}
+
 impl<const LEN: usize> GapBuffer<LEN> {
     pub fn new() -> Self {
         Self {
             data: [0; LEN],
             gap_start_bytes: 0,
             gap_start_chars: 0,
-            gap_len: LEN as u16,
+            gap_len: to_u16::<LEN>("gap_len", LEN),
             all_ascii: true,
         }
     }
 
+    /// Create a new gap buffer containing the passed string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the string is longer than LEN bytes. Use
+    /// [`try_new_from_str`](Self::try_new_from_str) or
+    /// [`new_from_str_truncated`](Self::new_from_str_truncated) for untrusted input.
     pub fn new_from_str(s: &str) -> Self {
+        Self::try_new_from_str(s).unwrap()
+    }
+
+    /// Create a new gap buffer containing the passed string, or return an error if the string
+    /// doesn't fit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::GapBuffer;
+    /// assert!(GapBuffer::<5>::try_new_from_str("hi").is_ok());
+    /// assert!(GapBuffer::<5>::try_new_from_str("hi there").is_err());
+    /// ```
+    pub fn try_new_from_str(s: &str) -> Result<Self, CapacityError> {
         let mut val = Self::new();
-        val.try_insert(0, s).unwrap();
-        val
+        val.try_insert(0, s)?;
+        Ok(val)
+    }
+
+    /// Create a new gap buffer containing as much of the passed string as will fit. The string is
+    /// only ever split on a character boundary. The rest of the string (which didn't fit) is
+    /// returned alongside the buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::GapBuffer;
+    /// let (buf, rest) = GapBuffer::<5>::new_from_str_truncated("hi there");
+    /// assert_eq!(buf.to_string(), "hi th");
+    /// assert_eq!(rest, "ere");
+    /// ```
+    pub fn new_from_str_truncated(s: &str) -> (Self, &str) {
+        let (start, rest) = s.split_at(floor_char_boundary(s, LEN));
+        (Self::new_from_str(start), rest)
     }
 
     #[allow(unused)]
@@ -67,25 +188,45 @@ impl<const LEN: usize> GapBuffer<LEN> {
         if self.all_ascii { char_len } else { str_chars_to_bytes_rev(s, char_len) }
     }
 
+    // The index in data of the first byte after the gap.
+    #[inline]
+    fn gap_end(&self) -> usize {
+        self.gap_start_bytes as usize + self.gap_len as usize
+    }
+
+    // A buffer stays marked as non-ASCII after its multi-byte characters are removed, which slows
+    // down counting. Whenever the gap is at the end of the content, the character count before the
+    // gap tells us for free whether everything is ASCII again.
+    fn recheck_ascii(&mut self) {
+        if !self.all_ascii && self.gap_end() == LEN {
+            self.all_ascii = self.gap_start_bytes == self.gap_start_chars;
+        }
+    }
+
+    /// Move the gap to the byte offset `new_start` in the content.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `new_start` is past the end of the content, or inside a character.
     pub fn move_gap(&mut self, new_start: usize) {
         let current_start = self.gap_start_bytes as usize;
 
         if new_start != current_start {
+            assert!(self.is_char_boundary(new_start), "GapBuffer position {} is not a character boundary", new_start);
             let len = self.gap_len as usize;
-            debug_assert!(new_start <= LEN-len);
 
             if new_start < current_start {
                 // move characters to the right.
                 let moved_chars = new_start..current_start;
                 let char_len = self.count_internal_chars(unsafe { slice_to_str(&self.data[moved_chars.clone()]) });
-                self.gap_start_chars -= char_len as u16;
+                self.gap_start_chars = sub_u16::<LEN>("gap_start_chars", self.gap_start_chars, char_len);
 
                 self.data.copy_within(moved_chars, new_start + len);
             } else if current_start < new_start {
                 // Move characters to the left
                 let moved_chars = current_start+len..new_start+len;
                 let char_len = self.count_internal_chars(unsafe { slice_to_str(&self.data[moved_chars.clone()]) });
-                self.gap_start_chars += char_len as u16;
+                self.gap_start_chars = add_u16::<LEN>("gap_start_chars", self.gap_start_chars, char_len);
 
                 self.data.copy_within(moved_chars, current_start);
             }
@@ -95,30 +236,62 @@ impl<const LEN: usize> GapBuffer<LEN> {
                 self.data[new_start..new_start+len].fill(0);
             }
 
-            self.gap_start_bytes = new_start as u16;
+            self.gap_start_bytes = to_u16::<LEN>("gap_start_bytes", new_start);
+            self.recheck_ascii();
+        }
+    }
+
+    /// Move the gap to the start of the buffer, so all content is after the gap.
+    ///
+    /// This is cheaper than calling `move_gap(0)` because the characters don't need to be counted.
+    pub fn gap_to_start(&mut self) {
+        let start = self.gap_start_bytes as usize;
+        if start == 0 { return; }
+
+        let len = self.gap_len as usize;
+        self.data.copy_within(0..start, len);
+        if cfg!(debug_assertions) {
+            self.data[0..len].fill(0);
         }
+        self.gap_start_bytes = 0;
+        self.gap_start_chars = 0;
+    }
+
+    /// Move the gap to the end of the buffer, so all content is before the gap. After calling this,
+    /// [`start_as_str`](Self::start_as_str) returns the entire content of the buffer.
+    pub fn gap_to_end(&mut self) {
+        self.move_gap(self.len_bytes());
     }
 
-    /// Panics if there's no room.
-    pub fn insert_in_gap(&mut self, s: &str) {
+    // Panics if there's no room. The gap is always on a character boundary, so this keeps the
+    // content valid.
+    pub(crate) fn insert_in_gap(&mut self, s: &str) {
         let len = s.len();
         let char_len = count_chars(s);
         assert!(len <= self.gap_len as usize);
 
         let start = self.gap_start_bytes as usize;
         self.data[start..start+len].copy_from_slice(s.as_bytes());
-        self.gap_start_bytes += len as u16;
-        self.gap_start_chars += char_len as u16;
-        self.gap_len -= len as u16;
+        self.gap_start_bytes = add_u16::<LEN>("gap_start_bytes", self.gap_start_bytes, len);
+        self.gap_start_chars = add_u16::<LEN>("gap_start_chars", self.gap_start_chars, char_len);
+        self.gap_len = sub_u16::<LEN>("gap_len", self.gap_len, len);
 
         if len != char_len { self.all_ascii = false; }
     }
 
-    pub fn try_insert(&mut self, byte_pos: usize, s: &str) -> Result<(), ()> {
+    /// Insert s at the specified byte position, or return an error if there isn't enough space.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_pos` is past the end of the content, or inside a character.
+    pub fn try_insert(&mut self, byte_pos: usize, s: &str) -> Result<(), CapacityError> {
         let len = s.len();
         if len > self.gap_len as usize {
             // No space in this node!
-            Result::Err(())
+            Result::Err(CapacityError {
+                needed: len,
+                available: self.gap_len as usize,
+            })
         } else {
             self.move_gap(byte_pos);
             self.insert_in_gap(s);
@@ -126,23 +299,29 @@ impl<const LEN: usize> GapBuffer<LEN> {
         }
     }
 
-    pub fn remove_at_gap(&mut self, del_len: usize) {
+    // The caller must make sure del_len bytes after the gap end on a character boundary.
+    pub(crate) fn remove_at_gap(&mut self, del_len: usize) {
         if cfg!(debug_assertions) {
             // Zero out the deleted bytes in debug mode.
-            self.data[
-                (self.gap_start_bytes +self.gap_len) as usize..(self.gap_start_bytes +self.gap_len) as usize + del_len
-                ].fill(0);
+            let gap_end = self.gap_end();
+            self.data[gap_end..gap_end + del_len].fill(0);
         }
-        self.gap_len += del_len as u16;
+        self.gap_len = add_u16::<LEN>("gap_len", self.gap_len, del_len);
+        self.recheck_ascii();
     }
 
-    // Returns the number of items actually removed.
-    #[allow(unused)]
+    /// Remove `del_len` bytes starting at the byte offset `pos`. The range is clamped to the
+    /// content. Returns the number of bytes actually removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either end of the removed range is inside a character.
     pub fn remove(&mut self, pos: usize, del_len: usize) -> usize {
         let len = self.len_bytes();
 
         if pos >= len { return 0; }
         let del_len = del_len.min(len - pos);
+        assert!(self.is_char_boundary(pos + del_len), "GapBuffer position {} is not a character boundary", pos + del_len);
 
         self.move_gap(pos);
 
@@ -150,7 +329,7 @@ impl<const LEN: usize> GapBuffer<LEN> {
         del_len
     }
 
-    pub fn remove_chars(&mut self, pos: usize, mut del_len: usize) -> usize {
+    pub(crate) fn remove_chars(&mut self, pos: usize, mut del_len: usize) -> usize {
         // This function is longer than it needs to be; but having it be a bit longer makes the
         // code faster. I think the trade-off is worth it.
         // self.move_gap(self.count_bytes(pos));
@@ -169,14 +348,17 @@ impl<const LEN: usize> GapBuffer<LEN> {
                 // TODO: It would be better to count backwards here.
                 // let pos_bytes = str_get_byte_offset(self.start_as_str(), pos) as u16;
                 // rm_start_bytes = self.gap_start_bytes - pos_bytes;
-                rm_start_bytes = self.int_chars_to_bytes_backwards(self.start_as_str(), gap_chars - pos) as u16;
+                rm_start_bytes = self.int_chars_to_bytes_backwards(self.start_as_str(), gap_chars - pos);
 
                 del_len -= self.gap_start_chars as usize - pos;
-                self.gap_len += rm_start_bytes;
-                self.gap_start_chars = pos as u16;
-                self.gap_start_bytes -= rm_start_bytes;
+                self.gap_len = add_u16::<LEN>("gap_len", self.gap_len, rm_start_bytes);
+                self.gap_start_chars = to_u16::<LEN>("gap_start_chars", pos);
+                self.gap_start_bytes = sub_u16::<LEN>("gap_start_bytes", self.gap_start_bytes, rm_start_bytes);
                 // self.gap_start_bytes = pos_bytes;
-                if del_len == 0 { return rm_start_bytes as usize; }
+                if del_len == 0 {
+                    self.recheck_ascii();
+                    return rm_start_bytes;
+                }
             }
 
             debug_assert!(del_len > 0);
@@ -195,7 +377,78 @@ impl<const LEN: usize> GapBuffer<LEN> {
         // At this point the gap is guaranteed to be directly after pos.
         let rm_end_bytes = self.int_str_get_byte_offset(self.end_as_str(), del_len);
         self.remove_at_gap(rm_end_bytes);
-        rm_start_bytes as usize + rm_end_bytes
+        rm_start_bytes + rm_end_bytes
+    }
+
+    // Get the index in data of the character at char_pos. The character must exist.
+    fn char_data_idx(&self, char_pos: usize) -> usize {
+        let byte_pos = self.count_bytes(char_pos);
+        if byte_pos < self.gap_start_bytes as usize { byte_pos } else { byte_pos + self.gap_len as usize }
+    }
+
+    /// Get the number of bytes used by the character at char_pos.
+    pub fn char_len_bytes_at(&self, char_pos: usize) -> usize {
+        utf8_char_width(self.data[self.char_data_idx(char_pos)])
+    }
+
+    // Overwrite the character at char_pos with c. The existing character must use exactly as many
+    // bytes as c.
+    pub(crate) fn overwrite_char(&mut self, char_pos: usize, c: char) {
+        let idx = self.char_data_idx(char_pos);
+        let len = c.len_utf8();
+        assert_eq!(utf8_char_width(self.data[idx]), len);
+
+        c.encode_utf8(&mut self.data[idx..idx + len]);
+        if len > 1 { self.all_ascii = false; }
+    }
+
+    /// Replace each ASCII byte in the specified character range with `f(byte)`. Non-ASCII
+    /// characters are left untouched. The content is modified in place, without moving the gap.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` maps an ASCII byte to a non-ASCII byte, as that would corrupt the UTF8
+    /// content.
+    pub fn map_ascii_bytes<F: FnMut(u8) -> u8>(&mut self, char_range: Range<usize>, mut f: F) {
+        let start = self.count_bytes(char_range.start);
+        let end = self.count_bytes(char_range.end);
+        let gap_start = self.gap_start_bytes as usize;
+        let gap_len = self.gap_len as usize;
+
+        let (before, after) = self.data.split_at_mut(gap_start + gap_len);
+        let before_gap = &mut before[start.min(gap_start)..end.min(gap_start)];
+        let after_gap = &mut after[start.max(gap_start) - gap_start..end.max(gap_start) - gap_start];
+        for b in before_gap.iter_mut().chain(after_gap.iter_mut()) {
+            if b.is_ascii() {
+                let mapped = f(*b);
+                assert!(mapped.is_ascii(), "map_ascii_bytes mapped an ASCII byte to non-ASCII");
+                *b = mapped;
+            }
+        }
+    }
+
+    // The bytes of the content in the specified character range, skipping the gap.
+    fn bytes_in(&self, char_range: Range<usize>) -> impl Iterator<Item = &u8> {
+        let start = self.count_bytes(char_range.start);
+        let end = self.count_bytes(char_range.end);
+        let gap_start = self.gap_start_bytes as usize;
+        let gap_end = gap_start + self.gap_len as usize;
+
+        let before_gap = &self.data[start.min(gap_start)..end.min(gap_start)];
+        let after_gap = &self.data[start.max(gap_start) - gap_start + gap_end..end.max(gap_start) - gap_start + gap_end];
+        before_gap.iter().chain(after_gap)
+    }
+
+    /// Count the newlines (`'\n'`) in the specified character range.
+    pub fn count_newlines_in(&self, char_range: Range<usize>) -> usize {
+        self.bytes_in(char_range).filter(|&&b| b == b'\n').count()
+    }
+
+    /// Count the characters which take 2 UTF-16 code units (a surrogate pair) in the specified
+    /// character range.
+    #[cfg(feature = "wchar_conversion")]
+    pub fn count_pairs_in(&self, char_range: Range<usize>) -> usize {
+        self.bytes_in(char_range).filter(|&&b| b >= 0xf0).count()
     }
 
     pub fn start_as_str(&self) -> &str {
@@ -205,7 +458,59 @@ impl<const LEN: usize> GapBuffer<LEN> {
     }
     pub fn end_as_str(&self) -> &str {
         unsafe {
-            slice_to_str(&self.data[(self.gap_start_bytes +self.gap_len) as usize..LEN])
+            slice_to_str(&self.data[self.gap_end()..LEN])
+        }
+    }
+
+    /// Iterate over the characters in the buffer, skipping the gap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::GapBuffer;
+    /// let mut buf = GapBuffer::<10>::new_from_str("hi!");
+    /// buf.move_gap(2);
+    /// assert_eq!(buf.chars().collect::<String>(), "hi!");
+    /// ```
+    pub fn chars(&self) -> impl DoubleEndedIterator<Item = char> + '_ {
+        self.start_as_str().chars().chain(self.end_as_str().chars())
+    }
+
+    /// Iterate over the characters in the buffer along with their byte offsets. Like
+    /// [`str::char_indices`], offsets are relative to the start of the content and ignore the gap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::GapBuffer;
+    /// let mut buf = GapBuffer::<10>::new_from_str("aδc");
+    /// buf.move_gap(1);
+    /// assert_eq!(buf.char_indices().collect::<Vec<_>>(), &[(0, 'a'), (1, 'δ'), (3, 'c')]);
+    /// ```
+    pub fn char_indices(&self) -> impl DoubleEndedIterator<Item = (usize, char)> + '_ {
+        let gap_start = self.gap_start_bytes as usize;
+        self.start_as_str().char_indices()
+            .chain(self.end_as_str().char_indices().map(move |(i, c)| (i + gap_start, c)))
+    }
+
+    /// Count the characters before byte_pos. If byte_pos is inside a character, that character
+    /// isn't counted.
+    pub fn count_chars(&self, byte_pos: usize) -> usize {
+        let gap_bytes = self.gap_start_bytes as usize;
+        if byte_pos <= gap_bytes {
+            str_bytes_to_chars(self.start_as_str(), byte_pos)
+        } else {
+            self.gap_start_chars as usize + str_bytes_to_chars(self.end_as_str(), byte_pos - gap_bytes)
+        }
+    }
+
+    /// Check whether byte_pos is at the start or end of a character.
+    pub fn is_char_boundary(&self, byte_pos: usize) -> bool {
+        let gap_bytes = self.gap_start_bytes as usize;
+        if byte_pos <= gap_bytes {
+            self.start_as_str().is_char_boundary(byte_pos)
+        } else {
+            self.end_as_str().is_char_boundary(byte_pos - gap_bytes)
         }
     }
 
@@ -222,17 +527,30 @@ impl<const LEN: usize> GapBuffer<LEN> {
         }
     }
 
-    /// Take the remaining contents in the gap buffer. Mark them as deleted, but return them.
-    /// This will leave those items non-zero, but that doesn't matter.
-    pub fn take_rest(&mut self) -> &str {
-        let last_idx = (self.gap_start_bytes +self.gap_len) as usize;
-        self.gap_len = LEN as u16 - self.gap_start_bytes;
+    // Take the remaining contents in the gap buffer. Mark them as deleted, but return them.
+    // This will leave those items non-zero, but that doesn't matter.
+    pub(crate) fn take_rest(&mut self) -> &str {
+        let last_idx = self.gap_end();
+        self.gap_len = to_u16::<LEN>("gap_len", LEN - self.gap_start_bytes as usize);
+        self.recheck_ascii();
         unsafe { slice_to_str(&self.data[last_idx..LEN]) }
     }
 
     pub(crate) fn check(&self) {
-        let char_len = count_chars(unsafe { slice_to_str(&self.data[..self.gap_start_bytes as usize]) });
+        let start = core::str::from_utf8(&self.data[..self.gap_start_bytes as usize]).unwrap();
+        core::str::from_utf8(&self.data[self.gap_end()..]).unwrap();
+        let char_len = count_chars(start);
         assert_eq!(char_len, self.gap_start_chars as usize);
+        if self.all_ascii {
+            assert!(self.data[..self.gap_start_bytes as usize].is_ascii());
+            assert!(self.data[self.gap_end()..].is_ascii());
+        }
+    }
+}
+
+impl<const LEN: usize> Default for GapBuffer<LEN> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -277,7 +595,7 @@ impl<const LEN: usize> PartialEq for GapBuffer<LEN> {
 
 #[cfg(test)]
 mod test {
-    use crate::gapbuffer::GapBuffer;
+    use crate::gapbuffer::{CapacityError, GapBuffer};
 
     fn check_eq<const LEN: usize>(b: &GapBuffer<LEN>, s: &str) {
         assert_eq!(b.to_string(), s);
@@ -309,6 +627,75 @@ mod test {
         check_eq(&b, "");
     }
 
+    #[test]
+    fn overwrite_char() {
+        let mut b = GapBuffer::<20>::new_from_str("aδ𐆐c");
+        b.move_gap(3); // After 'δ'.
+        assert_eq!(b.char_len_bytes_at(1), 2);
+        assert_eq!(b.char_len_bytes_at(2), 4);
+
+        b.overwrite_char(0, 'x');
+        b.overwrite_char(1, '¥');
+        b.overwrite_char(2, '𐆔');
+        b.overwrite_char(3, 'z');
+        check_eq(&b, "x¥𐆔z");
+        b.check();
+    }
+
+    #[test]
+    fn map_ascii_bytes() {
+        let mut b = GapBuffer::<30>::new_from_str("abδ𐆐cdefg");
+        b.move_gap(8); // After 'c'.
+        b.map_ascii_bytes(1..6, |c| c.to_ascii_uppercase());
+        check_eq(&b, "aBδ𐆐CDefg");
+        b.map_ascii_bytes(0..9, |c| c.to_ascii_uppercase());
+        check_eq(&b, "ABδ𐆐CDEFG");
+        b.check();
+    }
+
+    #[test]
+    fn ascii_recovery() {
+        let mut b = GapBuffer::<20>::new_from_str("abδcd");
+        assert!(!b.all_ascii);
+
+        // Removing the non-ASCII character in the middle of the content isn't enough on its own.
+        b.remove_chars(2, 1);
+        check_eq(&b, "abcd");
+        b.check();
+
+        // But the buffer is marked as ASCII again once the gap reaches the end.
+        b.gap_to_end();
+        assert!(b.all_ascii);
+        assert_eq!(b.count_bytes(3), 3);
+        b.check();
+
+        let mut b = GapBuffer::<20>::new_from_str("ab𐆐");
+        b.remove_chars(2, 1);
+        assert!(b.all_ascii);
+        b.try_insert(0, "é").unwrap();
+        assert!(!b.all_ascii);
+        b.remove_chars(0, 3);
+        assert!(b.is_empty());
+        assert!(b.all_ascii);
+        b.check();
+    }
+
+    #[test]
+    fn count_newlines_in() {
+        let text = "a\nδ\n\n𐆐b\n";
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut b = GapBuffer::<30>::new_from_str(text);
+        for gap in 0..=chars.len() {
+            b.move_gap(text.char_indices().nth(gap).map_or(text.len(), |(i, _)| i));
+            for start in 0..=chars.len() {
+                for end in start..=chars.len() {
+                    let expected = chars[start..end].iter().filter(|&&c| c == '\n').count();
+                    assert_eq!(b.count_newlines_in(start..end), expected);
+                }
+            }
+        }
+    }
+
     #[test]
     fn eq() {
         let hi = GapBuffer::<5>::new_from_str("hi");
@@ -323,4 +710,90 @@ mod test {
         hi2.move_gap(0);
         assert_eq!(hi, hi2);
     }
+
+    #[test]
+    fn fallible_constructors() {
+        assert_eq!(GapBuffer::<4>::try_new_from_str("hiya").unwrap().to_string(), "hiya");
+        assert_eq!(GapBuffer::<4>::try_new_from_str("hiya!"), Err(CapacityError { needed: 5, available: 4 }));
+
+        let (b, rest) = GapBuffer::<4>::new_from_str_truncated("κόσμε");
+        check_eq(&b, "κό");
+        assert_eq!(rest, "σμε");
+
+        let (b, rest) = GapBuffer::<5>::new_from_str_truncated("a𝕐𝕆");
+        check_eq(&b, "a𝕐");
+        assert_eq!(rest, "𝕆");
+
+        let (b, rest) = GapBuffer::<5>::new_from_str_truncated("");
+        check_eq(&b, "");
+        assert_eq!(rest, "");
+    }
+
+    #[test]
+    fn gap_to_ends() {
+        let mut b = GapBuffer::<20>::new_from_str("κό𝕐𝕆");
+        b.move_gap(4);
+
+        b.gap_to_start();
+        assert_eq!(b.start_as_str(), "");
+        assert_eq!(b.end_as_str(), "κό𝕐𝕆");
+        b.check();
+
+        b.gap_to_end();
+        assert_eq!(b.start_as_str(), "κό𝕐𝕆");
+        assert_eq!(b.end_as_str(), "");
+        b.check();
+        check_eq(&b, "κό𝕐𝕆");
+    }
+
+    #[test]
+    fn chars() {
+        let s = "κό𝕐𝕆😘σμε";
+        let mut b = GapBuffer::<40>::new_from_str(s);
+        for gap in [0, 2, 4, 8, s.len()] {
+            b.move_gap(gap);
+            assert_eq!(b.chars().collect::<String>(), s);
+            assert_eq!(b.chars().rev().collect::<String>(), s.chars().rev().collect::<String>());
+            assert_eq!(b.char_indices().collect::<Vec<_>>(), s.char_indices().collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "not a character boundary")]
+    fn move_gap_inside_char() {
+        let mut b = GapBuffer::<10>::new_from_str("δδ");
+        b.move_gap(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a character boundary")]
+    fn move_gap_past_end() {
+        let mut b = GapBuffer::<10>::new_from_str("abc");
+        b.move_gap(5);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a character boundary")]
+    fn remove_inside_char() {
+        let mut b = GapBuffer::<10>::new_from_str("aδ");
+        b.remove(0, 2);
+    }
+
+    #[test]
+    fn remove_clamped() {
+        let mut b = GapBuffer::<10>::new_from_str("aδc");
+        assert_eq!(b.remove(5, 1), 0);
+        assert_eq!(b.remove(1, 100), 3);
+        check_eq(&b, "a");
+    }
+
+    #[cfg(feature = "checked-math")]
+    #[test]
+    #[should_panic(expected = "GapBuffer<5> gap_start_chars out of range: 0 - 2")]
+    fn checked_math() {
+        let mut b = GapBuffer::<5>::new_from_str("hi");
+        // Corrupt the buffer, as a buggy caller might.
+        b.gap_start_chars = 0;
+        b.move_gap(0);
+    }
 }
\ No newline at end of file
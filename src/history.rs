@@ -0,0 +1,373 @@
+// A versioned log of edits, built on top of the operations recorded by with_recording().
+
+use core::ops::Range;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use crate::{JumpRope, ReversibleOp};
+use crate::utils::{count_chars, str_chars_to_bytes};
+
+/// A log of the edits made to a rope, with undo support. Each recorded edit increments the
+/// history's version, so a version number identifies a state of the document.
+///
+/// This can be used to collect local changes and send them to collaborators in batches, using
+/// [`ops_between`](Self::ops_between).
+///
+/// Edits are also grouped for undo. Each call to [`record`](Self::record) makes a new undo group,
+/// except that consecutive single character inserts or deletes are merged into the group before,
+/// so typing (or backspacing over) a word is undone in one step. A new group starts at each word
+/// boundary.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut rope = JumpRope::from("hi");
+/// let mut history = History::new();
+/// let v0 = history.version();
+///
+/// history.record(&mut rope, |r| r.insert(2, " there"));
+/// history.record(&mut rope, |r| r.insert(8, " everyone"));
+/// assert_eq!(rope, "hi there everyone");
+///
+/// // The two inserts are composed into one.
+/// let ops = history.ops_between(v0, history.version());
+/// assert_eq!(ops, vec![ReversibleOp::Insert { pos: 2, content: " there everyone".into() }]);
+///
+/// history.undo(&mut rope);
+/// assert_eq!(rope, "hi there");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    ops: VecDeque<ReversibleOp>,
+    // The version before ops[0] was applied.
+    first_version: usize,
+    // The total size of the content in ops.
+    bytes: usize,
+    byte_budget: Option<usize>,
+
+    // Version ranges of the undoable groups of edits in ops.
+    undo: Vec<Range<usize>>,
+    // Groups of edits which have been undone, and can be redone.
+    redo: Vec<Vec<ReversibleOp>>,
+}
+
+impl History {
+    /// Create a new, empty history at version 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The current version. This is the number of operations which have been recorded.
+    pub fn version(&self) -> usize {
+        self.first_version + self.ops.len()
+    }
+
+    /// The oldest version still stored in the history. This is 0 unless old entries have been
+    /// dropped to stay within the [byte budget](Self::set_byte_budget).
+    pub fn oldest_version(&self) -> usize {
+        self.first_version
+    }
+
+    /// Limit the total size of the text stored in the history to approximately `bytes`. When the
+    /// history grows past its budget, the oldest entries are dropped. Dropped entries can no longer
+    /// be undone or passed to [`ops_between`](Self::ops_between). Pass `None` to remove the limit.
+    pub fn set_byte_budget(&mut self, bytes: Option<usize>) {
+        self.byte_budget = bytes;
+        self.enforce_budget();
+    }
+
+    /// The number of bytes of text stored in the history. The budget set with
+    /// [`set_byte_budget`](Self::set_byte_budget) limits this value.
+    pub fn len_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// The number of groups of edits which can be undone.
+    pub fn undo_depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// The number of groups of edits which can be redone.
+    pub fn redo_depth(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Run the passed function against the rope, appending every edit it makes to this history.
+    /// This clears the redo stack.
+    pub fn record<F: FnOnce(&mut JumpRope)>(&mut self, rope: &mut JumpRope, f: F) {
+        let start = self.version();
+        self.push_ops(rope.with_recording(f));
+        let end = self.version();
+        if start == end { return; }
+
+        self.redo.clear();
+        match self.undo.last_mut() {
+            Some(group) if group.end == start && end - start == 1
+                && continues_word(&self.ops[start - 1 - self.first_version], &self.ops[start - self.first_version]) => {
+                group.end = end;
+            }
+            _ => self.undo.push(start..end),
+        }
+        self.enforce_budget();
+    }
+
+    /// Undo the most recent group of edits. The undo is itself recorded as new operations in the
+    /// history. Returns false if there was nothing to undo.
+    ///
+    /// The rope must be in the state it was left in by the most recent recorded edit.
+    pub fn undo(&mut self, rope: &mut JumpRope) -> bool {
+        let Some(group) = self.undo.pop() else { return false; };
+        let ops = self.ops.range(group.start - self.first_version..group.end - self.first_version)
+            .cloned()
+            .collect::<Vec<_>>();
+        self.push_ops(rope.with_recording(|r| {
+            for op in ops.iter().rev() { op.inverse().apply(r); }
+        }));
+        self.redo.push(ops);
+        self.enforce_budget();
+        true
+    }
+
+    /// Redo the most recently undone group of edits. Returns false if there was nothing to redo.
+    pub fn redo(&mut self, rope: &mut JumpRope) -> bool {
+        let Some(ops) = self.redo.pop() else { return false; };
+        let start = self.version();
+        self.push_ops(rope.with_recording(|r| {
+            for op in &ops { op.apply(r); }
+        }));
+        self.undo.push(start..self.version());
+        self.enforce_budget();
+        true
+    }
+
+    /// Get the operations which transform the document at version `from` into the document at
+    /// version `to`. Adjacent operations are composed where possible (eg consecutive inserts of
+    /// typed characters become a single insert), so the returned list is usually much shorter
+    /// than the recorded operations.
+    ///
+    /// If `to` is before `from`, the returned operations undo the changes between them instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either version is newer than [`version`](Self::version), or older than
+    /// [`oldest_version`](Self::oldest_version).
+    pub fn ops_between(&self, from: usize, to: usize) -> Vec<ReversibleOp> {
+        let version = self.version();
+        assert!(from <= version && to <= version, "Version is newer than the history");
+        assert!(from >= self.first_version && to >= self.first_version, "Version is no longer in the history");
+
+        let ops = self.ops.range(from.min(to) - self.first_version..from.max(to) - self.first_version);
+        if from <= to {
+            compose(ops.cloned())
+        } else {
+            compose(ops.rev().map(ReversibleOp::inverse))
+        }
+    }
+
+    fn push_ops(&mut self, ops: Vec<ReversibleOp>) {
+        self.bytes += ops.iter().map(|op| op_content(op).len()).sum::<usize>();
+        self.ops.extend(ops);
+    }
+
+    fn enforce_budget(&mut self) {
+        let Some(budget) = self.byte_budget else { return; };
+        while self.bytes > budget {
+            let Some(op) = self.ops.pop_front() else { break; };
+            self.bytes -= op_content(&op).len();
+            self.first_version += 1;
+        }
+        // Groups which have been partially dropped can't be undone.
+        self.undo.retain(|group| group.start >= self.first_version);
+    }
+}
+
+// Is next a single character edit continuing the same word as prev? These are grouped together for
+// undo.
+fn continues_word(prev: &ReversibleOp, next: &ReversibleOp) -> bool {
+    use ReversibleOp::*;
+    let single_char = |s: &str| {
+        let mut chars = s.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    };
+    let (Some(prev_char), Some(next_char)) = (single_char(op_content(prev)), single_char(op_content(next))) else {
+        return false;
+    };
+
+    let adjacent = match (prev, next) {
+        (Insert { pos, .. }, Insert { pos: next_pos, .. }) => *next_pos == *pos + 1,
+        // Deleting forwards or backspacing.
+        (Remove { pos, .. }, Remove { pos: next_pos, .. }) => *next_pos == *pos || *next_pos + 1 == *pos,
+        _ => false,
+    };
+    // Start a new group at the whitespace after each word.
+    adjacent && (!next_char.is_whitespace() || prev_char.is_whitespace())
+}
+
+fn op_content(op: &ReversibleOp) -> &str {
+    match op {
+        ReversibleOp::Insert { content, .. } | ReversibleOp::Remove { content, .. } => content,
+    }
+}
+
+/// Compose a sequence of operations, merging each operation into the previous one where the
+/// result is equivalent.
+pub(crate) fn compose<I: Iterator<Item = ReversibleOp>>(ops: I) -> Vec<ReversibleOp> {
+    let mut result: Vec<ReversibleOp> = Vec::new();
+    for op in ops {
+        if let Some(prev) = result.last_mut() {
+            if try_merge(prev, &op) {
+                if prev.range().is_empty() { result.pop(); }
+                continue;
+            }
+        }
+        result.push(op);
+    }
+    result
+}
+
+// Try to merge next into prev, returning true if it was merged. The merged op may be empty.
+fn try_merge(prev: &mut ReversibleOp, next: &ReversibleOp) -> bool {
+    use ReversibleOp::*;
+    match (prev, next) {
+        (Insert { pos, content }, Insert { pos: next_pos, content: next_content }) => {
+            if *next_pos == *pos + count_chars(content) {
+                content.push_str(next_content);
+            } else if *next_pos == *pos {
+                content.insert_str(0, next_content);
+            } else { return false; }
+        }
+        (Remove { pos, content }, Remove { pos: next_pos, content: next_content }) => {
+            if *next_pos == *pos {
+                // Deleting forwards.
+                content.push_str(next_content);
+            } else if *next_pos + count_chars(next_content) == *pos {
+                // Backspacing.
+                content.insert_str(0, next_content);
+                *pos = *next_pos;
+            } else { return false; }
+        }
+        (Insert { pos, content }, Remove { pos: next_pos, content: next_content }) => {
+            // Removing text which was just inserted.
+            let len = count_chars(next_content);
+            if *next_pos < *pos || *next_pos + len > *pos + count_chars(content) { return false; }
+            let start = str_chars_to_bytes(content, *next_pos - *pos);
+            content.replace_range(start..start + next_content.len(), "");
+        }
+        _ => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::{History, JumpRope, ReversibleOp};
+
+    #[test]
+    fn compose_typing() {
+        let mut rope = JumpRope::new();
+        let mut history = History::new();
+        for (i, c) in "hello".chars().enumerate() {
+            history.record(&mut rope, |r| r.insert(i, c.encode_utf8(&mut [0; 4])));
+        }
+        // Backspace twice.
+        history.record(&mut rope, |r| r.remove(4..5));
+        history.record(&mut rope, |r| r.remove(3..4));
+        assert_eq!(history.version(), 7);
+
+        assert_eq!(history.ops_between(0, 7), vec![ReversibleOp::Insert { pos: 0, content: "hel".into() }]);
+        assert_eq!(history.ops_between(5, 7), vec![ReversibleOp::Remove { pos: 3, content: "lo".into() }]);
+        assert_eq!(history.ops_between(7, 5), vec![ReversibleOp::Insert { pos: 3, content: "lo".into() }]);
+        assert!(history.ops_between(3, 3).is_empty());
+    }
+
+    fn type_str(history: &mut History, rope: &mut JumpRope, pos: usize, s: &str) {
+        for (i, c) in s.chars().enumerate() {
+            history.record(rope, |r| r.insert(pos + i, c.encode_utf8(&mut [0; 4])));
+        }
+    }
+
+    #[test]
+    fn word_undo() {
+        let mut rope = JumpRope::new();
+        let mut history = History::new();
+        type_str(&mut history, &mut rope, 0, "hi there κόσμε");
+        assert_eq!(history.undo_depth(), 3);
+
+        // Backspace over "με", then delete forwards over "hi".
+        history.record(&mut rope, |r| r.remove(13..14));
+        history.record(&mut rope, |r| r.remove(12..13));
+        history.record(&mut rope, |r| r.remove(0..1));
+        history.record(&mut rope, |r| r.remove(0..1));
+        assert_eq!(rope, " there κόσ");
+        assert_eq!(history.undo_depth(), 5);
+
+        assert!(history.undo(&mut rope));
+        assert_eq!(rope, "hi there κόσ");
+        assert!(history.undo(&mut rope));
+        assert_eq!(rope, "hi there κόσμε");
+        assert!(history.undo(&mut rope));
+        assert_eq!(rope, "hi there");
+        assert_eq!((history.undo_depth(), history.redo_depth()), (2, 3));
+
+        assert!(history.redo(&mut rope));
+        assert_eq!(rope, "hi there κόσμε");
+        assert!(history.undo(&mut rope));
+        assert!(history.undo(&mut rope));
+        assert!(history.undo(&mut rope));
+        assert_eq!(rope, "");
+        assert!(!history.undo(&mut rope));
+
+        // New edits clear the redo stack.
+        history.record(&mut rope, |r| r.insert(0, "x"));
+        assert_eq!(history.redo_depth(), 0);
+        assert!(!history.redo(&mut rope));
+    }
+
+    #[test]
+    fn byte_budget() {
+        let mut rope = JumpRope::new();
+        let mut history = History::new();
+        type_str(&mut history, &mut rope, 0, "one two three");
+        assert_eq!(history.len_bytes(), 13);
+
+        history.set_byte_budget(Some(7));
+        assert_eq!(history.len_bytes(), 7);
+        assert_eq!(history.oldest_version(), 6);
+        // Only " three" can still be undone.
+        assert_eq!(history.undo_depth(), 1);
+        history.undo(&mut rope);
+        assert_eq!(rope, "one two");
+        assert!(history.len_bytes() <= 7);
+        assert!(!history.undo(&mut rope));
+    }
+
+    #[test]
+    fn ops_between_random_edits() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut rope = JumpRope::new();
+        let mut history = History::new();
+        let mut snapshots = vec![rope.to_string()];
+
+        for _ in 0..200 {
+            let len = rope.len_chars();
+            let pos = rng.gen_range(0..=len);
+            if len > 0 && rng.gen_bool(0.4) {
+                let end = (pos + rng.gen_range(1..3)).min(len);
+                history.record(&mut rope, |r| r.remove(pos..end));
+            } else {
+                let content = ["a", "κό", "𝕐", "xyz"].choose(&mut rng).unwrap();
+                history.record(&mut rope, |r| r.insert(pos, content));
+            }
+            while snapshots.len() <= history.version() { snapshots.push(rope.to_string()); }
+        }
+
+        for _ in 0..200 {
+            let from = rng.gen_range(0..snapshots.len());
+            let to = rng.gen_range(0..snapshots.len());
+            let mut r = JumpRope::from(snapshots[from].as_str());
+            for op in history.ops_between(from, to) { op.apply(&mut r); }
+            assert_eq!(r, snapshots[to]);
+        }
+    }
+}
@@ -0,0 +1,347 @@
+// A trigram index for running lots of substring searches over a big, mostly static document.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::{JumpRope, ReversibleOp};
+use crate::utils::count_chars;
+
+const DEFAULT_BLOCK_SIZE: usize = 4096;
+
+/// An index which speeds up repeated substring searches in a large document.
+///
+/// The document is split into blocks of characters, and the index stores the set of trigrams
+/// (runs of 3 characters) which appear in each block. Searches only read the blocks which contain
+/// every trigram in the needle, so searching for text which is rare in the document is much
+/// faster than scanning it with [`JumpRope::matches`].
+///
+/// The index is kept in sync with the rope by passing it the operations recorded with
+/// [`JumpRope::with_recording`]. Edits mark the blocks they touch as dirty, and dirty blocks are
+/// re-read from the rope the next time the index is searched.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let mut rope = JumpRope::from("a very long log file\n".repeat(1000));
+/// let mut index = RopeIndex::new(&rope);
+/// assert!(index.find_all(&rope, "error").is_empty());
+///
+/// let ops = rope.with_recording(|r| r.insert(21, "error!\n"));
+/// index.update(&ops);
+/// assert_eq!(index.find_all(&rope, "error"), vec![21]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RopeIndex {
+    block_size: usize,
+    blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone)]
+struct Block {
+    // The number of characters in the block.
+    len: usize,
+    // The sorted hashes of the trigrams starting in this block. Trigrams starting at the end of a
+    // block extend into the next block. This is None if the block needs to be re-read.
+    trigrams: Option<Vec<u32>>,
+}
+
+fn hash_trigram(a: char, b: char, c: char) -> u32 {
+    // Collisions only make searches read extra blocks, so this doesn't need to be fancy.
+    let h = (a as u32).wrapping_mul(0x9e3779b1) ^ (b as u32).wrapping_mul(0x85ebca6b).rotate_left(11);
+    h ^ (c as u32).wrapping_mul(0xc2b2ae35).rotate_left(22)
+}
+
+fn trigrams<I: Iterator<Item = char>>(mut chars: I) -> Vec<u32> {
+    let mut result = Vec::new();
+    let (Some(mut a), Some(mut b)) = (chars.next(), chars.next()) else { return result; };
+    for c in chars {
+        result.push(hash_trigram(a, b, c));
+        (a, b) = (b, c);
+    }
+    result.sort_unstable();
+    result.dedup();
+    result
+}
+
+impl RopeIndex {
+    /// Create an index of the content of the passed rope.
+    pub fn new(rope: &JumpRope) -> Self {
+        Self::with_block_size(rope, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Create an index of the content of the passed rope, using blocks of approximately
+    /// `block_size` characters. Smaller blocks make searches for rare text read less of the
+    /// document, but the index uses more memory.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` is less than 3.
+    pub fn with_block_size(rope: &JumpRope, block_size: usize) -> Self {
+        assert!(block_size >= 3, "Block size must be at least 3");
+        let mut index = Self { block_size, blocks: Vec::new() };
+        index.insert(0, rope.len_chars());
+        index.refresh(rope);
+        index
+    }
+
+    /// The number of characters in the document, as far as the index knows.
+    fn len_chars(&self) -> usize {
+        self.blocks.iter().map(|b| b.len).sum()
+    }
+
+    /// Update the index with edits made to the rope. The operations must be passed in the order
+    /// they were made, as returned by [`JumpRope::with_recording`].
+    ///
+    /// The blocks touched by each edit are marked dirty, but they aren't re-read from the rope
+    /// until the next search.
+    pub fn update(&mut self, ops: &[ReversibleOp]) {
+        for op in ops {
+            match op {
+                ReversibleOp::Insert { pos, content } => self.insert(*pos, count_chars(content)),
+                ReversibleOp::Remove { .. } => self.remove(op.range()),
+            }
+        }
+    }
+
+    // Mark block i as dirty, along with the blocks before it whose trigrams extend into block i.
+    // Trigrams reach 2 characters past the end of their block, which can cover several short
+    // blocks.
+    fn mark_dirty(&mut self, i: usize) {
+        self.blocks[i].trigrams = None;
+        let mut reach = 0;
+        for block in self.blocks[..i].iter_mut().rev() {
+            if reach >= 2 { break; }
+            block.trigrams = None;
+            reach += block.len;
+        }
+    }
+
+    fn insert(&mut self, pos: usize, len: usize) {
+        if len == 0 { return; }
+        if self.blocks.is_empty() {
+            self.blocks.push(Block { len: 0, trigrams: None });
+        }
+
+        let mut start = 0;
+        let i = self.blocks.iter()
+            .position(|b| {
+                let found = pos <= start + b.len;
+                start += b.len;
+                found
+            })
+            .unwrap_or(self.blocks.len() - 1);
+        self.blocks[i].len += len;
+        self.mark_dirty(i);
+    }
+
+    fn remove(&mut self, range: core::ops::Range<usize>) {
+        let mut start = 0;
+        let mut first_touched = None;
+        for (i, block) in self.blocks.iter_mut().enumerate() {
+            let end = start + block.len;
+            let overlap = end.min(range.end).saturating_sub(start.max(range.start));
+            if overlap > 0 {
+                first_touched.get_or_insert(i);
+                block.len -= overlap;
+                block.trigrams = None;
+            }
+            start = end;
+        }
+
+        if let Some(i) = first_touched {
+            self.mark_dirty(i);
+            // The block after the removed range may now have a different block before it.
+            self.blocks.retain(|b| b.len > 0);
+            if let Some(next) = self.blocks.get_mut(i) {
+                next.trigrams = None;
+            }
+        }
+    }
+
+    /// Re-read any dirty blocks from the rope. This is called automatically by
+    /// [`find_all`](Self::find_all), but calling it directly moves the work somewhere else (eg
+    /// to when the user stops typing).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of sync with the rope - ie, if the rope was edited without
+    /// passing the edits to [`update`](Self::update).
+    pub fn refresh(&mut self, rope: &JumpRope) {
+        assert_eq!(self.len_chars(), rope.len_chars(), "RopeIndex is out of sync with the rope");
+
+        // Split up blocks which have grown too big.
+        let mut i = 0;
+        while i < self.blocks.len() {
+            let len = self.blocks[i].len;
+            if len > self.block_size * 2 {
+                self.blocks[i].len = self.block_size;
+                self.blocks[i].trigrams = None;
+                self.blocks.insert(i + 1, Block { len: len - self.block_size, trigrams: None });
+            }
+            i += 1;
+        }
+
+        let total = rope.len_chars();
+        let mut start = 0;
+        for block in &mut self.blocks {
+            if block.trigrams.is_none() {
+                let end = (start + block.len + 2).min(total);
+                block.trigrams = Some(trigrams(rope.slice_chars(start..end)));
+            }
+            start += block.len;
+        }
+    }
+
+    /// Find the character positions of every match of `needle` in the rope. Like
+    /// [`JumpRope::matches`], the matches don't overlap.
+    ///
+    /// The index is refreshed first if the rope has been edited. Needles shorter than 3
+    /// characters (or longer than a block) can't use the index, and are found by scanning the
+    /// whole document.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of sync with the rope. See [`refresh`](Self::refresh).
+    pub fn find_all(&mut self, rope: &JumpRope, needle: &str) -> Vec<usize> {
+        self.refresh(rope);
+
+        let needle_len = count_chars(needle);
+        if needle_len < 3 || needle_len > self.block_size {
+            return rope.matches(needle, 0).collect();
+        }
+
+        let wanted = trigrams(needle.chars());
+        let mut result = Vec::new();
+        let mut buf = String::new();
+        // Matches can't start before the end of the previous match.
+        let mut last_end = 0;
+        let mut start = 0;
+        for (i, block) in self.blocks.iter().enumerate() {
+            let block_start = start;
+            start += block.len;
+
+            // The trigrams of a match starting in this block start before start + needle_len - 2.
+            // Removals can shrink blocks to a few characters, so they may be spread over several
+            // of the following blocks.
+            let mut end = i + 1;
+            let mut end_start = start;
+            while end < self.blocks.len() && end_start < start + needle_len - 2 {
+                end_start += self.blocks[end].len;
+                end += 1;
+            }
+            let span = &self.blocks[i..end];
+            let is_candidate = wanted.iter().all(|t| {
+                span.iter().any(|b| b.trigrams.as_ref().unwrap().binary_search(t).is_ok())
+            });
+            if !is_candidate { continue; }
+
+            let from = block_start.max(last_end);
+            if from >= start { continue; }
+            let to = (start + needle_len - 1).min(rope.len_chars());
+            buf.clear();
+            buf.extend(rope.slice_chars(from..to));
+
+            let mut pos = from;
+            let mut byte = 0;
+            for (i, _) in buf.match_indices(needle) {
+                pos += count_chars(&buf[byte..i]);
+                byte = i;
+                if pos >= start { break; }
+                result.push(pos);
+                last_end = pos + needle_len;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::{JumpRope, RopeIndex};
+
+    #[test]
+    fn index_matches_search() {
+        let mut rng = SmallRng::seed_from_u64(3);
+        let alphabet = ['a', 'b', 'κ', '𝕐', '\n'];
+        let mut rope = JumpRope::new();
+        let mut index = RopeIndex::with_block_size(&rope, 8);
+
+        for _ in 0..300 {
+            let len = rope.len_chars();
+            let ops = rope.with_recording(|r| {
+                if len > 0 && rng.gen_bool(0.4) {
+                    let start = rng.gen_range(0..len);
+                    let end = (start + rng.gen_range(1..20)).min(len);
+                    r.remove(start..end);
+                } else {
+                    let content = (0..rng.gen_range(1..15))
+                        .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+                        .collect::<String>();
+                    r.insert(rng.gen_range(0..=len), content);
+                }
+            });
+            index.update(&ops);
+
+            for needle in ["aaa", "abκ", "𝕐\n𝕐", "ababa", "a", "bb", "κκκκκκκκκ"] {
+                let expected = rope.matches(needle, 0).collect::<Vec<_>>();
+                assert_eq!(index.find_all(&rope, needle), expected);
+            }
+        }
+
+        assert_eq!(index.len_chars(), rope.len_chars());
+    }
+
+    #[test]
+    fn index_matches_search_after_removals() {
+        // Removals can leave blocks only a character or two long, so a match's trigrams can be
+        // spread over several blocks.
+        let mut rng = SmallRng::seed_from_u64(4);
+        let text = "abcdefghij";
+        let mut rope = JumpRope::from(text.repeat(20));
+        let mut index = RopeIndex::with_block_size(&rope, 8);
+
+        for _ in 0..500 {
+            let len = rope.len_chars();
+            let ops = rope.with_recording(|r| {
+                if len > 0 && rng.gen_bool(0.6) {
+                    let start = rng.gen_range(0..len);
+                    let end = (start + rng.gen_range(1..8)).min(len);
+                    r.remove(start..end);
+                } else {
+                    let start = rng.gen_range(0..text.len());
+                    let end = rng.gen_range(start..=text.len());
+                    r.insert(rng.gen_range(0..=len), &text[start..end]);
+                }
+            });
+            index.update(&ops);
+
+            for needle in ["cde", "efghi", "bcdefg", "abcdefgh", "hij"] {
+                let expected = rope.matches(needle, 0).collect::<Vec<_>>();
+                assert_eq!(index.find_all(&rope, needle), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn match_spanning_shrunk_blocks() {
+        let mut rope = JumpRope::from("zeXXXXXXfXXXXXXXghizzzzzz");
+        let mut index = RopeIndex::with_block_size(&rope, 8);
+        let ops = rope.with_recording(|r| {
+            r.remove(2..8);
+            r.remove(3..10);
+        });
+        index.update(&ops);
+        assert_eq!(rope, "zefghizzzzzz");
+        assert_eq!(index.find_all(&rope, "efghi"), vec![1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of sync")]
+    fn out_of_sync() {
+        let mut rope = JumpRope::from("hello there");
+        let mut index = RopeIndex::new(&rope);
+        rope.insert(0, "oops");
+        index.find_all(&rope, "there");
+    }
+}
@@ -1,14 +1,15 @@
-use std::ops::Range;
+use core::iter::FusedIterator;
+use core::ops::{ControlFlow, Range};
 use crate::jumprope::*;
-use crate::utils::str_chars_to_bytes;
+use crate::utils::{count_chars, floor_char_boundary, str_chars_to_bytes};
 
 /// An iterator over chunks (nodes) in the list.
-pub(crate) struct NodeIter<'a>(Option<&'a Node>);
+pub(crate) struct NodeIter<'a, const N: usize = NODE_STR_SIZE>(Option<&'a Node<N>>);
 
-impl<'a> Iterator for NodeIter<'a> {
-    type Item = &'a Node;
+impl<'a, const N: usize> Iterator for NodeIter<'a, N> {
+    type Item = &'a Node<N>;
 
-    fn next(&mut self) -> Option<&'a Node> {
+    fn next(&mut self) -> Option<&'a Node<N>> {
         let prev = self.0;
         if let Some(n) = self.0 {
             // TODO: What?
@@ -19,13 +20,13 @@ impl<'a> Iterator for NodeIter<'a> {
 }
 
 /// A content iterator iterates over the strings in the rope
-pub struct RawContentIter<'a> {
-    next: Option<&'a Node>,
+pub struct RawContentIter<'a, const N: usize = NODE_STR_SIZE> {
+    next: Option<&'a Node<N>>,
     /// Are we at the start or the end of the gap buffer?
     at_start: bool,
 }
 
-impl<'a> Iterator for RawContentIter<'a> {
+impl<'a, const N: usize> Iterator for RawContentIter<'a, N> {
     type Item = (&'a str, usize);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -48,6 +49,39 @@ impl<'a> Iterator for RawContentIter<'a> {
     }
 }
 
+impl<const N: usize> FusedIterator for RawContentIter<'_, N> {}
+
+/// An iterator over the string chunks in a rope, along with an ID for each chunk. See
+/// [`JumpRope::chunks_with_ids`].
+pub struct ChunksWithIds<'a, const N: usize = NODE_STR_SIZE> {
+    next: Option<&'a Node<N>>,
+    at_start: bool,
+}
+
+impl<'a, const N: usize> Iterator for ChunksWithIds<'a, N> {
+    type Item = (u64, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(n) = self.next {
+            // Each node yields (up to) 2 chunks - the content before and after its gap.
+            let item = if self.at_start {
+                self.at_start = false;
+                (n.id * 2, n.str.start_as_str())
+            } else {
+                self.next = unsafe { n.next_ptr().as_ref() };
+                self.at_start = true;
+                (n.id * 2 + 1, n.str.end_as_str())
+            };
+
+            if !item.1.is_empty() {
+                return Some(item);
+            }
+        }
+
+        None
+    }
+}
+
 pub struct StrSlices<'a, I: Iterator<Item=(&'a str, usize)>>(I);
 
 impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for StrSlices<'a, I> {
@@ -58,67 +92,104 @@ impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for StrSlices<'a, I> {
     }
 }
 
+/// An iterator over the characters in a rope (or a range of a rope). Characters are read directly
+/// out of each chunk. Skipping characters with `nth()` and counting them with `count()` step over
+/// whole chunks at a time, without decoding them.
 pub struct CharsRaw<'a, I: Iterator<Item=(&'a str, usize)>> {
     inner: I,
-    current: std::str::Chars<'a>,
+    current: core::str::Chars<'a>,
+    // The number of characters left in current.
+    current_len: usize,
 }
 
 impl<'a, I: Iterator<Item=(&'a str, usize)>> From<I> for CharsRaw<'a, I> {
     fn from(inner: I) -> Self {
         Self {
             inner,
-            current: "".chars()
+            current: "".chars(),
+            current_len: 0,
         }
     }
 }
 
+impl<'a, I: Iterator<Item=(&'a str, usize)>> CharsRaw<'a, I> {
+    fn next_chunk(&mut self) -> Option<()> {
+        let (s, char_len) = self.inner.next()?;
+        // None of the items returned from our inner iterator should be empty.
+        debug_assert!(char_len > 0);
+        self.current = s.chars();
+        self.current_len = char_len;
+        Some(())
+    }
+}
+
 impl<'a, I: Iterator<Item=(&'a str, usize)>> Iterator for CharsRaw<'a, I> {
     type Item = char;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.current.next().or_else(|| {
-            self.current = self.inner.next()?.0.chars();
-            let next = self.current.next();
-            // None of the items returned from our inner iterator should be empty.
-            debug_assert!(next.is_some());
-            next
-        })
+        if self.current_len == 0 { self.next_chunk()?; }
+        self.current_len -= 1;
+        self.current.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.current_len, None)
+    }
+
+    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
+        while n >= self.current_len {
+            n -= self.current_len;
+            if self.next_chunk().is_none() {
+                // Everything has been skipped, including the rest of the current chunk.
+                self.current = "".chars();
+                self.current_len = 0;
+                return None;
+            }
+        }
+        self.current_len -= n + 1;
+        self.current.nth(n)
+    }
+
+    fn count(self) -> usize {
+        self.current_len + self.inner.map(|(_, char_len)| char_len).sum::<usize>()
     }
 }
 
-pub type StrContentIter<'a> = StrSlices<'a, RawContentIter<'a>>;
-pub type Chars<'a> = CharsRaw<'a, RawContentIter<'a>>;
+impl<'a, I: FusedIterator<Item=(&'a str, usize)>> FusedIterator for CharsRaw<'a, I> {}
 
-impl<'a> RawContentIter<'a> {
-    pub fn strings(self) -> StrContentIter<'a> {
+pub type StrContentIter<'a, const N: usize = NODE_STR_SIZE> = StrSlices<'a, RawContentIter<'a, N>>;
+pub type Chars<'a, const N: usize = NODE_STR_SIZE> = CharsRaw<'a, RawContentIter<'a, N>>;
+
+impl<'a, const N: usize> RawContentIter<'a, N> {
+    pub fn strings(self) -> StrContentIter<'a, N> {
         StrSlices(self)
     }
 
-    pub fn chars(self) -> Chars<'a> {
+    pub fn chars(self) -> Chars<'a, N> {
         self.into()
     }
 }
 
-pub struct ContentRangeIter<'a> {
-    inner: RawContentIter<'a>,
+pub struct ContentRangeIter<'a, const N: usize = NODE_STR_SIZE> {
+    inner: RawContentIter<'a, N>,
     skip: usize,
     take_len: usize,
 }
 
-pub type StrRangeIter<'a> = StrSlices<'a, ContentRangeIter<'a>>;
-pub type CharsSlice<'a> = CharsRaw<'a, ContentRangeIter<'a>>;
+pub type StrRangeIter<'a, const N: usize = NODE_STR_SIZE> = StrSlices<'a, ContentRangeIter<'a, N>>;
+pub type CharsSlice<'a, const N: usize = NODE_STR_SIZE> = CharsRaw<'a, ContentRangeIter<'a, N>>;
 
-impl<'a> ContentRangeIter<'a> {
-    pub fn strings(self) -> StrRangeIter<'a> {
+impl<'a, const N: usize> ContentRangeIter<'a, N> {
+    pub fn strings(self) -> StrRangeIter<'a, N> {
         StrSlices(self)
     }
 
-    pub fn chars(self) -> CharsSlice<'a> {
+    pub fn chars(self) -> CharsSlice<'a, N> {
         self.into()
     }
 }
 
-impl<'a> Iterator for ContentRangeIter<'a> {
+impl<'a, const N: usize> Iterator for ContentRangeIter<'a, N> {
     type Item = (&'a str, usize);
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -147,8 +218,142 @@ impl<'a> Iterator for ContentRangeIter<'a> {
     }
 }
 
-impl JumpRope {
-    pub(crate) fn node_iter(&self) -> NodeIter { NodeIter(Some(&self.head)) }
+impl<const N: usize> FusedIterator for ContentRangeIter<'_, N> {}
+
+/// An iterator over the chunks in a rope, with each chunk split to fit within a maximum size. See
+/// [`JumpRope::chunks_capped`].
+pub struct CappedChunks<'a, const N: usize = NODE_STR_SIZE> {
+    inner: RawContentIter<'a, N>,
+    // The remainder of the current chunk which hasn't been yielded yet.
+    rest: &'a str,
+    max_bytes: usize,
+}
+
+impl<'a, const N: usize> Iterator for CappedChunks<'a, N> {
+    type Item = (&'a str, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            let (s, char_len) = self.inner.next()?;
+            if s.len() <= self.max_bytes { return Some((s, char_len)); }
+            self.rest = s;
+        }
+
+        let mut split = floor_char_boundary(self.rest, self.max_bytes);
+        if split == 0 {
+            // The next character is bigger than max_bytes on its own. Yield it anyway.
+            split = self.rest.chars().next().unwrap().len_utf8();
+        }
+        let (s, rest) = self.rest.split_at(split);
+        self.rest = rest;
+        Some((s, count_chars(s)))
+    }
+}
+
+/// Iterates backwards over the chunks in the rope before some character position. Nodes don't
+/// store back pointers, so each node is found by descending the skip list again (O(log n) per
+/// node).
+pub(crate) struct RevChunks<'a, const N: usize = NODE_STR_SIZE, const B: u8 = DEFAULT_BIAS> {
+    rope: &'a JumpRopeCustom<N, B>,
+    // Characters before this position haven't been yielded yet.
+    end: usize,
+    // The start of the gap buffer of the most recently visited node, if it still needs yielding.
+    pending: Option<(&'a str, usize)>,
+}
+
+impl<'a, const N: usize, const B: u8> Iterator for RevChunks<'a, N, B> {
+    type Item = (&'a str, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(p) = self.pending.take() { return Some(p); }
+        if self.end == 0 { return None; }
+
+        let cursor = self.rope.cursor_at_char(self.end, true);
+        let node = unsafe { &*cursor.here_ptr() };
+        let local_pos = cursor.local_char_pos();
+        debug_assert!(local_pos > 0);
+        self.end -= local_pos;
+
+        let gap_chars = node.str.gap_start_chars as usize;
+        let start = node.str.start_as_str();
+        if local_pos <= gap_chars {
+            Some((&start[..str_chars_to_bytes(start, local_pos)], local_pos))
+        } else {
+            let end = node.str.end_as_str();
+            if gap_chars > 0 { self.pending = Some((start, gap_chars)); }
+            Some((&end[..str_chars_to_bytes(end, local_pos - gap_chars)], local_pos - gap_chars))
+        }
+    }
+}
+
+/// An iterator over the characters in a rope before some position, from the end backwards. See
+/// [`JumpRope::chars_rev`].
+pub struct RevChars<'a, const N: usize = NODE_STR_SIZE, const B: u8 = DEFAULT_BIAS> {
+    inner: RevChunks<'a, N, B>,
+    current: core::str::Chars<'a>,
+    // The number of characters left in current.
+    current_len: usize,
+}
+
+impl<'a, const N: usize, const B: u8> RevChars<'a, N, B> {
+    fn next_chunk(&mut self) -> Option<()> {
+        let (s, char_len) = self.inner.next()?;
+        debug_assert!(char_len > 0);
+        self.current = s.chars();
+        self.current_len = char_len;
+        Some(())
+    }
+
+    fn len_remaining(&self) -> usize {
+        // The inner iterator has already stepped its end past any pending chunk.
+        let pending = self.inner.pending.map_or(0, |(_, len)| len);
+        self.current_len + pending + self.inner.end
+    }
+}
+
+impl<'a, const N: usize, const B: u8> Iterator for RevChars<'a, N, B> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_len == 0 { self.next_chunk()?; }
+        self.current_len -= 1;
+        self.current.next_back()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len_remaining();
+        (len, Some(len))
+    }
+
+    fn nth(&mut self, mut n: usize) -> Option<Self::Item> {
+        while n >= self.current_len {
+            n -= self.current_len;
+            if self.next_chunk().is_none() {
+                // Everything has been skipped, including the rest of the current chunk.
+                self.current = "".chars();
+                self.current_len = 0;
+                return None;
+            }
+        }
+        self.current_len -= n + 1;
+        self.current.nth_back(n)
+    }
+
+    fn count(self) -> usize {
+        self.len_remaining()
+    }
+}
+
+impl<const N: usize, const B: u8> ExactSizeIterator for RevChars<'_, N, B> {}
+impl<const N: usize, const B: u8> FusedIterator for RevChars<'_, N, B> {}
+
+impl<const NODE: usize, const BIAS: u8> JumpRopeCustom<NODE, BIAS> {
+    pub(crate) fn node_iter(&self) -> NodeIter<'_, NODE> { NodeIter(Some(&self.head)) }
+
+    // Iterate backwards through the chunks containing the characters before end.
+    pub(crate) fn rev_chunks_before(&self, end: usize) -> RevChunks<'_, NODE, BIAS> {
+        RevChunks { rope: self, end: end.min(self.len_chars()), pending: None }
+    }
 
     /// Iterate over all "string chunks" in the rope. Iterated chunks are pairs of (str, char_len)
     /// items. The way items are split by the library is undefined, and should not be relied upon.
@@ -166,17 +371,79 @@ impl JumpRope {
     /// }
     /// assert_eq!(string, "oh hai");
     /// ```
-    pub fn chunks(&self) -> RawContentIter {
+    pub fn chunks(&self) -> RawContentIter<'_, NODE> {
         RawContentIter {
             next: Some(&self.head),
             at_start: true
         }
     }
 
-    /// Get an iterator over all characters in the rope.
+    /// Iterate over the string chunks in the rope, like [`chunks`](Self::chunks), along with an
+    /// ID for each chunk. This is useful for caching work done per chunk (like syntax highlighting
+    /// state or hashes) between edits.
+    ///
+    /// A chunk's ID changes whenever its content changes, and IDs are never reused within a rope.
+    /// So after an edit, a chunk with a previously seen ID is guaranteed to contain the same text
+    /// as before - though it may have moved to a different position in the document. IDs are not
+    /// shared between ropes, and a cloned rope has its own IDs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("a long document. ".repeat(1000));
+    /// let before = rope.chunks_with_ids().map(|(id, _)| id).collect::<Vec<_>>();
+    ///
+    /// rope.insert(rope.len_chars(), "!");
+    /// let after = rope.chunks_with_ids().map(|(id, _)| id).collect::<Vec<_>>();
+    ///
+    /// // The chunk at the end was modified, so it gets a new ID. The rest are unchanged.
+    /// assert_eq!(before[0], after[0]);
+    /// assert_ne!(before.last(), after.last());
+    /// ```
+    pub fn chunks_with_ids(&self) -> ChunksWithIds<'_, NODE> {
+        ChunksWithIds {
+            next: Some(&self.head),
+            at_start: true,
+        }
+    }
+
+    /// Iterate over the string chunks in the rope, like [`chunks`](Self::chunks), but with any
+    /// chunk longer than `max_bytes` split into smaller pieces. Chunks are only ever split on
+    /// character boundaries. This is useful for sending a document over a protocol with a maximum
+    /// frame size.
+    ///
+    /// If `max_bytes` is smaller than a character's UTF8 length (ie, less than 4), that character
+    /// is yielded as a chunk on its own anyway.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε and some more text");
+    /// let mut string = String::new();
+    /// for (str, char_len) in rope.chunks_capped(5) {
+    ///     assert!(str.len() <= 5);
+    ///     assert_eq!(str.chars().count(), char_len);
+    ///     string.push_str(str);
+    /// }
+    /// assert_eq!(string, "κόσμε and some more text");
+    /// ```
+    pub fn chunks_capped(&self, max_bytes: usize) -> CappedChunks<'_, NODE> {
+        CappedChunks {
+            inner: self.chunks(),
+            rest: "",
+            max_bytes,
+        }
+    }
+
+    /// Get an iterator over all characters in the rope. Use [`slice_chars`](Self::slice_chars)
+    /// to iterate over the characters in a range instead.
     ///
-    /// In most cases this will be less efficient than using [`chunks`](Self::chunks) to
-    /// iterate over all &str items contained in the rope.
+    /// Characters are read directly out of each node's gap buffer. Skipping characters with
+    /// `nth()` (or `skip()`) and counting them step over whole chunks at a time. In most cases
+    /// this will still be less efficient than using [`chunks`](Self::chunks) to iterate over all
+    /// &str items contained in the rope.
     ///
     /// # Example
     ///
@@ -184,11 +451,47 @@ impl JumpRope {
     /// # use jumprope::*;
     /// let rope = JumpRope::from("oh hai");
     /// assert_eq!("oh hai", rope.chars().collect::<String>());
+    /// assert_eq!(rope.chars().nth(3), Some('h'));
     /// ```
-    pub fn chars(&self) -> Chars {
+    pub fn chars(&self) -> Chars<'_, NODE> {
         self.chunks().chars()
     }
 
+    /// Iterate backwards through the characters in the rope, starting from the end of the
+    /// document. Chunks are found by searching the skip list from the top, so each chunk costs
+    /// *O(log n)* time to find. Skipping with `nth()` steps over whole chunks at a time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("oh hai");
+    /// assert_eq!("iah ho", rope.chars_rev().collect::<String>());
+    /// ```
+    pub fn chars_rev(&self) -> RevChars<'_, NODE, BIAS> {
+        self.chars_before(self.len_chars())
+    }
+
+    /// Iterate backwards through the characters before the specified character position. This is
+    /// useful for scanning backwards from the cursor (eg, to find the start of the previous word).
+    /// Positions past the end of the document are clamped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("hi there everyone");
+    /// let word_len = rope.chars_before(8).take_while(|c| c.is_alphanumeric()).count();
+    /// assert_eq!(word_len, 5); // "there"
+    /// ```
+    pub fn chars_before(&self, pos: usize) -> RevChars<'_, NODE, BIAS> {
+        RevChars {
+            inner: self.rev_chunks_before(pos),
+            current: "".chars(),
+            current_len: 0,
+        }
+    }
+
     /// Iterate through chunks across a character range in the document.
     ///
     /// # Example
@@ -212,7 +515,7 @@ impl JumpRope {
     /// let string = rope.slice_chunks(3..13).map(|(str, _len)| str).collect::<String>();
     /// assert_eq!(string, "Greetings!");
     /// ```
-    pub fn slice_chunks(&self, range: Range<usize>) -> ContentRangeIter {
+    pub fn slice_chunks(&self, range: Range<usize>) -> ContentRangeIter<'_, NODE> {
         let cursor = self.cursor_at_char(range.start, false);
         let node = unsafe { cursor.here_ptr().as_ref().unwrap() };
         let node_gap_start = node.str.gap_start_chars as usize;
@@ -233,6 +536,86 @@ impl JumpRope {
         }
     }
 
+    /// Iterate through the string chunks covering a character range in the document. The first
+    /// and last chunks are trimmed to the range. This is the same as
+    /// [`slice_chunks`](Self::slice_chunks), without the character lengths. The range is clamped
+    /// to the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxGreetings!xxx");
+    /// assert_eq!(rope.chunks_in_range(3..13).collect::<String>(), "Greetings!");
+    /// assert_eq!(rope.chunks_in_range(13..100).collect::<String>(), "xxx");
+    /// ```
+    pub fn chunks_in_range(&self, range: Range<usize>) -> StrRangeIter<'_, NODE> {
+        let end = range.end.min(self.len_chars());
+        self.slice_chunks(range.start.min(end)..end).strings()
+    }
+
+    /// Call `visit` with each chunk of the rope from character position `pos` to the end of the
+    /// document. The first chunk is trimmed to start at `pos`. Like [`chunks`](Self::chunks), each
+    /// chunk is passed as a `(str, char_len)` pair.
+    ///
+    /// Traversal stops early if `visit` returns [`ControlFlow::Break`], and the break value is
+    /// returned. This is a lower overhead alternative to
+    /// [`slice_chunks`](Self::slice_chunks) for performance sensitive consumers, like searchers
+    /// and hashers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use std::ops::ControlFlow;
+    /// let rope = JumpRope::from("xxx hi there");
+    ///
+    /// // Find the character position of the first 'e' after position 4.
+    /// let mut offset = 4;
+    /// let result = rope.visit_chunks_from(4, |s, len| {
+    ///     match s.chars().position(|c| c == 'e') {
+    ///         Some(i) => ControlFlow::Break(offset + i),
+    ///         None => { offset += len; ControlFlow::Continue(()) }
+    ///     }
+    /// });
+    /// assert_eq!(result, ControlFlow::Break(9));
+    /// ```
+    pub fn visit_chunks_from<B, F: FnMut(&str, usize) -> ControlFlow<B>>(&self, pos: usize, mut visit: F) -> ControlFlow<B> {
+        let pos = pos.min(self.len_chars());
+        let cursor = self.cursor_at_char(pos, false);
+        let mut node = unsafe { &*cursor.here_ptr() };
+        let mut skip = cursor.local_char_pos();
+
+        loop {
+            let gap_chars = node.str.gap_start_chars as usize;
+            let mut start = node.str.start_as_str();
+            let mut start_chars = gap_chars;
+            let mut end = node.str.end_as_str();
+            let mut end_chars = node.num_chars() - gap_chars;
+
+            if skip > 0 {
+                if skip < gap_chars {
+                    start = &start[str_chars_to_bytes(start, skip)..];
+                    start_chars -= skip;
+                } else {
+                    start = "";
+                    start_chars = 0;
+                    end = &end[str_chars_to_bytes(end, skip - gap_chars)..];
+                    end_chars -= skip - gap_chars;
+                }
+                skip = 0;
+            }
+
+            if start_chars > 0 { visit(start, start_chars)?; }
+            if end_chars > 0 { visit(end, end_chars)?; }
+
+            match unsafe { node.next_ptr().as_ref() } {
+                Some(next) => node = next,
+                None => return ControlFlow::Continue(()),
+            }
+        }
+    }
+
     /// Iterate through characters in the rope within the specified range. The range is specified
     /// using unicode characters, not bytes.
     ///
@@ -246,13 +629,14 @@ impl JumpRope {
     ///     rope.slice_chars(3..rope.len_chars() - 3).collect::<String>()
     /// );
     /// ```
-    pub fn slice_chars(&self, range: Range<usize>) -> CharsSlice {
+    pub fn slice_chars(&self, range: Range<usize>) -> CharsSlice<'_, NODE> {
         self.slice_chunks(range).chars()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use core::ops::ControlFlow;
     use crate::JumpRope;
     use crate::jumprope::NODE_STR_SIZE;
     use crate::utils::{count_chars, str_chars_to_bytes};
@@ -269,13 +653,77 @@ mod tests {
         }
 
         assert_eq!(rope.chunks().chars().collect::<String>(), rope.to_string());
+
+        for max_bytes in [0, 1, 3, 4, 7, 1000] {
+            let mut content = String::new();
+            for (s, len) in rope.chunks_capped(max_bytes) {
+                assert_eq!(count_chars(s), len);
+                assert_ne!(len, 0);
+                assert!(s.len() <= max_bytes.max(4));
+                assert!(s.len() <= max_bytes || len == 1);
+                content.push_str(s);
+            }
+            assert_eq!(content, rope.to_string());
+        }
         assert_eq!(rope.chars().collect::<String>(), rope.to_string());
         assert_eq!(rope.slice_chars(0..rope.len_chars()).collect::<String>(), rope.to_string());
+        let chars = rope.to_string().chars().collect::<Vec<_>>();
+        assert_eq!(rope.chars().count(), chars.len());
+        for n in 0..=chars.len() {
+            assert_eq!(rope.chars().nth(n), chars.get(n).copied());
+            let mut iter = rope.chars();
+            assert_eq!(iter.nth(n / 2), chars.get(n / 2).copied());
+            assert_eq!(iter.nth(n / 2), chars.get(n / 2 * 2 + 1).copied());
+            assert_eq!(rope.chars().skip(n).count(), chars.len() - n);
+            assert_eq!(rope.slice_chars(n..chars.len()).count(), chars.len() - n);
+        }
+        for n in chars.len()..chars.len() + 2 {
+            // Skipping past the end exhausts the iterator.
+            let mut iter = rope.chars();
+            assert_eq!(iter.nth(n), None);
+            assert_eq!(iter.next(), None);
+        }
 
         let s = rope.to_string();
+        for end in 0..=rope.len_chars() {
+            let mut rev = rope.rev_chunks_before(end).collect::<Vec<_>>();
+            rev.reverse();
+            for &(s, len) in &rev {
+                assert_eq!(count_chars(s), len);
+                assert_ne!(len, 0);
+            }
+            let str = rev.iter().map(|(s, _)| *s).collect::<String>();
+            assert_eq!(str, &s[..str_chars_to_bytes(&s, end)]);
+
+            let expected = chars[..end].iter().rev().copied().collect::<Vec<_>>();
+            assert_eq!(rope.chars_before(end).collect::<Vec<_>>(), expected);
+            assert_eq!(rope.chars_before(end).len(), end);
+            let mut iter = rope.chars_before(end);
+            assert_eq!(iter.nth(end / 2), expected.get(end / 2).copied());
+            assert_eq!(iter.count(), end - (end / 2 + 1).min(end));
+
+            let mut iter = rope.chars_before(end);
+            assert_eq!(iter.nth(end), None);
+            assert_eq!(iter.len(), 0);
+            assert_eq!(iter.next(), None);
+        }
+        assert_eq!(rope.chars_rev().collect::<Vec<_>>(), chars.iter().rev().copied().collect::<Vec<_>>());
+
         for start in 0..=rope.len_chars() {
+            let mut visited = String::new();
+            let result = rope.visit_chunks_from(start, |s, len| {
+                assert_eq!(count_chars(s), len);
+                assert_ne!(len, 0);
+                visited.push_str(s);
+                ControlFlow::<()>::Continue(())
+            });
+            assert_eq!(result, ControlFlow::Continue(()));
+            assert_eq!(visited, rope.slice_chars(start..rope.len_chars()).collect::<String>());
+
             let iter = rope.slice_chars(start..rope.len_chars());
             let str = iter.collect::<String>();
+            assert_eq!(rope.chunks_in_range(start..start + 5).collect::<String>(),
+                rope.slice_chars(start..(start + 5).min(rope.len_chars())).collect::<String>());
 
             let byte_start = str_chars_to_bytes(&s, start);
             assert_eq!(str, &s[byte_start..]);
@@ -304,6 +752,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn eq_slice() {
+        let s = "κό𝕐𝕆😘σμε XXXaaaaaaaaaaaaaaaaaaaaaaaaaaXXX";
+        let rope = JumpRope::from(s);
+        let len = rope.len_chars();
+        for start in 0..=len {
+            for end in start..=len {
+                let expected = rope.slice_chars(start..end).collect::<String>();
+                assert!(rope.eq_slice(start..end, &expected));
+                assert!(!rope.eq_slice(start..end, &format!("{}x", expected)));
+            }
+        }
+    }
+
     #[test]
     fn iter_non_ascii() {
         check(&JumpRope::from("κό𝕐𝕆😘σμε"));
@@ -312,7 +774,7 @@ mod tests {
     #[test]
     fn iter_chars_tricky() {
         let mut rope = JumpRope::new();
-        rope.extend(std::iter::repeat("x").take(NODE_STR_SIZE * 2));
+        rope.extend(core::iter::repeat("x").take(NODE_STR_SIZE * 2));
         check(&rope);
     }
 }
\ No newline at end of file
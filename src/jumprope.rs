@@ -9,14 +9,22 @@
 
 // use rope::*;
 
-use std::{mem, ptr, str};
-use std::alloc::{alloc, dealloc, Layout};
-use std::cmp::min;
-use std::fmt::{Debug, Display, Formatter};
-use std::ops::Range;
-use rand::prelude::*;
-use rand::Rng;
+use core::{mem, ptr, str};
+use alloc::alloc::{alloc, dealloc, Layout};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::{Add, AddAssign, Range};
+#[cfg(debug_assertions)]
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "rand")]
+use rand::{RngCore, SeedableRng};
 use crate::gapbuffer::GapBuffer;
+use crate::ops::ReversibleOp;
+use crate::error::{PosError, RangeError};
+use crate::memory::MemoryWatch;
 use crate::utils::*;
 // use crate::params::*;
 
@@ -25,63 +33,171 @@ use crate::utils::*;
 //const NODE_SIZE: usize = 136;
 
 // The likelyhood (out of 256) a node will have height (n+1) instead of n
-const BIAS: u8 = 65;
+pub(crate) const DEFAULT_BIAS: u8 = 65;
 // const BIAS: u8 = XX_BIAS;
 
 // The rope will become less efficient after the string is 2 ^ ROPE_MAX_HEIGHT nodes.
 
+// Debug builds use tiny nodes to exercise the node splitting code. Release builds can pick a
+// smaller or larger node size using the small-nodes and large-nodes features. If both are enabled,
+// small-nodes wins.
 #[cfg(debug_assertions)]
 pub(crate) const NODE_STR_SIZE: usize = 10;
-#[cfg(not(debug_assertions))]
+#[cfg(all(not(debug_assertions), feature = "small-nodes"))]
+pub(crate) const NODE_STR_SIZE: usize = 128;
+#[cfg(all(not(debug_assertions), feature = "large-nodes", not(feature = "small-nodes")))]
+pub(crate) const NODE_STR_SIZE: usize = 1024;
+#[cfg(all(not(debug_assertions), not(feature = "small-nodes"), not(feature = "large-nodes")))]
 pub(crate) const NODE_STR_SIZE: usize = 392;
 // pub(crate) const NODE_STR_SIZE: usize = XX_SIZE;
 
-const MAX_HEIGHT: usize = 20;//NODE_STR_SIZE / mem::size_of::<SkipEntry>();
+pub(crate) const MAX_HEIGHT: usize = 20;//NODE_STR_SIZE / mem::size_of::<SkipEntry>();
 const MAX_HEIGHT_U8: u8 = MAX_HEIGHT as u8;
 
 // Using StdRng notably increases wasm code size, providing some tiny extra protection against
 // ddos attacks. See main module documentation for details.
 #[cfg(feature = "ddos_protection")]
-type RopeRng = StdRng;
-#[cfg(not(feature = "ddos_protection"))]
-type RopeRng = SmallRng;
+pub(crate) type RopeRng = rand::rngs::StdRng;
+#[cfg(all(feature = "rand", not(feature = "ddos_protection")))]
+pub(crate) type RopeRng = rand::rngs::SmallRng;
+#[cfg(not(feature = "rand"))]
+pub(crate) type RopeRng = SplitMix64;
+
+#[cfg(feature = "ddos_protection")]
+pub(crate) const RNG_NAME: &str = "StdRng";
+#[cfg(all(feature = "rand", not(feature = "ddos_protection")))]
+pub(crate) const RNG_NAME: &str = "SmallRng";
+#[cfg(not(feature = "rand"))]
+pub(crate) const RNG_NAME: &str = "SplitMix64";
+
+/// Where the heights of new nodes come from. Usually this is an RNG, but users can supply their
+/// own function via [`JumpRope::new_with_heights`].
+// The rope is already big because the head node is inline, so boxing the RNG wouldn't save much.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum HeightSource {
+    Rng(RopeRng),
+    Custom(Box<dyn FnMut() -> u8>),
+}
+
+impl HeightSource {
+    fn next_height(&mut self, bias: u8) -> u8 {
+        match self {
+            HeightSource::Rng(rng) => random_height(rng, bias),
+            // The head node ends up 1 taller than the tallest node, and that needs to fit too.
+            HeightSource::Custom(f) => f().clamp(1, MAX_HEIGHT_U8 - 1),
+        }
+    }
+}
+
+// Held while the rope is being modified. In debug builds this sets a flag (like RefCell's borrow
+// flag) so we can panic with a clear message if the rope is modified re-entrantly or from two
+// threads at once. That can only happen through unsafe code, but the internal pointer juggling
+// would silently corrupt the rope. In release builds this does nothing.
+pub(crate) struct MutationGuard(#[cfg(debug_assertions)] *const AtomicBool);
+
+#[cfg(debug_assertions)]
+impl Drop for MutationGuard {
+    fn drop(&mut self) {
+        unsafe { (*self.0).store(false, Ordering::Release); }
+    }
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "modified while another modification was in progress")]
+fn test_reentrant_mutation() {
+    let rope = JumpRope::new();
+    let _a = rope.begin_mutation();
+    let _b = rope.begin_mutation();
+}
 
+/// A rope with the default tuning. This is what you want unless you're benchmarking node sizes. See
+/// [`JumpRopeCustom`].
+pub type JumpRope = JumpRopeCustom<NODE_STR_SIZE, DEFAULT_BIAS>;
 
 // The node structure is designed in a very fancy way which would be more at home in C or something
 // like that. The basic idea is that the node structure is fixed size in memory, but the proportion
 // of that space taken up by characters and by the height are different depentant on a node's
 // height.
+/// A rope with its node size and skip list bias chosen at compile time. Most code should use the
+/// [`JumpRope`] alias, which uses the default tuning.
+///
+/// - `NODE` is the number of bytes of text stored in each node. Bigger nodes make edits a bit
+///   slower, and reads and memory use a bit better. It must be between 4 and 65535.
+/// - `BIAS` is the probability (out of 256) that a node is one level taller than the level below
+///   it. The default is 65.
+///
+/// Each tuning is its own type, so several can be used in one program - for example to compare
+/// them against a real workload. Editing, iteration, recording and segmentation work with every
+/// tuning. The other helpers in this crate (for lines, searching, files and so on) are only
+/// implemented for [`JumpRope`].
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// type BigRope = JumpRopeCustom<4096, 65>;
+///
+/// let mut rope = BigRope::from("hi there");
+/// rope.insert(2, "!");
+/// assert_eq!(rope, "hi! there");
+/// assert_eq!(BigRope::node_capacity(), 4096);
+/// ```
 #[repr(C)]
-pub struct JumpRope {
-    rng: RopeRng,
+pub struct JumpRopeCustom<const NODE: usize, const BIAS: u8> {
+    pub(crate) heights: HeightSource,
     // The total number of characters in the rope
     // num_chars: usize,
 
     // The total number of bytes which the characters in the rope take up
     num_bytes: usize,
 
+    // If we're inside with_recording(), edits are logged here.
+    pub(crate) recording: Option<Vec<ReversibleOp>>,
+
+    // The number of bytes allocated for nodes (not including the head).
+    pub(crate) node_mem: usize,
+
+    // The ID to give the next node which is created or modified. IDs are never reused.
+    next_node_id: u64,
+
+    // Empty nodes allocated by reserve_end(), which are used up before allocating any more. They
+    // are included in node_mem.
+    spare_nodes: Vec<*mut Node<NODE>>,
+    pub(crate) memory_watch: Option<Box<MemoryWatch>>,
+
+    #[cfg(debug_assertions)]
+    mutating: AtomicBool,
+
+    // The cursor left behind by the last insert or remove, so the next edit at the same spot
+    // (like a user typing) doesn't need to search the skip list again.
+    edit_cache: Option<EditCache<NODE>>,
+
     // The first node is inline. The height is the max height we've ever used in the rope + 1. The
     // highest entry points "past the end" of the list, including the entire list length.
-    pub(super) head: Node,
+    pub(super) head: Node<NODE>,
 
     // This is so dirty. The first node is embedded in JumpRope; but we need to allocate enough room
     // for height to get arbitrarily large. I could insist on JumpRope always getting allocated on
     // the heap, but for small strings its better that the first string is just on the stack. So
     // this struct is repr(C) and I'm just padding out the struct directly.
-    nexts: [SkipEntry; MAX_HEIGHT+1],
+    nexts: [SkipEntry<NODE>; MAX_HEIGHT+1],
 
     // The nexts array contains an extra entry at [head.height-1] the which points past the skip
     // list. The size is the size of the entire list.
 }
 
 #[repr(C)] // Prevent parameter reordering.
-pub(super) struct Node {
+pub(super) struct Node<const N: usize = NODE_STR_SIZE> {
     // The first num_bytes of this store a valid utf8 string.
     // str: [u8; NODE_STR_SIZE],
     //
     // // Number of bytes in str in use
     // num_bytes: u8,
-    pub(super) str: GapBuffer<NODE_STR_SIZE>,
+    pub(super) str: GapBuffer<N>,
+
+    // Changes whenever the node's content changes. See JumpRope::chunks_with_ids.
+    pub(super) id: u64,
 
     // Height of nexts array.
     pub(super) height: u8,
@@ -91,15 +207,49 @@ pub(super) struct Node {
     // This array actually has the size of height; but we dynamically allocate the structure on the
     // heap to avoid wasting memory.
     // TODO: Honestly this memory saving is very small anyway. Reconsider this choice.
-    nexts: [SkipEntry; 0],
+    nexts: [SkipEntry<N>; 0],
+}
+
+/// Where [`JumpRope::overwrite_with_limit`] stops overwriting existing characters.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum OverwriteLimit {
+    /// Stop at the end of the current line. The newline itself is not overwritten.
+    Line,
+    /// Stop at the end of the document.
+    Document,
 }
 
+/// The kind of boundary [`JumpRope::sanitize_range`] rounds range endpoints to.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
-pub(super) struct SkipEntry {
-    pub(super) node: *mut Node,
+#[non_exhaustive]
+pub enum Boundary {
+    /// Unicode characters. Every character position is a valid boundary, so ranges are only
+    /// clamped to the document.
+    Char,
+    /// Extended grapheme clusters. This is only available with the `unicode-segmentation` feature
+    /// enabled.
+    #[cfg(feature = "unicode-segmentation")]
+    Grapheme,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(super) struct SkipEntry<const N: usize = NODE_STR_SIZE> {
+    pub(super) node: *mut Node<N>,
     /// The number of *characters* between the start of the current node and the start of the next
     /// node.
     pub(super) skip_chars: usize,
+    /// The number of UTF8 bytes in the same span of characters.
+    pub(super) skip_bytes: usize,
+    /// The number of newlines (`'\n'`) in the same span of characters.
+    ///
+    /// In a [`RopeCursor`], skip_bytes and skip_lines instead count the bytes and newlines between
+    /// the start of the node and the start of the cursor's current (level 0) node.
+    pub(super) skip_lines: usize,
+    /// The number of characters outside the basic multilingual plane in the same span of
+    /// characters. Each of these takes 2 UTF-16 code units (a surrogate pair). Cursors count these
+    /// the same way as skip_bytes.
+    #[cfg(feature = "wchar_conversion")]
+    pub(super) skip_pairs: usize,
 }
 
 // Make sure nexts uses correct alignment. This should be guaranteed by repr(C)
@@ -110,54 +260,64 @@ fn test_align() {
     assert!(mem::align_of::<Check>() >= mem::align_of::<SkipEntry>());
 }
 
-fn random_height(rng: &mut RopeRng) -> u8 {
+pub(crate) fn random_height(rng: &mut RopeRng, bias: u8) -> u8 {
     let mut h: u8 = 1;
     // TODO: This is using the thread_local rng, which is secure (?!). Check
     // this is actually fast.
-    while h < MAX_HEIGHT_U8 && rng.gen::<u8>() < BIAS { h+=1; }
+    // The head node ends up 1 taller than the tallest node, so leave room for it. With a high bias
+    // this limit is actually reached.
+    while h < MAX_HEIGHT_U8 - 1 && (rng.next_u32() as u8) < bias { h+=1; }
     h
 }
 
 
-impl SkipEntry {
+impl<const N: usize> SkipEntry<N> {
     fn new() -> Self {
-        SkipEntry { node: ptr::null_mut(), skip_chars: 0 }
+        SkipEntry {
+            node: ptr::null_mut(),
+            skip_chars: 0,
+            skip_bytes: 0,
+            skip_lines: 0,
+            #[cfg(feature = "wchar_conversion")]
+            skip_pairs: 0,
+        }
     }
 }
 
-impl Node {
+impl<const N: usize> Node<N> {
     pub(super) fn next_ptr(&self) -> *const Self { // TODO: Pin.
         self.first_next().node
     }
 
     // Do I need to be explicit about the lifetime of the references being tied
     // to the lifetime of the node?
-    fn nexts(&self) -> &[SkipEntry] {
+    pub(super) fn nexts(&self) -> &[SkipEntry<N>] {
         unsafe {
-            std::slice::from_raw_parts(self.nexts.as_ptr(), self.height as usize)
+            core::slice::from_raw_parts(self.nexts.as_ptr(), self.height as usize)
         }
     }
 
-    fn nexts_mut(&mut self) -> &mut [SkipEntry] {
+    fn nexts_mut(&mut self) -> &mut [SkipEntry<N>] {
         unsafe {
-            std::slice::from_raw_parts_mut(self.nexts.as_mut_ptr(), self.height as usize)
+            core::slice::from_raw_parts_mut(self.nexts.as_mut_ptr(), self.height as usize)
         }
     }
 
-    fn layout_with_height(height: u8) -> Layout {
+    pub(crate) fn layout_with_height(height: u8) -> Layout {
         Layout::from_size_align(
-            mem::size_of::<Node>() + mem::size_of::<SkipEntry>() * (height as usize),
-            mem::align_of::<Node>()).unwrap()
+            mem::size_of::<Self>() + mem::size_of::<SkipEntry<N>>() * (height as usize),
+            mem::align_of::<Self>()).unwrap()
     }
 
-    fn alloc_with_height(height: u8, content: &str) -> *mut Node {
+    pub(crate) fn alloc_with_height(height: u8, content: &str) -> *mut Self {
         //println!("height {} {}", height, max_height());
         assert!(height >= 1 && height <= MAX_HEIGHT_U8);
 
         unsafe {
-            let node = alloc(Self::layout_with_height(height)) as *mut Node;
+            let node = alloc(Self::layout_with_height(height)) as *mut Self;
             (*node) = Node {
                 str: GapBuffer::new_from_str(content),
+                id: 0,
                 height,
                 nexts: [],
             };
@@ -170,11 +330,8 @@ impl Node {
         }
     }
 
-    fn alloc(rng: &mut RopeRng, content: &str) -> *mut Node {
-        Self::alloc_with_height(random_height(rng), content)
-    }
 
-    unsafe fn free(p: *mut Node) {
+    pub(crate) unsafe fn free(p: *mut Self) {
         dealloc(p as *mut u8, Self::layout_with_height((*p).height));
     }
 
@@ -186,43 +343,99 @@ impl Node {
     }
 
     // The height is at least 1, so this is always valid.
-    pub(super) fn first_next<'a>(&self) -> &'a SkipEntry {
+    pub(super) fn first_next<'a>(&self) -> &'a SkipEntry<N> {
         unsafe { &*self.nexts.as_ptr() }
     }
 
-    fn first_next_mut<'a>(&mut self) -> &'a mut SkipEntry {
+    fn first_next_mut<'a>(&mut self) -> &'a mut SkipEntry<N> {
         unsafe { &mut *self.nexts.as_mut_ptr() }
     }
 
     pub(super) fn num_chars(&self) -> usize {
         self.first_next().skip_chars
     }
+
+    pub(super) fn num_bytes(&self) -> usize {
+        self.first_next().skip_bytes
+    }
+
+    pub(super) fn num_lines(&self) -> usize {
+        self.first_next().skip_lines
+    }
+
+    #[cfg(feature = "wchar_conversion")]
+    pub(super) fn num_pairs(&self) -> usize {
+        self.first_next().skip_pairs
+    }
 }
 
 #[derive(Debug, Clone)]
-pub(crate) struct RopeCursor([SkipEntry; MAX_HEIGHT+1]);
+pub(crate) struct RopeCursor<const N: usize = NODE_STR_SIZE>([SkipEntry<N>; MAX_HEIGHT+1]);
 
-impl RopeCursor {
-    fn update_offsets(&mut self, height: usize, by: isize) {
+// A cursor cached between edits. Every change to the content of the rope renews a node's ID (which
+// bumps next_node_id), and cursors point at the inline head node, so the cursor is only still valid
+// if neither of those have changed since it was stored.
+#[derive(Debug, Clone)]
+struct EditCache<const N: usize> {
+    cursor: RopeCursor<N>,
+    next_node_id: u64,
+    head: *const Node<N>,
+}
+
+impl<const N: usize> RopeCursor<N> {
+    fn update_offsets(&mut self, height: usize, by: isize, by_bytes: isize, by_lines: isize) {
         for i in 0..height {
             unsafe {
                 // This is weird but makes sense when you realise the nexts in
                 // the cursor are pointers into the elements that have the
                 // actual pointers.
                 // Also adding a usize + isize is awful in rust :/
-                let skip = &mut (*self.0[i].node).nexts_mut()[i].skip_chars;
-                *skip = skip.wrapping_add(by as usize);
+                let entry = &mut (*self.0[i].node).nexts_mut()[i];
+                entry.skip_chars = entry.skip_chars.wrapping_add(by as usize);
+                entry.skip_bytes = entry.skip_bytes.wrapping_add(by_bytes as usize);
+                entry.skip_lines = entry.skip_lines.wrapping_add(by_lines as usize);
+            }
+        }
+    }
+
+    #[cfg(feature = "wchar_conversion")]
+    fn update_pair_offsets(&mut self, height: usize, by: isize) {
+        for i in 0..height {
+            unsafe {
+                let entry = &mut (*self.0[i].node).nexts_mut()[i];
+                entry.skip_pairs = entry.skip_pairs.wrapping_add(by as usize);
+            }
+        }
+    }
+
+    // Move the cursor from somewhere in the current node to the start of the next node, which
+    // must exist.
+    pub(crate) unsafe fn advance_node(&mut self, height: usize) {
+        let here = &*self.here_ptr();
+        let next = &mut *here.first_next().node;
+        let chars = here.num_chars() - self.local_char_pos();
+        let (bytes, lines) = (here.num_bytes(), here.num_lines());
+        for (i, e) in self.0[..height].iter_mut().enumerate() {
+            if i < next.height as usize {
+                *e = SkipEntry { node: next, ..SkipEntry::new() };
+            } else {
+                e.skip_chars += chars;
+                e.skip_bytes += bytes;
+                e.skip_lines += lines;
+                #[cfg(feature = "wchar_conversion")] {
+                    e.skip_pairs += here.num_pairs();
+                }
             }
         }
     }
 
-    fn move_within_node(&mut self, height: usize, by: isize) {
+    pub(crate) fn move_within_node(&mut self, height: usize, by: isize) {
         for e in &mut self.0[..height] {
             e.skip_chars = e.skip_chars.wrapping_add(by as usize);
         }
     }
 
-    pub(crate) fn here_ptr(&self) -> *mut Node {
+    pub(crate) fn here_ptr(&self) -> *mut Node<N> {
         self.0[0].node
     }
 
@@ -233,21 +446,79 @@ impl RopeCursor {
     pub(crate) fn local_char_pos(&self) -> usize {
         self.0[0].skip_chars
     }
+
+    // The byte offset of the cursor from the start of the rope.
+    pub(crate) fn global_byte_pos(&self, head_height: u8) -> usize {
+        let here = unsafe { &*self.here_ptr() };
+        self.0[head_height as usize - 1].skip_bytes + here.str.count_bytes(self.local_char_pos())
+    }
+
+    // The number of bytes before the start of the cursor's current node.
+    pub(crate) fn global_bytes_before_node(&self, head_height: u8) -> usize {
+        self.0[head_height as usize - 1].skip_bytes
+    }
+
+    // The number of newlines before the start of the cursor's current node.
+    pub(crate) fn global_lines_before_node(&self, head_height: u8) -> usize {
+        self.0[head_height as usize - 1].skip_lines
+    }
+
+    // The number of surrogate pairs before the start of the cursor's current node.
+    #[cfg(feature = "wchar_conversion")]
+    pub(crate) fn global_pairs_before_node(&self, head_height: u8) -> usize {
+        self.0[head_height as usize - 1].skip_pairs
+    }
 }
 
 /// A rope is a "rich string" data structure for storing fancy strings, like the contents of a
 /// text editor. See module level documentation for more information.
-impl JumpRope {
-    fn new_with_rng(rng: RopeRng) -> Self {
-        JumpRope {
-            rng,
+impl<const NODE: usize, const BIAS: u8> JumpRopeCustom<NODE, BIAS> {
+    #[inline]
+    pub(crate) fn begin_mutation(&self) -> MutationGuard {
+        #[cfg(debug_assertions)] {
+            if self.mutating.swap(true, Ordering::Acquire) {
+                panic!("JumpRope was modified while another modification was in progress. This \
+                    happens if the rope is modified re-entrantly (eg from a callback) or from two \
+                    threads at once.");
+            }
+            MutationGuard(&self.mutating)
+        }
+        #[cfg(not(debug_assertions))]
+        MutationGuard()
+    }
+
+    // Give the node a new ID, after its content has been modified.
+    #[inline]
+    fn renew_id(&mut self, node: *mut Node<NODE>) {
+        unsafe { (*node).id = self.next_node_id; }
+        self.next_node_id += 1;
+    }
+
+    // Checked when a rope is created, so invalid tunings fail to compile.
+    const VALID_NODE_SIZE: () = assert!(NODE >= 4 && NODE <= u16::MAX as usize,
+        "JumpRopeCustom node size must be between 4 and 65535 bytes");
+
+    fn new_with_height_source(heights: HeightSource) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID_NODE_SIZE;
+        JumpRopeCustom {
+            heights,
             num_bytes: 0,
+            recording: None,
+            node_mem: 0,
+            next_node_id: 1,
+            spare_nodes: Vec::new(),
+            memory_watch: None,
+            #[cfg(debug_assertions)]
+            mutating: AtomicBool::new(false),
+            edit_cache: None,
             // nexts: [SkipEntry::new(); MAX_HEIGHT],
 
             // We don't ever store characters in the head node, but the height
             // here is the maximum height of the entire rope.
             head: Node {
                 str: GapBuffer::new(),
+                id: 0,
                 height: 1,
                 nexts: [],
             },
@@ -261,19 +532,24 @@ impl JumpRope {
     /// But when compiled for testing (or in debug mode), we use a fixed seed in order to keep tests
     /// fully deterministic.
     ///
+    /// Without the `std` feature there is no entropy source available, so this method always uses
+    /// a fixed seed. Use [`new_from_seed`](Self::new_from_seed) with your own entropy if you need
+    /// DoS protection in that configuration.
+    ///
     /// Note using this method in wasm significantly increases bundle size. Use
     /// [`new_with_seed`](Self::new_from_seed) instead.
     pub fn new() -> Self {
-        if cfg!(test) || cfg!(debug_assertions) {
-            Self::new_from_seed(123)
-        } else {
-            Self::new_from_entropy()
+        #[cfg(feature = "std")]
+        if !(cfg!(test) || cfg!(debug_assertions)) {
+            return Self::new_from_entropy();
         }
+        Self::new_from_seed(123)
     }
 
     /// Creates a new, empty rope seeded from an entropy source.
+    #[cfg(feature = "std")]
     pub fn new_from_entropy() -> Self {
-        Self::new_with_rng(RopeRng::from_entropy())
+        Self::new_with_height_source(HeightSource::Rng(RopeRng::from_entropy()))
     }
 
     /// Creates a new, empty rope using an RNG seeded from the passed u64 parameter.
@@ -284,7 +560,77 @@ impl JumpRope {
     ///
     /// - Jumprope will always use a fixed seed
     pub fn new_from_seed(seed: u64) -> Self {
-        Self::new_with_rng(RopeRng::seed_from_u64(seed))
+        Self::new_with_height_source(HeightSource::Rng(RopeRng::seed_from_u64(seed)))
+    }
+
+    // An RNG for seeding the ropes made from this one's content (eg by splitting it up), so making
+    // lots of ropes doesn't read from the OS entropy source for each one.
+    pub(crate) fn child_seeds(&mut self) -> RopeRng {
+        match &mut self.heights {
+            HeightSource::Rng(rng) => RopeRng::seed_from_u64(rng.next_u64()),
+            // Custom heights are for deterministic tests and benchmarks, so use the same fixed seed
+            // as new() does in tests.
+            HeightSource::Custom(_) => RopeRng::seed_from_u64(123),
+        }
+    }
+
+    /// Creates a new, empty rope which calls the passed function to pick the height of each new
+    /// node in the underlying skip list, instead of using an RNG.
+    ///
+    /// Heights are clamped to the range supported by the rope. For good performance the heights
+    /// should be geometrically distributed, with each height about 4 times less likely than the one
+    /// below it. A constant height degrades the rope to a linked list.
+    ///
+    /// Clones of the rope use the default RNG.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut heights = [1, 1, 2, 1, 1, 1, 1, 3].into_iter().cycle();
+    /// let mut rope = JumpRope::new_with_heights(move || heights.next().unwrap());
+    /// rope.insert(0, "Some text which is spread across a few nodes");
+    /// assert_eq!(rope, "Some text which is spread across a few nodes");
+    /// ```
+    pub fn new_with_heights<F: FnMut() -> u8 + 'static>(f: F) -> Self {
+        Self::new_with_height_source(HeightSource::Custom(Box::new(f)))
+    }
+
+    /// Creates a new rope from a list of lines, with a newline character inserted between each
+    /// one. The rope is loaded in a single pass.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from_lines(["one", "two", "three"]);
+    /// assert_eq!(rope, "one\ntwo\nthree");
+    /// ```
+    pub fn from_lines<I: IntoIterator<Item = S>, S: AsRef<str>>(lines: I) -> Self {
+        Self::join("\n", lines)
+    }
+
+    /// Creates a new rope by concatenating the passed fragments, with `separator` inserted between
+    /// each one. This is equivalent to `JumpRope::from(fragments.join(separator))`, but it streams
+    /// the content straight into the rope without building an intermediate string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::join(", ", ["a", "b", "c"]);
+    /// assert_eq!(rope, "a, b, c");
+    /// ```
+    pub fn join<I: IntoIterator<Item = S>, S: AsRef<str>>(separator: &str, fragments: I) -> Self {
+        let mut rope = Self::new();
+        let mut cursor = rope.cursor_at_start();
+        for (i, fragment) in fragments.into_iter().enumerate() {
+            unsafe {
+                if i > 0 { rope.insert_at_cursor(&mut cursor, separator); }
+                rope.insert_at_cursor(&mut cursor, fragment.as_ref());
+            }
+        }
+        rope
     }
 
     fn new_from_str(s: &str) -> Self {
@@ -315,16 +661,56 @@ impl JumpRope {
         self.head.nexts()[self.head.height as usize - 1].skip_chars
     }
 
+    // The number of newlines in the rope. Like the number of characters, this is stored in the
+    // head's highest skip entry.
+    pub(crate) fn len_newlines(&self) -> usize {
+        self.head.nexts()[self.head.height as usize - 1].skip_lines
+    }
+
+    // Get a cursor for editing at char_pos, the same as cursor_at_char(char_pos, true). If the
+    // position is inside the node where the last edit happened, the cached cursor is moved instead
+    // of searching from the top of the skip list.
+    fn edit_cursor_at(&mut self, char_pos: usize) -> RopeCursor<NODE> {
+        if let Some(cache) = self.edit_cache.take() {
+            if cache.next_node_id == self.next_node_id && ptr::eq(cache.head, &self.head) {
+                let mut cursor = cache.cursor;
+                let height = self.head.height;
+                let here = unsafe { &*cursor.here_ptr() };
+                let pos = cursor.global_char_pos(height);
+                let node_start = pos - cursor.local_char_pos();
+                // Positions at the very start of a node need the cursor to stick to the end of the
+                // previous node, so they're left to cursor_at_char.
+                if char_pos > node_start && char_pos <= node_start + here.num_chars() {
+                    cursor.move_within_node(height as usize, char_pos as isize - pos as isize);
+                    return cursor;
+                }
+            }
+        }
+        self.cursor_at_char(char_pos, true)
+    }
+
+    fn cache_edit_cursor(&mut self, cursor: RopeCursor<NODE>) {
+        self.edit_cache = Some(EditCache {
+            cursor,
+            next_node_id: self.next_node_id,
+            head: &self.head,
+        });
+    }
+
     // Internal function for navigating to a particular character offset in the rope.  The function
     // returns the list of nodes which point past the position, as well as offsets of how far into
     // their character lists the specified characters are.
-    pub(crate) fn cursor_at_char(&self, char_pos: usize, stick_end: bool) -> RopeCursor {
+    pub(crate) fn cursor_at_char(&self, char_pos: usize, stick_end: bool) -> RopeCursor<NODE> {
         assert!(char_pos <= self.len_chars());
 
-        let mut e: *const Node = &self.head;
+        let mut e: *const Node<NODE> = &self.head;
         let mut height = self.head.height as usize - 1;
         
         let mut offset = char_pos; // How many more chars to skip
+        let mut bytes = 0; // Bytes and newlines skipped so far
+        let mut lines = 0;
+        #[cfg(feature = "wchar_conversion")]
+        let mut pairs = 0;
 
         let mut iter = RopeCursor([SkipEntry::new(); MAX_HEIGHT+1]);
 
@@ -336,49 +722,153 @@ impl JumpRope {
                 // Go right.
                 assert!(e == &self.head || !en.str.is_empty());
                 offset -= skip;
+                bytes += next.skip_bytes;
+                lines += next.skip_lines;
+                #[cfg(feature = "wchar_conversion")] {
+                    pairs += next.skip_pairs;
+                }
                 e = next.node;
                 assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
             } else {
                 // Record this and go down.
                 iter.0[height] = SkipEntry {
                     skip_chars: offset,
-                    node: e as *mut Node, // This is pretty gross
+                    node: e as *mut Node<NODE>, // This is pretty gross
+                    skip_bytes: bytes,
+                    skip_lines: lines,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: pairs,
                 };
 
                 if height == 0 { break; } else { height -= 1; }
             }
         };
 
-        assert!(offset <= NODE_STR_SIZE);
+        // Each entry recorded the bytes and newlines skipped before it. Convert them to the bytes
+        // and newlines skipped after it, up to the start of the final node.
+        for entry in &mut iter.0[..self.head.height as usize] {
+            entry.skip_bytes = bytes - entry.skip_bytes;
+            entry.skip_lines = lines - entry.skip_lines;
+            #[cfg(feature = "wchar_conversion")] {
+                entry.skip_pairs = pairs - entry.skip_pairs;
+            }
+        }
+
+        assert!(offset <= NODE);
         iter
     }
 
-    fn cursor_at_start(&self) -> RopeCursor {
+    // Like cursor_at_char, but navigating to a byte offset instead. If the byte offset is inside a
+    // character, the cursor is placed at the start of that character. Also returns whether
+    // byte_pos was on a character boundary.
+    pub(crate) fn cursor_at_byte(&self, byte_pos: usize, stick_end: bool) -> (RopeCursor<NODE>, bool) {
+        assert!(byte_pos <= self.num_bytes);
+
+        let mut e: *const Node<NODE> = &self.head;
+        let mut height = self.head.height as usize - 1;
+
+        let mut offset = byte_pos; // How many more bytes to skip
+        let mut chars = 0; // Characters and newlines skipped so far
+        let mut lines = 0;
+        #[cfg(feature = "wchar_conversion")]
+        let mut pairs = 0;
+
+        let mut iter = RopeCursor([SkipEntry::new(); MAX_HEIGHT+1]);
+
+        loop {
+            let en = unsafe { &*e };
+            let next = en.nexts()[height];
+            let skip = next.skip_bytes;
+            if offset > skip || (!stick_end && offset == skip && !next.node.is_null()) {
+                // Go right.
+                offset -= skip;
+                chars += next.skip_chars;
+                lines += next.skip_lines;
+                #[cfg(feature = "wchar_conversion")] {
+                    pairs += next.skip_pairs;
+                }
+                e = next.node;
+                assert!(!e.is_null(), "Internal constraint violation: Reached rope end prematurely");
+            } else {
+                // Record this and go down. The offsets are fixed up below.
+                iter.0[height] = SkipEntry {
+                    node: e as *mut Node<NODE>,
+                    skip_chars: chars,
+                    skip_bytes: byte_pos - offset,
+                    skip_lines: lines,
+                    #[cfg(feature = "wchar_conversion")]
+                    skip_pairs: pairs,
+                };
+
+                if height == 0 { break; } else { height -= 1; }
+            }
+        }
+
+        let node = unsafe { &*e };
+        let local_chars = node.str.count_chars(offset);
+        for entry in &mut iter.0[..self.head.height as usize] {
+            entry.skip_chars = chars - entry.skip_chars + local_chars;
+            entry.skip_bytes = byte_pos - offset - entry.skip_bytes;
+            entry.skip_lines = lines - entry.skip_lines;
+            #[cfg(feature = "wchar_conversion")] {
+                entry.skip_pairs = pairs - entry.skip_pairs;
+            }
+        }
+
+        (iter, node.str.is_char_boundary(offset))
+    }
+
+    pub(crate) fn cursor_at_start(&self) -> RopeCursor<NODE> {
         RopeCursor([SkipEntry {
             node: &self.head as *const _ as *mut _,
-            skip_chars: 0
+            ..SkipEntry::new()
         }; MAX_HEIGHT+1])
     }
 
-    fn cursor_at_end(&self) -> RopeCursor {
+    pub(crate) fn cursor_at_end(&self) -> RopeCursor<NODE> {
         self.cursor_at_char(self.len_chars(), true)
     }
 
     // Internal fn to create a new node at the specified iterator filled with the specified
     // content.
-    unsafe fn insert_node_at(&mut self, cursor: &mut RopeCursor, contents: &str, num_chars: usize, update_cursor: bool) {
+    unsafe fn insert_node_at(&mut self, cursor: &mut RopeCursor<NODE>, contents: &str, num_chars: usize, update_cursor: bool) {
         // println!("Insert_node_at {} len {}", contents.len(), self.num_bytes);
         // assert!(contents.len() < NODE_STR_SIZE);
         debug_assert_eq!(count_chars(contents), num_chars);
-        debug_assert!(num_chars <= NODE_STR_SIZE);
+        debug_assert!(num_chars <= NODE);
 
         // TODO: Pin this sucka.
         // let new_node = Pin::new(Node::alloc());
-        let new_node = Node::alloc(&mut self.rng, contents);
+        let (new_node, counted) = match self.spare_nodes.pop() {
+            Some(node) => {
+                (*node).str = GapBuffer::new_from_str(contents);
+                (node, true)
+            }
+            None => (Node::alloc_with_height(self.heights.next_height(BIAS), contents), false),
+        };
         // (*new_node).num_bytes = contents.len() as u8;
         // (*new_node).str[..contents.len()].copy_from_slice(contents.as_bytes());
 
+        self.link_node_at(cursor, new_node, num_chars, update_cursor, counted);
+    }
+
+    // Internal fn to link an already allocated node into the list at the specified cursor. The
+    // node must contain num_chars characters, and its nexts must not be in use. The cursor must
+    // be at the end of its current node. If counted is set, the node's memory is already included
+    // in node_mem (like spare nodes from reserve_end).
+    pub(crate) unsafe fn link_node_at(&mut self, cursor: &mut RopeCursor<NODE>, new_node: *mut Node<NODE>, num_chars: usize, update_cursor: bool, counted: bool) {
+        debug_assert_eq!(count_chars((*new_node).as_str_1()) + count_chars((*new_node).as_str_2()), num_chars);
+        debug_assert_eq!(cursor.local_char_pos(), (*cursor.here_ptr()).num_chars());
+
+        self.renew_id(new_node);
         let new_height = (*new_node).height as usize;
+        let num_bytes = (*new_node).str.len_bytes();
+        let num_lines = count_newlines((*new_node).as_str_1()) + count_newlines((*new_node).as_str_2());
+        // The cursor is at the end of this node, so all of its content is before the cursor.
+        let here = &*cursor.here_ptr();
+        let (here_bytes, here_lines) = (here.num_bytes(), here.num_lines());
+        #[cfg(feature = "wchar_conversion")]
+        let (num_pairs, here_pairs) = (count_surrogate_pairs((*new_node).as_str_1()) + count_surrogate_pairs((*new_node).as_str_2()), here.num_pairs());
 
         let mut head_height = self.head.height as usize;
         while head_height <= new_height {
@@ -395,31 +885,68 @@ impl JumpRope {
             let prev_skip = &mut (*cursor.0[i].node).nexts_mut()[i];
             let nexts = (*new_node).nexts_mut();
             nexts[i].node = prev_skip.node;
+            let cursor_bytes = cursor.0[i].skip_bytes + here_bytes;
+            let cursor_lines = cursor.0[i].skip_lines + here_lines;
             nexts[i].skip_chars = num_chars + prev_skip.skip_chars - cursor.0[i].skip_chars;
+            nexts[i].skip_bytes = num_bytes + prev_skip.skip_bytes - cursor_bytes;
+            nexts[i].skip_lines = num_lines + prev_skip.skip_lines - cursor_lines;
 
             prev_skip.node = new_node;
             prev_skip.skip_chars = cursor.0[i].skip_chars;
+            prev_skip.skip_bytes = cursor_bytes;
+            prev_skip.skip_lines = cursor_lines;
+
+            #[cfg(feature = "wchar_conversion")] {
+                let cursor_pairs = cursor.0[i].skip_pairs + here_pairs;
+                nexts[i].skip_pairs = num_pairs + prev_skip.skip_pairs - cursor_pairs;
+                prev_skip.skip_pairs = cursor_pairs;
+            }
 
             // & move the iterator to the end of the newly inserted node.
             if update_cursor {
                 cursor.0[i].node = new_node;
                 cursor.0[i].skip_chars = num_chars;
+                cursor.0[i].skip_bytes = 0;
+                cursor.0[i].skip_lines = 0;
+                #[cfg(feature = "wchar_conversion")] {
+                    cursor.0[i].skip_pairs = 0;
+                }
             }
         }
 
         for i in new_height..head_height {
-            (*cursor.0[i].node).nexts_mut()[i].skip_chars += num_chars;
+            let skip = &mut (*cursor.0[i].node).nexts_mut()[i];
+            skip.skip_chars += num_chars;
+            skip.skip_bytes += num_bytes;
+            skip.skip_lines += num_lines;
+            #[cfg(feature = "wchar_conversion")] {
+                skip.skip_pairs += num_pairs;
+            }
             if update_cursor {
                 cursor.0[i].skip_chars += num_chars;
+                cursor.0[i].skip_bytes += here_bytes;
+                cursor.0[i].skip_lines += here_lines;
+                #[cfg(feature = "wchar_conversion")] {
+                    cursor.0[i].skip_pairs += here_pairs;
+                }
             }
         }
 
         // self.nexts[self.head.height as usize - 1].skip_chars += num_chars;
-        self.num_bytes += contents.len();
+        self.num_bytes += num_bytes;
+        if !counted {
+            self.node_mem_grew(Node::<NODE>::layout_with_height(new_height as u8).size());
+        }
+    }
+
+    pub(crate) unsafe fn insert_at_cursor(&mut self, cursor: &mut RopeCursor<NODE>, contents: &str) {
+        self.insert_at_cursor_counted(cursor, contents, count_chars(contents));
     }
 
-    unsafe fn insert_at_cursor(&mut self, cursor: &mut RopeCursor, contents: &str) {
+    // Same as insert_at_cursor, but num_inserted_chars must be the number of characters in contents.
+    unsafe fn insert_at_cursor_counted(&mut self, cursor: &mut RopeCursor<NODE>, contents: &str, num_inserted_chars: usize) {
         if contents.is_empty() { return; }
+        let _guard = self.begin_mutation();
         // iter contains how far (in characters) into the current element to
         // skip. Figure out how much that is in bytes.
         let mut offset_bytes: usize = 0;
@@ -430,13 +957,19 @@ impl JumpRope {
         // We might be able to insert the new data into the current node, depending on
         // how big it is. We'll count the bytes, and also check that its valid utf8.
         let num_inserted_bytes = contents.len();
-        let num_inserted_chars = count_chars(contents);
+        debug_assert_eq!(count_chars(contents), num_inserted_chars);
+        let num_inserted_lines = count_newlines(contents);
+        #[cfg(feature = "wchar_conversion")]
+        let num_inserted_pairs = count_surrogate_pairs(contents);
 
         // Adding this short circuit makes the code about 2% faster for 1% more code
         if (*e).str.gap_start_chars as usize == offset && (*e).str.gap_len as usize >= num_inserted_bytes {
             // Short circuit. If we can just insert all the content right here in the gap, do so.
             (*e).str.insert_in_gap(contents);
-            cursor.update_offsets(self.head.height as usize, num_inserted_chars as isize);
+            self.renew_id(e);
+            cursor.update_offsets(self.head.height as usize, num_inserted_chars as isize, num_inserted_bytes as isize, num_inserted_lines as isize);
+            #[cfg(feature = "wchar_conversion")]
+            cursor.update_pair_offsets(self.head.height as usize, num_inserted_pairs as isize);
             cursor.move_within_node(self.head.height as usize, num_inserted_chars as isize);
             self.num_bytes += num_inserted_bytes;
             return;
@@ -451,7 +984,7 @@ impl JumpRope {
 
         // Can we insert into the current node?
         let current_len_bytes = (*e).str.len_bytes();
-        let mut insert_here = current_len_bytes + num_inserted_bytes <= NODE_STR_SIZE;
+        let mut insert_here = current_len_bytes + num_inserted_bytes <= NODE;
 
         // If we can't insert here, see if we can move the cursor forward and insert into the
         // subsequent node.
@@ -462,16 +995,27 @@ impl JumpRope {
             // - The insert would be at the start of the next node
             // - There's room in the next node
             if let Some(next) = (*e).first_next_mut().node.as_mut() {
-                if next.str.len_bytes() + num_inserted_bytes <= NODE_STR_SIZE {
+                if next.str.len_bytes() + num_inserted_bytes <= NODE {
                     offset_bytes = 0;
 
                     // Could do this with slice::fill but this seems slightly faster.
                     for e in &mut cursor.0[..next.height as usize] {
                         *e = SkipEntry {
                             node: next,
-                            skip_chars: 0
+                            ..SkipEntry::new()
                         };
                     }
+                    // Higher entries now skip the content of the current node too.
+                    let (bytes, lines) = ((*e).num_bytes(), (*e).num_lines());
+                    #[cfg(feature = "wchar_conversion")]
+                    let pairs = (*e).num_pairs();
+                    for e in &mut cursor.0[next.height as usize..self.head.height as usize] {
+                        e.skip_bytes += bytes;
+                        e.skip_lines += lines;
+                        #[cfg(feature = "wchar_conversion")] {
+                            e.skip_pairs += pairs;
+                        }
+                    }
                     e = next;
 
                     insert_here = true;
@@ -483,10 +1027,13 @@ impl JumpRope {
             // First move the current bytes later on in the string.
             let c = &mut (*e).str;
             c.try_insert(offset_bytes, contents).unwrap();
+            self.renew_id(e);
 
             self.num_bytes += num_inserted_bytes;
             // .... aaaand update all the offset amounts.
-            cursor.update_offsets(self.head.height as usize, num_inserted_chars as isize);
+            cursor.update_offsets(self.head.height as usize, num_inserted_chars as isize, num_inserted_bytes as isize, num_inserted_lines as isize);
+            #[cfg(feature = "wchar_conversion")]
+            cursor.update_pair_offsets(self.head.height as usize, num_inserted_pairs as isize);
             cursor.move_within_node(self.head.height as usize, num_inserted_chars as isize);
         } else {
             // There isn't room. We'll need to add at least one new node to the rope.
@@ -494,6 +1041,7 @@ impl JumpRope {
             // If we're not at the end of the current node, we'll need to remove
             // the end of the current node's data and reinsert it later.
             (*e).str.move_gap(offset_bytes);
+            self.renew_id(e);
 
             let num_end_bytes = (*e).str.len_bytes() - offset_bytes;
             let mut num_end_chars: usize = 0;
@@ -504,8 +1052,11 @@ impl JumpRope {
                 // new string's characters into this node after trimming it.
                 let end_str = (*e).str.take_rest();
                 num_end_chars = (*e).num_chars() - offset;
+                let num_end_lines = count_newlines(end_str);
 
-                cursor.update_offsets(self.head.height as usize, -(num_end_chars as isize));
+                cursor.update_offsets(self.head.height as usize, -(num_end_chars as isize), -(num_end_bytes as isize), -(num_end_lines as isize));
+                #[cfg(feature = "wchar_conversion")]
+                cursor.update_pair_offsets(self.head.height as usize, -(count_surrogate_pairs(end_str) as isize));
                 self.num_bytes -= num_end_bytes;
                 Some(end_str)
             } else {
@@ -521,25 +1072,15 @@ impl JumpRope {
             let mut remainder = contents;
             while !remainder.is_empty() {
                 // println!(". {}", remainder);
-                // Find the first index after STR_SIZE bytes
-                let mut byte_pos = 0;
-                let mut char_pos = 0;
-
                 // Find a suitable cut point. We should take as many characters as we can fit in
                 // the node, without splitting any unicode codepoints.
-                for c in remainder.chars() {
-                    // TODO: This could definitely be more efficient.
-                    let cs = c.len_utf8();
-                    if cs + byte_pos > NODE_STR_SIZE { break }
-                    else {
-                        char_pos += 1;
-                        byte_pos += cs;
-                    }
-                }
-                
+                let byte_pos = floor_char_boundary(remainder, NODE);
+
                 let (next, rem) = remainder.split_at(byte_pos);
                 assert!(!next.is_empty());
-                self.insert_node_at(cursor, next, char_pos, true);
+                // ASCII content doesn't need counting.
+                let next_chars = if num_inserted_chars == num_inserted_bytes { next.len() } else { count_chars(next) };
+                self.insert_node_at(cursor, next, next_chars, true);
                 remainder = rem;
             }
 
@@ -551,10 +1092,12 @@ impl JumpRope {
         assert_ne!(cursor.local_char_pos(), 0);
     }
 
-    unsafe fn del_at_cursor(&mut self, cursor: &mut RopeCursor, mut length: usize) {
+    pub(crate) unsafe fn del_at_cursor(&mut self, cursor: &mut RopeCursor<NODE>, mut length: usize) {
         if length == 0 { return; }
+        let _guard = self.begin_mutation();
         let mut offset = cursor.local_char_pos();
         let mut node = cursor.here_ptr();
+        let mut crossed_nodes = false;
         while length > 0 {
             {
                 let s = (&*node).first_next();
@@ -562,47 +1105,123 @@ impl JumpRope {
                     // End of current node. Skip to the start of the next one.
                     node = s.node;
                     offset = 0;
+                    crossed_nodes = true;
                 }
             }
 
             let num_chars = (&*node).num_chars();
-            let removed = std::cmp::min(length, num_chars - offset);
+            let removed = core::cmp::min(length, num_chars - offset);
             assert!(removed > 0);
 
             let height = (*node).height as usize;
-            if removed < num_chars || std::ptr::eq(node, &self.head) {
+            let removed_lines = if removed == num_chars || (*node).num_lines() == 0 {
+                (*node).num_lines()
+            } else {
+                (*node).str.count_newlines_in(offset..offset + removed)
+            };
+            #[cfg(feature = "wchar_conversion")]
+            let removed_pairs = if removed == num_chars || (*node).num_pairs() == 0 {
+                (*node).num_pairs()
+            } else {
+                (*node).str.count_pairs_in(offset..offset + removed)
+            };
+
+            let removed_bytes;
+            if removed < num_chars || core::ptr::eq(node, &self.head) {
                 // Just trim the node down.
                 let s = &mut (*node).str;
-                let removed_bytes = s.remove_chars(offset, removed);
+                removed_bytes = s.remove_chars(offset, removed);
                 self.num_bytes -= removed_bytes;
+                self.renew_id(node);
 
                 for s in (*node).nexts_mut() {
                     s.skip_chars -= removed;
+                    s.skip_bytes -= removed_bytes;
+                    s.skip_lines -= removed_lines;
+                    #[cfg(feature = "wchar_conversion")] {
+                        s.skip_pairs -= removed_pairs;
+                    }
                 }
             } else {
                 // Remove the node from the skip list. This works because the cursor must be
                 // pointing from the previous element to the start of this element.
                 assert_ne!(cursor.0[0].node, node);
 
+                removed_bytes = (*node).str.len_bytes();
                 for i in 0..(*node).height as usize {
                     let s = &mut (*cursor.0[i].node).nexts_mut()[i];
                     s.node = (*node).nexts_mut()[i].node;
                     s.skip_chars += (*node).nexts()[i].skip_chars - removed;
+                    s.skip_bytes += (*node).nexts()[i].skip_bytes - removed_bytes;
+                    s.skip_lines += (*node).nexts()[i].skip_lines - removed_lines;
+                    #[cfg(feature = "wchar_conversion")] {
+                        s.skip_pairs += (*node).nexts()[i].skip_pairs - removed_pairs;
+                    }
                 }
 
-                self.num_bytes -= (*node).str.len_bytes();
+                self.num_bytes -= removed_bytes;
                 let next = (*node).first_next().node;
+                let node_size = Node::<NODE>::layout_with_height((*node).height).size();
+                // Bump the ID counter like any other structural change, so cached cursors (which
+                // may point at this node) are invalidated.
+                self.renew_id(node);
                 Node::free(node);
+                self.node_mem_shrank(node_size);
                 node = next;
             }
 
             for i in height..self.head.height as usize {
                 let s = &mut (*cursor.0[i].node).nexts_mut()[i];
                 s.skip_chars -= removed;
+                s.skip_bytes -= removed_bytes;
+                s.skip_lines -= removed_lines;
+                #[cfg(feature = "wchar_conversion")] {
+                    s.skip_pairs -= removed_pairs;
+                }
             }
 
             length -= removed;
         }
+
+        // Deleting across a node boundary often leaves the nodes on either side mostly empty.
+        if crossed_nodes {
+            self.merge_next_node(cursor);
+        }
+    }
+
+    // Merge the node after the cursor's node into it, if their content fits in a single node. The
+    // cursor must be at the end of its node, and it stays at the same position.
+    unsafe fn merge_next_node(&mut self, cursor: &mut RopeCursor<NODE>) {
+        let here = cursor.here_ptr();
+        let next = (*here).first_next().node;
+        if next.is_null() || (*here).str.len_bytes() + (*next).str.len_bytes() > NODE {
+            return;
+        }
+        debug_assert_eq!(cursor.local_char_pos(), (*here).num_chars());
+
+        let s = &mut (*here).str;
+        s.gap_to_end();
+        s.insert_in_gap((*next).as_str_1());
+        s.insert_in_gap((*next).as_str_2());
+        self.renew_id(here);
+
+        // The entries pointing to next now skip over its content (which is in here) as well.
+        for i in 0..(*next).height as usize {
+            let skip = &mut (*cursor.0[i].node).nexts_mut()[i];
+            debug_assert_eq!(skip.node, next);
+            let next_skip = (*next).nexts()[i];
+            skip.node = next_skip.node;
+            skip.skip_chars += next_skip.skip_chars;
+            skip.skip_bytes += next_skip.skip_bytes;
+            skip.skip_lines += next_skip.skip_lines;
+            #[cfg(feature = "wchar_conversion")] {
+                skip.skip_pairs += next_skip.skip_pairs;
+            }
+        }
+
+        let node_size = Node::<NODE>::layout_with_height((*next).height).size();
+        Node::free(next);
+        self.node_mem_shrank(node_size);
     }
 
     fn eq_str(&self, mut other: &str) -> bool {
@@ -618,13 +1237,30 @@ impl JumpRope {
     }
 }
 
-impl Default for JumpRope {
+impl<const NODE: usize, const BIAS: u8> Default for JumpRopeCustom<NODE, BIAS> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Drop for JumpRope {
+impl<const NODE: usize, const BIAS: u8> JumpRopeCustom<NODE, BIAS> {
+    // Empty the rope without freeing any of its nodes, for when they have been moved to another
+    // rope. The nodes must not be used again through this rope.
+    pub(crate) unsafe fn forget_nodes(&mut self) {
+        self.head.str = GapBuffer::new();
+        let head: *mut Node<NODE> = &mut self.head;
+        self.renew_id(head);
+        self.head.height = 1;
+        self.nexts[0] = SkipEntry::new();
+        self.num_bytes = 0;
+        // The spare nodes are still ours.
+        self.node_mem = self.spare_nodes.iter()
+            .map(|&node| Node::<NODE>::layout_with_height((*node).height).size())
+            .sum();
+    }
+}
+
+impl<const NODE: usize, const BIAS: u8> Drop for JumpRopeCustom<NODE, BIAS> {
     fn drop(&mut self) {
         let mut node = self.head.first_next().node;
         unsafe {
@@ -633,23 +1269,90 @@ impl Drop for JumpRope {
                 Node::free(node);
                 node = next;
             }
+
+            for &node in &self.spare_nodes {
+                Node::free(node);
+            }
         }
     }
 }
 
-impl From<&str> for JumpRope {
+impl<const NODE: usize, const BIAS: u8> From<&str> for JumpRopeCustom<NODE, BIAS> {
     fn from(str: &str) -> Self {
-        JumpRope::new_from_str(str)
+        Self::new_from_str(str)
     }
 }
 
-impl From<String> for JumpRope {
+impl<const NODE: usize, const BIAS: u8> From<String> for JumpRopeCustom<NODE, BIAS> {
     fn from(str: String) -> Self {
-        JumpRope::new_from_str(&str)
+        Self::new_from_str(&str)
+    }
+}
+
+/// Create a rope by concatenating the passed fragments.
+impl<const NODE: usize, const BIAS: u8> From<&[&str]> for JumpRopeCustom<NODE, BIAS> {
+    fn from(fragments: &[&str]) -> Self {
+        let mut rope = Self::new();
+        rope.extend(fragments.iter().copied());
+        rope
+    }
+}
+
+/// Create a rope by concatenating the passed fragments.
+impl<const NODE: usize, const BIAS: u8> From<Vec<String>> for JumpRopeCustom<NODE, BIAS> {
+    fn from(fragments: Vec<String>) -> Self {
+        let mut rope = Self::new();
+        rope.extend(fragments.iter().map(String::as_str));
+        rope
+    }
+}
+
+/// Create a rope from UTF-8 bytes. The bytes are validated in blocks as they are loaded into the
+/// rope, so there's no separate validation pass or intermediate copy.
+///
+/// If the bytes aren't valid UTF-8, the returned error's
+/// [`valid_up_to`](core::str::Utf8Error::valid_up_to) is the byte offset of the invalid content in
+/// the passed slice.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// let rope = JumpRope::try_from("κόσμε".as_bytes()).unwrap();
+/// assert_eq!(rope, "κόσμε");
+///
+/// let err = JumpRope::try_from(&b"hi\xffthere"[..]).unwrap_err();
+/// assert_eq!(err.valid_up_to(), 2);
+/// ```
+impl<const NODE: usize, const BIAS: u8> TryFrom<&[u8]> for JumpRopeCustom<NODE, BIAS> {
+    type Error = core::str::Utf8Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        const BLOCK_SIZE: usize = 16 * 1024;
+
+        let mut rope = Self::new();
+        let mut cursor = rope.cursor_at_start();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let block = &bytes[pos..bytes.len().min(pos + BLOCK_SIZE)];
+            let valid = match core::str::from_utf8(block) {
+                Ok(s) => s,
+                // A character was cut off by the end of the block. It'll be in the next block.
+                Err(e) if e.error_len().is_none() && pos + block.len() < bytes.len() => {
+                    unsafe { core::str::from_utf8_unchecked(&block[..e.valid_up_to()]) }
+                }
+                // Validate again from the start, so the error's position is relative to the whole
+                // slice. This only happens once, for invalid content.
+                Err(_) => return Err(core::str::from_utf8(bytes).unwrap_err()),
+            };
+            unsafe { rope.insert_at_cursor(&mut cursor, valid); }
+            pos += valid.len();
+        }
+        Ok(rope)
     }
 }
 
-impl PartialEq for JumpRope {
+impl<const NODE: usize, const BIAS: u8> PartialEq for JumpRopeCustom<NODE, BIAS> {
     // This is quite complicated. It would be cleaner to just write a bytes
     // iterator, then iterate over the bytes of both strings comparing along the
     // way.
@@ -659,7 +1362,7 @@ impl PartialEq for JumpRope {
     // an iterator over &str. Then the rope vs rope comparison would be trivial,
     // but also we could add comparison functions with a single &str and stuff
     // very easily.
-    fn eq(&self, other: &JumpRope) -> bool {
+    fn eq(&self, other: &Self) -> bool {
         if self.num_bytes != other.num_bytes
                 || self.len_chars() != other.len_chars() {
             return false
@@ -694,18 +1397,18 @@ impl PartialEq for JumpRope {
         true
     }
 }
-impl Eq for JumpRope {}
+impl<const NODE: usize, const BIAS: u8> Eq for JumpRopeCustom<NODE, BIAS> {}
 
-impl Debug for JumpRope {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl<const NODE: usize, const BIAS: u8> Debug for JumpRopeCustom<NODE, BIAS> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         f.debug_list()
             .entries(self.chunks().strings())
             .finish()
     }
 }
 
-impl Display for JumpRope {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+impl<const NODE: usize, const BIAS: u8> Display for JumpRopeCustom<NODE, BIAS> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         for (s, _) in self.chunks() {
             f.write_str(s)?;
         }
@@ -714,36 +1417,86 @@ impl Display for JumpRope {
 }
 
 // I don't know why I need all three of these, but I do.
-impl PartialEq<str> for JumpRope {
+impl<const NODE: usize, const BIAS: u8> PartialEq<str> for JumpRopeCustom<NODE, BIAS> {
     fn eq(&self, other: &str) -> bool {
         self.eq_str(other)
     }
 }
-impl PartialEq<&str> for JumpRope {
+impl<const NODE: usize, const BIAS: u8> PartialEq<&str> for JumpRopeCustom<NODE, BIAS> {
     fn eq(&self, other: &&str) -> bool {
         self.eq_str(*other)
     }
 }
-impl PartialEq<String> for JumpRope {
+impl<const NODE: usize, const BIAS: u8> PartialEq<String> for JumpRopeCustom<NODE, BIAS> {
     fn eq(&self, other: &String) -> bool {
         self.eq_str(other.as_str())
     }
 }
 
-impl<'a> Extend<&'a str> for JumpRope {
+impl<'a, const NODE: usize, const BIAS: u8> Extend<&'a str> for JumpRopeCustom<NODE, BIAS> {
     fn extend<T: IntoIterator<Item = &'a str>>(&mut self, iter: T) {
         let mut cursor = self.cursor_at_end();
         iter.into_iter().for_each(|s| {
+            self.record_insert(cursor.global_char_pos(self.head.height), s);
             unsafe { self.insert_at_cursor(&mut cursor, s); }
         });
     }
 }
 
-impl Clone for JumpRope {
+/// Append text to the end of the rope, like `String + &str`.
+impl<const NODE: usize, const BIAS: u8> Add<&str> for JumpRopeCustom<NODE, BIAS> {
+    type Output = Self;
+
+    fn add(mut self, rhs: &str) -> Self {
+        self += rhs;
+        self
+    }
+}
+
+/// Join two ropes. The nodes of `rhs` are moved rather than copied. See
+/// [`append`](JumpRopeCustom::append).
+impl<const NODE: usize, const BIAS: u8> Add for JumpRopeCustom<NODE, BIAS> {
+    type Output = Self;
+
+    fn add(mut self, rhs: Self) -> Self {
+        self.append(rhs);
+        self
+    }
+}
+
+/// Append the content of another rope. See [`insert_rope`](JumpRopeCustom::insert_rope).
+impl<const NODE: usize, const BIAS: u8> Add<&JumpRopeCustom<NODE, BIAS>> for JumpRopeCustom<NODE, BIAS> {
+    type Output = Self;
+
+    fn add(mut self, rhs: &Self) -> Self {
+        self += rhs;
+        self
+    }
+}
+
+impl<const NODE: usize, const BIAS: u8> AddAssign<&str> for JumpRopeCustom<NODE, BIAS> {
+    fn add_assign(&mut self, rhs: &str) {
+        self.insert(self.len_chars(), rhs);
+    }
+}
+
+impl<const NODE: usize, const BIAS: u8> AddAssign for JumpRopeCustom<NODE, BIAS> {
+    fn add_assign(&mut self, rhs: Self) {
+        self.append(rhs);
+    }
+}
+
+impl<const NODE: usize, const BIAS: u8> AddAssign<&JumpRopeCustom<NODE, BIAS>> for JumpRopeCustom<NODE, BIAS> {
+    fn add_assign(&mut self, rhs: &Self) {
+        self.insert_rope(self.len_chars(), rhs);
+    }
+}
+
+impl<const NODE: usize, const BIAS: u8> Clone for JumpRopeCustom<NODE, BIAS> {
     fn clone(&self) -> Self {
         // This method could be a little bit more efficient, but I think improving clone()
         // performance isn't worth the extra effort.
-        let mut r = JumpRope::new();
+        let mut r = Self::new();
         let mut cursor = r.cursor_at_start();
         for node in self.node_iter() {
             unsafe {
@@ -755,7 +1508,7 @@ impl Clone for JumpRope {
     }
 }
 
-impl JumpRope {
+impl<const NODE: usize, const BIAS: u8> JumpRopeCustom<NODE, BIAS> {
     /// Insert new content into the rope. The content is inserted at the specified unicode character
     /// offset, which is different from a byte offset for non-ASCII characters.
     ///
@@ -769,15 +1522,21 @@ impl JumpRope {
     /// ```
     ///
     /// If the position names a location past the end of the rope, it is truncated.
-    pub fn insert(&mut self, mut pos: usize, contents: &str) {
+    ///
+    /// The content can be any string type - like `&str`, `String`, `Cow<str>` or `Arc<str>`.
+    pub fn insert<S: AsRef<str>>(&mut self, mut pos: usize, contents: S) {
+        let contents = contents.as_ref();
         if contents.is_empty() { return; }
-        pos = std::cmp::min(pos, self.len_chars());
+        pos = core::cmp::min(pos, self.len_chars());
 
-        let mut cursor = self.cursor_at_char(pos, true);
+        self.record_insert(pos, contents);
+
+        let mut cursor = self.edit_cursor_at(pos);
         unsafe { self.insert_at_cursor(&mut cursor, contents); }
 
         debug_assert_eq!(cursor.global_char_pos(self.head.height), pos + count_chars(contents));
         // dbg!(&cursor.0[..self.head.height as usize]);
+        self.cache_edit_cursor(cursor);
     }
 
     /// Delete a span of unicode characters from the rope. The span is specified in unicode
@@ -797,42 +1556,689 @@ impl JumpRope {
         range.end = range.end.min(self.len_chars());
         if range.start >= range.end { return; }
 
+        self.record_remove(range.clone());
+
         // We need to stick_end so we can delete entries.
-        let mut cursor = self.cursor_at_char(range.start, true);
+        let mut cursor = self.edit_cursor_at(range.start);
         unsafe { self.del_at_cursor(&mut cursor, range.end - range.start); }
 
         debug_assert_eq!(cursor.global_char_pos(self.head.height), range.start);
+        self.cache_edit_cursor(cursor);
     }
 
-    /// Replace the specified range with new content. This is equivalent to calling
-    /// [`remove`](Self::remove) followed by [`insert`](Self::insert), but it is simpler and faster.
+    /// Insert content at a character position, without clamping the position or counting the
+    /// characters in the content. This is meant for trusted hot paths which already know the
+    /// position is valid and how long the content is - like a CRDT replaying millions of
+    /// operations while loading a document.
+    ///
+    /// Edits are still recorded by [`with_recording`](Self::with_recording).
+    ///
+    /// # Safety
+    ///
+    /// - `pos` must be at most [`len_chars`](Self::len_chars).
+    /// - `num_chars` must be the number of unicode characters in `contents`.
+    ///
+    /// These are checked with debug assertions. In release builds, breaking them corrupts the rope.
     ///
     /// # Example
     ///
     /// ```
     /// # use jumprope::*;
-    /// let mut rope = JumpRope::from("Hi Mike!");
-    /// rope.replace(3..7, "Duane"); // replace "Mike" with "Duane"
-    /// assert_eq!(rope.to_string(), "Hi Duane!");
+    /// let mut rope = JumpRope::from("--");
+    /// unsafe { rope.insert_unchecked(1, "κόσμε", 5); }
+    /// assert_eq!(rope, "-κόσμε-");
     /// ```
-    pub fn replace(&mut self, range: Range<usize>, content: &str) {
-        let len = self.len_chars();
-        let pos = usize::min(range.start, len);
-        let del_len = usize::min(range.end, len) - pos;
+    pub unsafe fn insert_unchecked(&mut self, pos: usize, contents: &str, num_chars: usize) {
+        debug_assert!(pos <= self.len_chars());
+        if contents.is_empty() { return; }
 
-        let mut cursor = self.cursor_at_char(pos, true);
-        if del_len > 0 {
-            unsafe { self.del_at_cursor(&mut cursor, del_len); }
-        }
-        if !content.is_empty() {
-            unsafe { self.insert_at_cursor(&mut cursor, content); }
-        }
+        self.record_insert(pos, contents);
 
-        debug_assert_eq!(cursor.global_char_pos(self.head.height), pos + count_chars(content));
+        let mut cursor = self.edit_cursor_at(pos);
+        self.insert_at_cursor_counted(&mut cursor, contents, num_chars);
+        self.cache_edit_cursor(cursor);
     }
 
-    /// Get the number of bytes used for the UTF8 representation of the rope. This will always match
-    /// the .len() property of the equivalent String.
+    /// Delete a range of characters from the rope, without clamping the range. See
+    /// [`insert_unchecked`](Self::insert_unchecked).
+    ///
+    /// # Safety
+    ///
+    /// `range.start <= range.end <= self.len_chars()` must hold. This is checked with debug
+    /// assertions. In release builds, breaking it corrupts the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Whoa dawg!");
+    /// unsafe { rope.remove_unchecked(4..9); }
+    /// assert_eq!(rope, "Whoa!");
+    /// ```
+    pub unsafe fn remove_unchecked(&mut self, range: Range<usize>) {
+        debug_assert!(range.start <= range.end && range.end <= self.len_chars());
+        if range.start == range.end { return; }
+
+        self.record_remove(range.clone());
+
+        let mut cursor = self.edit_cursor_at(range.start);
+        self.del_at_cursor(&mut cursor, range.end - range.start);
+        self.cache_edit_cursor(cursor);
+    }
+
+    // Remove a list of sorted, non-overlapping ranges, given as positions from before any of them
+    // are removed. A single cursor walks forward through the rope deleting each range in turn,
+    // instead of searching the skip list once per range.
+    pub(crate) fn remove_sorted_ranges(&mut self, ranges: &[Range<usize>]) {
+        let Some(first) = ranges.first() else { return; };
+        let height = self.head.height as usize;
+        let mut cursor = self.edit_cursor_at(first.start);
+        let mut pos = first.start;
+        let mut removed = 0;
+        for range in ranges {
+            debug_assert!(range.start - removed >= pos && range.end <= self.len_chars() + removed);
+            let start = range.start - removed;
+            while pos < start {
+                let here = unsafe { &*cursor.here_ptr() };
+                let step = min(here.num_chars() - cursor.local_char_pos(), start - pos);
+                if step == 0 {
+                    unsafe { cursor.advance_node(height); }
+                } else {
+                    cursor.move_within_node(height, step as isize);
+                    pos += step;
+                }
+            }
+
+            let len = range.end - range.start;
+            self.record_remove(start..start + len);
+            unsafe { self.del_at_cursor(&mut cursor, len); }
+            removed += len;
+        }
+        debug_assert_eq!(cursor.global_char_pos(self.head.height), pos);
+        self.cache_edit_cursor(cursor);
+    }
+
+    /// Replace the specified range with new content. This is equivalent to calling
+    /// [`remove`](Self::remove) followed by [`insert`](Self::insert), but it is simpler and faster.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hi Mike!");
+    /// rope.replace(3..7, "Duane"); // replace "Mike" with "Duane"
+    /// assert_eq!(rope.to_string(), "Hi Duane!");
+    /// ```
+    pub fn replace<S: AsRef<str>>(&mut self, range: Range<usize>, content: S) {
+        let content = content.as_ref();
+        let len = self.len_chars();
+        let pos = usize::min(range.start, len);
+        let del_len = usize::min(range.end, len) - pos;
+
+        if del_len > 0 { self.record_remove(pos..pos + del_len); }
+        self.record_insert(pos, content);
+
+        let mut cursor = self.cursor_at_char(pos, true);
+        if del_len > 0 {
+            unsafe { self.del_at_cursor(&mut cursor, del_len); }
+        }
+        if !content.is_empty() {
+            unsafe { self.insert_at_cursor(&mut cursor, content); }
+        }
+
+        debug_assert_eq!(cursor.global_char_pos(self.head.height), pos + count_chars(content));
+    }
+
+    /// Shorten the rope to `char_len` characters, removing everything after it. Nothing happens if
+    /// the rope is already that short. Like [`String::truncate`], but the length is in characters.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κόσμε");
+    /// rope.truncate(2);
+    /// assert_eq!(rope, "κό");
+    /// ```
+    pub fn truncate(&mut self, char_len: usize) {
+        self.remove(char_len..self.len_chars());
+    }
+
+    /// Remove all content from the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi there");
+    /// rope.clear();
+    /// assert!(rope.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        self.remove(0..self.len_chars());
+    }
+
+    /// Remove the last character from the rope and return it, or return `None` if the rope is
+    /// empty. This is a backspace at the end of the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("ab😘");
+    /// assert_eq!(rope.pop_char(), Some('😘'));
+    /// assert_eq!(rope, "ab");
+    /// ```
+    pub fn pop_char(&mut self) -> Option<char> {
+        let c = self.chars_rev().next()?;
+        let len = self.len_chars();
+        self.remove(len - 1..len);
+        Some(c)
+    }
+
+    /// Insert the content of another rope at a character position. The other rope's chunks are
+    /// copied straight in, so this avoids building an intermediate string (like
+    /// `rope.insert(pos, other.to_string())` does). The other rope can use a different
+    /// [node size or bias](JumpRopeCustom).
+    ///
+    /// If the position is past the end of the rope, it is truncated. To move an entire rope into
+    /// this one without copying its content, use [`split_off`](Self::split_off) and
+    /// [`append`](Self::append) instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let clipboard = JumpRope::from("big ");
+    /// let mut rope = JumpRope::from("a document");
+    /// rope.insert_rope(2, &clipboard);
+    /// assert_eq!(rope, "a big document");
+    /// ```
+    pub fn insert_rope<const N: usize, const B: u8>(&mut self, pos: usize, other: &JumpRopeCustom<N, B>) {
+        if other.is_empty() { return; }
+        let pos = pos.min(self.len_chars());
+        if self.recording.is_some() {
+            let content = other.chunks().strings().collect::<String>();
+            self.record_insert(pos, &content);
+        }
+
+        let mut cursor = self.edit_cursor_at(pos);
+        for (s, num_chars) in other.chunks() {
+            unsafe { self.insert_at_cursor_counted(&mut cursor, s, num_chars); }
+        }
+
+        debug_assert_eq!(cursor.global_char_pos(self.head.height), pos + other.len_chars());
+        self.cache_edit_cursor(cursor);
+    }
+
+    /// Append each line to the end of the rope, followed by a newline. This is like
+    /// [`extend`](Extend::extend), but it saves formatting each line with a trailing `'\n'` first -
+    /// which is handy for appending to logs.
+    ///
+    /// The end of the rope is only looked up once, and each line is inserted at the same cursor.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("log:\n");
+    /// rope.extend_lines(["one", "two"]);
+    /// assert_eq!(rope, "log:\none\ntwo\n");
+    /// ```
+    pub fn extend_lines<I: IntoIterator<Item = S>, S: AsRef<str>>(&mut self, lines: I) {
+        let mut cursor = self.cursor_at_end();
+        for line in lines {
+            let line = line.as_ref();
+            let pos = cursor.global_char_pos(self.head.height);
+            self.record_insert(pos, line);
+            self.record_insert(pos + count_chars(line), "\n");
+            unsafe {
+                self.insert_at_cursor(&mut cursor, line);
+                self.insert_at_cursor(&mut cursor, "\n");
+            }
+        }
+    }
+
+    /// Move the content of `other` onto the end of this rope.
+    ///
+    /// The nodes of `other` are linked into this rope's skip list rather than copied, so this is
+    /// much faster than `rope.insert(rope.len_chars(), other.to_string())` for large documents.
+    /// Only the text in the head of `other` (at most one node's worth) is copied. Linking takes
+    /// *O(log n)* time, though each moved node is still given a new ID for
+    /// [`chunks_with_ids`](Self::chunks_with_ids), which touches every node without copying text.
+    ///
+    /// Inside [`with_recording`](Self::with_recording) this is recorded as a single insert at the
+    /// end of the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hello ");
+    /// rope.append(JumpRope::from("world!"));
+    /// assert_eq!(rope, "Hello world!");
+    /// ```
+    pub fn append(&mut self, mut other: Self) {
+        if other.is_empty() { return; }
+        if self.recording.is_some() {
+            let content = other.chunks().strings().collect::<String>();
+            self.record_insert(self.len_chars(), &content);
+        }
+
+        // The head of other is inline, so its content can't be moved.
+        let recording = self.recording.take();
+        let (head_chars, head_bytes, head_lines) = (other.head.num_chars(), other.head.num_bytes(), other.head.num_lines());
+        #[cfg(feature = "wchar_conversion")]
+        let head_pairs = other.head.num_pairs();
+        other.head.str.gap_to_end();
+        self.insert(self.len_chars(), other.head.str.start_as_str());
+        self.recording = recording;
+
+        let mut cursor = self.cursor_at_end();
+        let _guard = self.begin_mutation();
+        unsafe {
+            // Make the head of this rope at least as tall as the head of other.
+            let mut head_height = self.head.height as usize;
+            while head_height < other.head.height as usize {
+                self.nexts[head_height] = self.nexts[head_height - 1];
+                cursor.0[head_height] = cursor.0[head_height - 1];
+                self.head.height += 1;
+                head_height += 1;
+            }
+
+            // The last node at each level points past the end of this rope. Point it at the first
+            // node of other at that level instead, skipping over other's (now empty) head.
+            let other_top = other.head.nexts()[other.head.height as usize - 1];
+            for i in 0..head_height {
+                let other_skip = other.head.nexts().get(i).copied().unwrap_or(other_top);
+                let skip = &mut (*cursor.0[i].node).nexts_mut()[i];
+                debug_assert!(skip.node.is_null());
+                skip.node = other_skip.node;
+                skip.skip_chars += other_skip.skip_chars - head_chars;
+                skip.skip_bytes += other_skip.skip_bytes - head_bytes;
+                skip.skip_lines += other_skip.skip_lines - head_lines;
+                #[cfg(feature = "wchar_conversion")] {
+                    skip.skip_pairs += other_skip.skip_pairs - head_pairs;
+                }
+            }
+
+            let mut node = other.head.first_next().node;
+            while !node.is_null() {
+                self.renew_id(node);
+                node = (*node).first_next().node;
+            }
+
+            self.num_bytes += other.num_bytes - head_bytes;
+            let other_mem = other.node_mem;
+            other.forget_nodes();
+            self.node_mem_grew(other_mem - other.node_mem);
+        }
+    }
+
+    /// Split the rope in two at `char_pos`. This rope keeps the content before `char_pos`, and the
+    /// rest is returned as a new rope. Positions past the end of the rope are truncated.
+    ///
+    /// The nodes after the split point are moved into the returned rope rather than copied. Only
+    /// the node containing `char_pos` is split, so this is much faster than copying the tail out of
+    /// a large document. Like [`append`](Self::append), each moved node is given a new ID.
+    ///
+    /// Inside [`with_recording`](Self::with_recording) this is recorded as a removal of the tail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hello world!");
+    /// let tail = rope.split_off(5);
+    /// assert_eq!(rope, "Hello");
+    /// assert_eq!(tail, " world!");
+    /// ```
+    pub fn split_off(&mut self, char_pos: usize) -> Self {
+        let len = self.len_chars();
+        let pos = char_pos.min(len);
+        let mut tail = Self::new();
+        if pos == len { return tail; }
+
+        self.record_remove(pos..len);
+        self.edit_cache = None;
+
+        // Move the rest of the node containing pos into the head of the tail.
+        let mut cursor = self.cursor_at_char(pos, true);
+        let here = cursor.here_ptr();
+        let local_pos = cursor.local_char_pos();
+        if local_pos < unsafe { (*here).num_chars() } {
+            let content = unsafe { (*here).str.chars().skip(local_pos).collect::<String>() };
+            unsafe {
+                tail.insert_at_cursor(&mut tail.cursor_at_start(), &content);
+                self.del_at_cursor(&mut cursor, count_chars(&content));
+            }
+            cursor = self.cursor_at_char(pos, true);
+        }
+
+        let mut tail_cursor = tail.cursor_at_end();
+        let _guard = self.begin_mutation();
+        unsafe {
+            let head_height = self.head.height as usize;
+            while tail.head.height < self.head.height {
+                let h = tail.head.height as usize;
+                tail.nexts[h] = tail.nexts[h - 1];
+                tail_cursor.0[h] = tail_cursor.0[h - 1];
+                tail.head.height += 1;
+            }
+
+            // The distance from the start of the cursor's node at each level to pos.
+            let here = &*cursor.here_ptr();
+            let before_pos = |c: &SkipEntry<NODE>| SkipEntry {
+                node: c.node,
+                skip_chars: c.skip_chars,
+                skip_bytes: c.skip_bytes + here.num_bytes(),
+                skip_lines: c.skip_lines + here.num_lines(),
+                #[cfg(feature = "wchar_conversion")]
+                skip_pairs: c.skip_pairs + here.num_pairs(),
+            };
+
+            // Everything after pos moves to the tail.
+            let top = before_pos(&cursor.0[head_height - 1]);
+            let total = self.nexts[head_height - 1];
+            let moved: SkipEntry<NODE> = SkipEntry {
+                node: ptr::null_mut(),
+                skip_chars: total.skip_chars - top.skip_chars,
+                skip_bytes: total.skip_bytes - top.skip_bytes,
+                skip_lines: total.skip_lines - top.skip_lines,
+                #[cfg(feature = "wchar_conversion")]
+                skip_pairs: total.skip_pairs - top.skip_pairs,
+            };
+
+            for i in 0..tail.head.height as usize {
+                let tail_skip = &mut (*tail_cursor.0[i].node).nexts_mut()[i];
+                debug_assert!(tail_skip.node.is_null());
+                if i >= head_height {
+                    tail_skip.skip_chars += moved.skip_chars;
+                    tail_skip.skip_bytes += moved.skip_bytes;
+                    tail_skip.skip_lines += moved.skip_lines;
+                    #[cfg(feature = "wchar_conversion")] {
+                        tail_skip.skip_pairs += moved.skip_pairs;
+                    }
+                    continue;
+                }
+
+                // The node before pos at this level now ends the rope, and the tail's last node
+                // at this level points to whatever came after it.
+                let c = before_pos(&cursor.0[i]);
+                let skip = &mut (*c.node).nexts_mut()[i];
+                tail_skip.node = skip.node;
+                tail_skip.skip_chars += skip.skip_chars - c.skip_chars;
+                tail_skip.skip_bytes += skip.skip_bytes - c.skip_bytes;
+                tail_skip.skip_lines += skip.skip_lines - c.skip_lines;
+                #[cfg(feature = "wchar_conversion")] {
+                    tail_skip.skip_pairs += skip.skip_pairs - c.skip_pairs;
+                }
+                *skip = SkipEntry { node: ptr::null_mut(), ..c };
+            }
+
+            self.num_bytes -= moved.skip_bytes;
+            tail.num_bytes += moved.skip_bytes;
+            let mut moved_mem = 0;
+            let mut node = (*tail_cursor.here_ptr()).first_next().node;
+            while !node.is_null() {
+                tail.renew_id(node);
+                moved_mem += Node::<NODE>::layout_with_height((*node).height).size();
+                node = (*node).first_next().node;
+            }
+            self.node_mem_shrank(moved_mem);
+            tail.node_mem_grew(moved_mem);
+        }
+
+        tail
+    }
+
+    /// Insert new content at the specified UTF8 byte offset. This is useful when positions come
+    /// from a parser or protocol which counts bytes (like tree-sitter, or LSP with UTF8 position
+    /// encoding). The position is found in *O(log n)* time.
+    ///
+    /// If the position is past the end of the rope, it is truncated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte_pos` is inside a multi-byte character.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κόσμε");
+    /// rope.insert_at_byte(4, "-"); // After "κό"
+    /// assert_eq!(rope, "κό-σμε");
+    /// ```
+    pub fn insert_at_byte<S: AsRef<str>>(&mut self, byte_pos: usize, contents: S) {
+        let contents = contents.as_ref();
+        let byte_pos = byte_pos.min(self.num_bytes);
+        let (mut cursor, on_boundary) = self.cursor_at_byte(byte_pos, true);
+        assert!(on_boundary, "Byte offset {} is inside a character", byte_pos);
+        if contents.is_empty() { return; }
+
+        self.record_insert(cursor.global_char_pos(self.head.height), contents);
+        unsafe { self.insert_at_cursor(&mut cursor, contents); }
+    }
+
+    /// Delete a span of the rope, specified in UTF8 bytes. The positions are found in
+    /// *O(log n)* time.
+    ///
+    /// Any attempt to delete past the end of the rope will be silently ignored.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either end of the range is inside a multi-byte character.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("κόσμε");
+    /// rope.remove_byte_range(2..6); // Remove "όσ"
+    /// assert_eq!(rope, "κμε");
+    /// ```
+    pub fn remove_byte_range(&mut self, range: Range<usize>) {
+        let end = range.end.min(self.num_bytes);
+        if range.start >= end { return; }
+
+        let (mut cursor, start_on_boundary) = self.cursor_at_byte(range.start, true);
+        let (end_cursor, end_on_boundary) = self.cursor_at_byte(end, true);
+        assert!(start_on_boundary, "Byte offset {} is inside a character", range.start);
+        assert!(end_on_boundary, "Byte offset {} is inside a character", end);
+
+        let start = cursor.global_char_pos(self.head.height);
+        let end = end_cursor.global_char_pos(self.head.height);
+        self.record_remove(start..end);
+        unsafe { self.del_at_cursor(&mut cursor, end - start); }
+    }
+
+    /// Replace the specified range with new content, like [`replace`](Self::replace). But unlike
+    /// `replace`, which clamps the range to the document, this returns an error (and leaves the
+    /// rope unchanged) if the range is inverted or extends past the end of the rope. This is
+    /// useful for catching desync bugs when applying edits from elsewhere.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hi Mike!");
+    /// assert!(rope.replace_strict(3..7, "Duane").is_ok());
+    /// assert_eq!(rope, "Hi Duane!");
+    ///
+    /// assert_eq!(rope.replace_strict(3..100, "x"), Err(RangeError::new(3..100, 9)));
+    /// assert_eq!(rope, "Hi Duane!");
+    /// ```
+    pub fn replace_strict<S: AsRef<str>>(&mut self, range: Range<usize>, content: S) -> Result<(), RangeError> {
+        self.validate_char_range(range.clone())?;
+        self.replace(range, content);
+        Ok(())
+    }
+
+    /// Replace the single character at `pos` with `c`.
+    ///
+    /// If the existing character takes up the same number of bytes as `c` (eg when toggling the
+    /// case of an ASCII letter), the character is overwritten in place without restructuring the
+    /// rope. Otherwise this is equivalent to `rope.replace(pos..pos+1, c)`. (So if `pos` names the
+    /// end of the rope, `c` is appended.)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("'quoted'");
+    /// rope.replace_char(0, '"');
+    /// rope.replace_char(7, '"');
+    /// assert_eq!(rope, "\"quoted\"");
+    /// ```
+    pub fn replace_char(&mut self, pos: usize, c: char) {
+        if pos < self.len_chars() {
+            let cursor = self.cursor_at_char(pos, false);
+            let node = unsafe { &mut *cursor.here_ptr() };
+            let local_pos = cursor.local_char_pos();
+
+            // The fast path can't change the number of newlines in the node.
+            if node.str.char_len_bytes_at(local_pos) == c.len_utf8()
+                && node.str.count_newlines_in(local_pos..local_pos + 1) == (c == '\n') as usize
+            {
+                self.record_remove(pos..pos + 1);
+                self.record_insert(pos, c.encode_utf8(&mut [0; 4]));
+                let _guard = self.begin_mutation();
+                node.str.overwrite_char(local_pos, c);
+                self.renew_id(node);
+                return;
+            }
+        }
+
+        self.replace(pos..pos + 1, c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Transform every ASCII character in the specified range by mapping its byte through `f`.
+    /// Non-ASCII characters are left untouched. This is useful for fast transformations of large
+    /// regions, like ASCII case folding or rot13.
+    ///
+    /// The content is modified directly inside the rope's nodes. Nothing is allocated, moved or
+    /// relinked, so this is much faster than replacing the range.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `f` maps an ASCII byte to a non-ASCII byte. If this happens (or `f` panics), the
+    /// bytes before the panic have already been mapped, but the rope is still valid. The edit isn't
+    /// recorded by [`with_recording`](Self::with_recording).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hello wörld");
+    /// rope.map_bytes_in_place(0..100, |b| b.to_ascii_uppercase());
+    /// assert_eq!(rope, "HELLO WöRLD");
+    ///
+    /// let rot13 = |b: u8| match b {
+    ///     b'a'..=b'z' => (b - b'a' + 13) % 26 + b'a',
+    ///     b'A'..=b'Z' => (b - b'A' + 13) % 26 + b'A',
+    ///     _ => b,
+    /// };
+    /// rope.map_bytes_in_place(0..5, rot13);
+    /// assert_eq!(rope, "URYYB WöRLD");
+    /// ```
+    pub fn map_bytes_in_place<F: FnMut(u8) -> u8>(&mut self, range: Range<usize>, mut f: F) {
+        let end = range.end.min(self.len_chars());
+        let start = range.start.min(end);
+        if start == end { return; }
+        // The edit is only recorded once the mapping has succeeded, in case f panics.
+        let old_content = self.recording.is_some()
+            .then(|| self.slice_chars(start..end).collect::<String>());
+
+        let guard = self.begin_mutation();
+        let height = self.head.height as usize;
+        let mut cursor = self.cursor_at_char(start, false);
+        let mut remaining = end - start;
+        loop {
+            let n = unsafe { &mut *cursor.here_ptr() };
+            let local_start = cursor.local_char_pos();
+            let len = min(n.num_chars() - local_start, remaining);
+            self.renew_id(n);
+
+            // f might add or remove newlines, which changes the line counts in the skip list. They
+            // are updated as each byte is written, so the skip list still matches the content if f
+            // panics partway through.
+            n.str.map_ascii_bytes(local_start..local_start + len, |b| {
+                let mapped = f(b);
+                assert!(mapped.is_ascii(), "map_bytes_in_place mapped an ASCII byte to non-ASCII");
+                let lines_delta = (mapped == b'\n') as isize - (b == b'\n') as isize;
+                if lines_delta != 0 { cursor.update_offsets(height, 0, 0, lines_delta); }
+                mapped
+            });
+
+            remaining -= len;
+            if remaining == 0 { break; }
+            unsafe { cursor.advance_node(height); }
+        }
+        drop(guard);
+
+        if let Some(content) = old_content {
+            let new_content = self.slice_chars(start..end).collect::<String>();
+            if let Some(ops) = self.recording.as_mut() {
+                ops.push(ReversibleOp::Remove { pos: start, content });
+            }
+            self.record_insert(start, &new_content);
+        }
+    }
+
+    /// Overwrite characters starting at `pos` with `content`, like typing with the insert key
+    /// toggled off. One existing character is replaced for each character in `content`, but
+    /// newlines are never overwritten. Once the end of the line is reached, any remaining content
+    /// is inserted instead.
+    ///
+    /// This is equivalent to calling [`overwrite_with_limit`](Self::overwrite_with_limit) with
+    /// [`OverwriteLimit::Line`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("abc\ndef");
+    /// rope.overwrite(1, "XY");
+    /// assert_eq!(rope, "aXY\ndef");
+    ///
+    /// rope.overwrite(2, "1234");
+    /// assert_eq!(rope, "aX1234\ndef");
+    /// ```
+    pub fn overwrite<S: AsRef<str>>(&mut self, pos: usize, content: S) {
+        self.overwrite_with_limit(pos, content, OverwriteLimit::Line);
+    }
+
+    /// Overwrite characters starting at `pos` with `content`. One existing character is replaced
+    /// for each character in `content`, up to the boundary specified by `limit`. Any content
+    /// which would overwrite past that point is inserted instead.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("abc\ndef");
+    /// rope.overwrite_with_limit(2, "XYZ", OverwriteLimit::Document);
+    /// assert_eq!(rope, "abXYZef");
+    ///
+    /// rope.overwrite_with_limit(5, "123", OverwriteLimit::Document);
+    /// assert_eq!(rope, "abXYZ123");
+    /// ```
+    pub fn overwrite_with_limit<S: AsRef<str>>(&mut self, pos: usize, content: S, limit: OverwriteLimit) {
+        let content = content.as_ref();
+        let pos = pos.min(self.len_chars());
+        let end = usize::min(pos + count_chars(content), self.len_chars());
+
+        let del_len = match limit {
+            OverwriteLimit::Document => end - pos,
+            OverwriteLimit::Line => self.slice_chars(pos..end).take_while(|&c| c != '\n').count(),
+        };
+
+        self.replace(pos..pos + del_len, content);
+    }
+
+    /// Get the number of bytes used for the UTF8 representation of the rope. This will always match
+    /// the .len() property of the equivalent String.
     ///
     /// Note: This is only useful in specific situations - like preparing a byte buffer for saving
     /// or sending over the internet. In many cases it is preferable to use
@@ -850,9 +2256,323 @@ impl JumpRope {
     /// ```
     pub fn len_bytes(&self) -> usize { self.num_bytes }
 
+    /// Convert a character position to the byte offset of that character in the UTF8
+    /// representation of the rope. Positions past the end of the rope return
+    /// [`len_bytes`](Self::len_bytes).
+    ///
+    /// This runs in *O(log n)* time, using the byte counts stored in the skip list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("aδc"); // δ takes 2 bytes.
+    /// assert_eq!(rope.char_to_byte(1), 1);
+    /// assert_eq!(rope.char_to_byte(2), 3);
+    /// assert_eq!(rope.char_to_byte(100), 4);
+    /// ```
+    pub fn char_to_byte(&self, char_pos: usize) -> usize {
+        if char_pos >= self.len_chars() { return self.num_bytes; }
+        let cursor = self.cursor_at_char(char_pos, true);
+        cursor.global_byte_pos(self.head.height)
+    }
+
+    /// Convert a byte offset in the UTF8 representation of the rope to the position of the
+    /// character containing that byte. If the byte offset lands in the middle of a multi-byte
+    /// character, the position of that character is returned. Offsets past the end of the rope
+    /// return [`len_chars`](Self::len_chars).
+    ///
+    /// This runs in *O(log n)* time, using the byte counts stored in the skip list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("aδc"); // δ takes 2 bytes.
+    /// assert_eq!(rope.byte_to_char(1), 1);
+    /// assert_eq!(rope.byte_to_char(2), 1);
+    /// assert_eq!(rope.byte_to_char(3), 2);
+    /// assert_eq!(rope.byte_to_char(100), 3);
+    /// ```
+    pub fn byte_to_char(&self, byte_pos: usize) -> usize {
+        if byte_pos >= self.num_bytes { return self.len_chars(); }
+        let (cursor, _) = self.cursor_at_byte(byte_pos, true);
+        cursor.global_char_pos(self.head.height)
+    }
+
+    /// Map a byte offset in the UTF8 representation of the rope to the offset of the character
+    /// containing that byte. This is the same as [`byte_to_char`](Self::byte_to_char).
+    ///
+    /// This is useful for reporting progress in terms of characters while reading or writing the
+    /// rope as bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("aδc"); // δ takes 2 bytes.
+    /// assert_eq!(rope.len_chars_at_or_before_byte(2), 1);
+    /// assert_eq!(rope.len_chars_at_or_before_byte(100), 3);
+    /// ```
+    pub fn len_chars_at_or_before_byte(&self, byte_pos: usize) -> usize {
+        self.byte_to_char(byte_pos)
+    }
+
+    /// Convert a range of characters to the equivalent range of bytes in the UTF8 representation
+    /// of the rope. Positions past the end of the rope are clamped, like
+    /// [`char_to_byte`](Self::char_to_byte).
+    ///
+    /// This is faster than converting each end separately. If the whole range is in one node, the
+    /// skip list is only searched once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("aδc"); // δ takes 2 bytes.
+    /// assert_eq!(rope.byte_range_of_char_range(1..2), 1..3);
+    /// assert_eq!(rope.byte_range_of_char_range(1..100), 1..4);
+    /// ```
+    pub fn byte_range_of_char_range(&self, range: Range<usize>) -> Range<usize> {
+        let len = self.len_chars();
+        let end = range.end.min(len);
+        let start = range.start.min(end);
+        if start == len { return self.num_bytes..self.num_bytes; }
+
+        let cursor = self.cursor_at_char(start, false);
+        let start_byte = cursor.global_byte_pos(self.head.height);
+        let here = unsafe { &*cursor.here_ptr() };
+        let local_end = cursor.local_char_pos() + (end - start);
+        let end_byte = if local_end <= here.num_chars() {
+            start_byte + here.str.count_bytes(local_end) - here.str.count_bytes(cursor.local_char_pos())
+        } else {
+            self.char_to_byte(end)
+        };
+        start_byte..end_byte
+    }
+
+    /// Convert a range of bytes in the UTF8 representation of the rope to the equivalent range of
+    /// characters. Each end is converted with [`byte_to_char`](Self::byte_to_char), so byte
+    /// offsets in the middle of a character round down to the start of that character.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("aδc"); // δ takes 2 bytes.
+    /// assert_eq!(rope.char_range_of_byte_range(1..3), 1..2);
+    /// assert_eq!(rope.char_range_of_byte_range(2..100), 1..3);
+    /// ```
+    pub fn char_range_of_byte_range(&self, range: Range<usize>) -> Range<usize> {
+        let end = self.byte_to_char(range.end);
+        if range.start >= range.end { return end..end; }
+        self.byte_to_char(range.start)..end
+    }
+
     /// Returns `true` if the rope contains no elements.
     pub fn is_empty(&self) -> bool { self.num_bytes == 0 }
 
+    /// Reserve space for appending at least `additional` more bytes to the end of the rope, for
+    /// append heavy workloads like logging. Empty nodes are allocated up front, and then used
+    /// (instead of allocating) by later inserts which need new nodes.
+    ///
+    /// Reserved space is included in [`mem_size`](Self::mem_size), and it is released when the
+    /// rope is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Log started\n");
+    /// rope.reserve_end(10_000);
+    /// assert!(rope.end_capacity() >= 10_000);
+    ///
+    /// let size = rope.mem_size();
+    /// for _ in 0..100 {
+    ///     rope.insert(rope.len_chars(), "Another line\n");
+    /// }
+    /// assert_eq!(rope.mem_size(), size); // No more memory was allocated.
+    /// ```
+    pub fn reserve_end(&mut self, additional: usize) {
+        let available = self.end_capacity();
+        if additional <= available { return; }
+
+        let num_nodes = (additional - available).div_ceil(NODE);
+        self.spare_nodes.reserve(num_nodes);
+        for _ in 0..num_nodes {
+            let height = self.heights.next_height(BIAS);
+            self.spare_nodes.push(Node::alloc_with_height(height, ""));
+            self.node_mem_grew(Node::<NODE>::layout_with_height(height).size());
+        }
+    }
+
+    /// The approximate number of bytes which can be appended to the rope before it needs to
+    /// allocate more memory. This is the free space in the last node, plus any space reserved with
+    /// [`reserve_end`](Self::reserve_end).
+    ///
+    /// This is approximate because content is never split in the middle of a character, and
+    /// inserts which don't fit in the last node start a new node instead of filling it up.
+    pub fn end_capacity(&self) -> usize {
+        let last = unsafe { &*self.cursor_at_end().here_ptr() };
+        NODE - last.str.len_bytes() + self.spare_nodes.len() * NODE
+    }
+
+    /// Rewrite the nodes overlapping `range` so the content is packed into as few nodes (and
+    /// chunks) as possible. This is useful before handing part of a document to code which prefers
+    /// contiguous memory - like a renderer reading the viewport through
+    /// [`slice_chunks`](Self::slice_chunks). The range is clamped to the document.
+    ///
+    /// Editing a rope one character at a time can leave content spread over many partly full
+    /// nodes, each of which may be split into two chunks by its gap. Only the nodes overlapping the
+    /// range are rewritten, so this takes time proportional to the size of the range rather than
+    /// the document. The content doesn't change, but the rewritten chunks get new IDs (see
+    /// [`chunks_with_ids`](Self::chunks_with_ids)). This isn't recorded by
+    /// [`with_recording`](Self::with_recording).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::new();
+    /// for _ in 0..1000 {
+    ///     rope.insert(0, "ab");
+    ///     rope.insert(1, "c");
+    /// }
+    /// let chunks = rope.slice_chunks(0..600).count();
+    /// rope.defragment_range(0..600);
+    /// assert!(rope.slice_chunks(0..600).count() < chunks);
+    /// assert_eq!(rope.slice_chars(0..6).collect::<String>(), "acbacb");
+    /// ```
+    pub fn defragment_range(&mut self, range: Range<usize>) {
+        let len = self.len_chars();
+        let end = range.end.min(len);
+        if range.start >= end { return; }
+
+        // Widen the range out to whole nodes.
+        let cursor = self.cursor_at_char(range.start, false);
+        let start = range.start - cursor.local_char_pos();
+        let cursor = self.cursor_at_char(end, true);
+        let end = end - cursor.local_char_pos() + unsafe { (*cursor.here_ptr()).num_chars() };
+
+        let content = self.slice_chunks(start..end).strings().collect::<String>();
+        let recording = self.recording.take();
+        self.edit_cache = None;
+        let mut cursor = self.cursor_at_char(start, true);
+        unsafe {
+            self.del_at_cursor(&mut cursor, end - start);
+            // New nodes are filled up before moving on to the next one.
+            self.insert_at_cursor(&mut cursor, &content);
+        }
+        self.recording = recording;
+    }
+
+    /// Check that a character position (eg received over the network, or from a plugin) names a
+    /// valid location in the rope. Every position from 0 to [`len_chars`](Self::len_chars)
+    /// (inclusive) is valid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert!(rope.validate_char_pos(5).is_ok());
+    /// assert_eq!(rope.validate_char_pos(6), Err(PosError::new(6, 5)));
+    /// ```
+    pub fn validate_char_pos(&self, pos: usize) -> Result<(), PosError> {
+        let len_chars = self.len_chars();
+        if pos <= len_chars { Ok(()) } else { Err(PosError::new(pos, len_chars)) }
+    }
+
+    /// Check that a character range names a valid span of the rope. The range must not be
+    /// inverted, and must not extend past [`len_chars`](Self::len_chars).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert!(rope.validate_char_range(2..5).is_ok());
+    /// assert!(rope.validate_char_range(4..6).is_err());
+    /// assert!(rope.validate_char_range(3..2).is_err());
+    /// ```
+    pub fn validate_char_range(&self, range: Range<usize>) -> Result<(), RangeError> {
+        let len_chars = self.len_chars();
+        if range.start <= range.end && range.end <= len_chars { Ok(()) }
+        else { Err(RangeError::new(range, len_chars)) }
+    }
+
+    /// Clamp a character position to the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.clamp_char_pos(3), 3);
+    /// assert_eq!(rope.clamp_char_pos(100), 5);
+    /// ```
+    pub fn clamp_char_pos(&self, pos: usize) -> usize {
+        pos.min(self.len_chars())
+    }
+
+    /// Clamp an arbitrary character range (eg received over the network) to the document, and
+    /// round its ends outwards to the nearest `boundary`. Inverted ranges become empty ranges at
+    /// their end. Empty ranges stay empty.
+    ///
+    /// Returns the sanitized range, and whether it differs from the passed range.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε");
+    /// assert_eq!(rope.sanitize_range(1..3, Boundary::Char), (1..3, false));
+    /// assert_eq!(rope.sanitize_range(3..100, Boundary::Char), (3..5, true));
+    /// assert_eq!(rope.sanitize_range(4..2, Boundary::Char), (2..2, true));
+    /// ```
+    pub fn sanitize_range(&self, range: Range<usize>, boundary: Boundary) -> (Range<usize>, bool) {
+        let end = range.end.min(self.len_chars());
+        let start = range.start.min(end);
+        let sanitized = match boundary {
+            Boundary::Char => start..end,
+            #[cfg(feature = "unicode-segmentation")]
+            Boundary::Grapheme => {
+                let empty = start == end;
+                let start = if start < self.len_chars() { self.prev_grapheme_boundary(start + 1) } else { start };
+                let end = if empty { start } else { self.next_grapheme_boundary(end - 1) };
+                start..end
+            }
+        };
+        let changed = sanitized != range;
+        (sanitized, changed)
+    }
+
+    /// Check if the content in the specified character range is equal to the passed string. Only
+    /// the nodes covering the range are visited, and no intermediate string is allocated.
+    ///
+    /// Returns false if the range extends past the end of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("Hi Duane!");
+    /// assert!(rope.eq_slice(3..8, "Duane"));
+    /// assert!(!rope.eq_slice(3..8, "Mike"));
+    /// assert!(!rope.eq_slice(3..100, "Duane!"));
+    /// ```
+    pub fn eq_slice(&self, range: Range<usize>, mut other: &str) -> bool {
+        if range.start > range.end || range.end > self.len_chars() { return false; }
+        // Every character takes at least 1 byte.
+        if other.len() < range.end - range.start { return false; }
+
+        for s in self.slice_chunks(range).strings() {
+            if !other.starts_with(s) { return false; }
+            other = &other[s.len()..];
+        }
+        other.is_empty()
+    }
+
     pub fn check(&self) {
         assert!(self.head.height >= 1);
         assert!(self.head.height < MAX_HEIGHT_U8 + 1);
@@ -866,41 +2586,69 @@ impl JumpRope {
         let mut iter = [SkipEntry::new(); MAX_HEIGHT];
         for i in 0..self.head.height {
             // Bleh.
-            iter[i as usize].node = &self.head as *const Node as *mut Node;
+            iter[i as usize].node = &self.head as *const Node<NODE> as *mut Node<NODE>;
         }
 
         let mut num_bytes: usize = 0;
         let mut num_chars = 0;
+        let mut num_lines = 0;
+        #[cfg(feature = "wchar_conversion")]
+        let mut num_pairs = 0;
+        let mut bytes_before = 0;
 
         for n in self.node_iter() {
             // println!("visiting {:?}", n.as_str());
-            assert!(!n.str.is_empty() || std::ptr::eq(n, &self.head));
+            assert!(!n.str.is_empty() || core::ptr::eq(n, &self.head));
             assert!(n.height <= MAX_HEIGHT_U8);
             assert!(n.height >= 1);
             n.str.check();
 
             assert_eq!(count_chars(n.as_str_1()) + count_chars(n.as_str_2()), n.num_chars());
+            assert_eq!(count_newlines(n.as_str_1()) + count_newlines(n.as_str_2()), n.num_lines());
+            #[cfg(feature = "wchar_conversion")]
+            assert_eq!(count_surrogate_pairs(n.as_str_1()) + count_surrogate_pairs(n.as_str_2()), n.num_pairs());
             for (i, entry) in iter[0..n.height as usize].iter_mut().enumerate() {
-                assert_eq!(entry.node as *const Node, n as *const Node);
+                assert_eq!(entry.node as *const Node<NODE>, n as *const Node<NODE>);
                 assert_eq!(entry.skip_chars, num_chars);
+                assert_eq!(entry.skip_bytes, bytes_before);
+                assert_eq!(entry.skip_lines, num_lines);
+                #[cfg(feature = "wchar_conversion")]
+                assert_eq!(entry.skip_pairs, num_pairs);
 
                 // println!("replacing entry {:?} with {:?}", entry, n.nexts()[i].node);
                 entry.node = n.nexts()[i].node;
                 entry.skip_chars += n.nexts()[i].skip_chars;
+                entry.skip_bytes += n.nexts()[i].skip_bytes;
+                entry.skip_lines += n.nexts()[i].skip_lines;
+                #[cfg(feature = "wchar_conversion")] {
+                    entry.skip_pairs += n.nexts()[i].skip_pairs;
+                }
             }
 
+            assert_eq!(n.str.len_bytes(), n.num_bytes());
+            bytes_before += n.num_bytes();
+
             num_bytes += n.str.len_bytes();
             num_chars += n.num_chars();
+            num_lines += n.num_lines();
+            #[cfg(feature = "wchar_conversion")] {
+                num_pairs += n.num_pairs();
+            }
         }
 
         for entry in iter[0..self.head.height as usize].iter() {
             // println!("{:?}", entry);
             assert!(entry.node.is_null());
             assert_eq!(entry.skip_chars, num_chars);
+            assert_eq!(entry.skip_bytes, bytes_before);
+            assert_eq!(entry.skip_lines, num_lines);
+            #[cfg(feature = "wchar_conversion")]
+            assert_eq!(entry.skip_pairs, num_pairs);
         }
 
         // println!("self bytes: {}, count bytes {}", self.num_bytes, num_bytes);
         assert_eq!(self.num_bytes, num_bytes);
+        assert_eq!(self.mem_size(), mem::size_of::<Self>() + self.node_mem);
         assert_eq!(self.len_chars(), num_chars);
     }
 
@@ -918,31 +2666,35 @@ impl JumpRope {
         let mut nodes = self.node_iter();
         let mut size = 0;
         // The first node is the head. Count the actual head size.
-        size += std::mem::size_of::<Self>();
+        size += core::mem::size_of::<Self>();
         nodes.next(); // And discard it from the iterator.
 
         for n in nodes {
-            let layout = Node::layout_with_height(n.height);
+            let layout = Node::<NODE>::layout_with_height(n.height);
             size += layout.size();
         }
+        for &n in &self.spare_nodes {
+            size += Node::<NODE>::layout_with_height(unsafe { (*n).height }).size();
+        }
 
         size
     }
 
     #[allow(unused)]
+    #[cfg(feature = "std")]
     pub(crate) fn print(&self) {
         println!("chars: {}\tbytes: {}\theight: {}", self.len_chars(), self.num_bytes, self.head.height);
 
         print!("HEAD:");
         for s in self.head.nexts() {
-            print!(" |{} ", s.skip_chars);
+            print!(" |{} ({}) ", s.skip_chars, s.skip_lines);
         }
         println!();
 
         for (i, node) in self.node_iter().enumerate() {
             print!("{}:", i);
             for s in node.nexts() {
-                print!(" |{} ", s.skip_chars);
+                print!(" |{} ({}) ", s.skip_chars, s.skip_lines);
             }
             println!("      : {:?} + {:?}", node.as_str_1(), node.as_str_2());
         }
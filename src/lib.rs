@@ -52,9 +52,36 @@
 //! features turned off:
 //!
 //! ```toml
-//! jumprope = { default-features = false }
+//! jumprope = { default-features = false, features = ["std"] }
 //! ```
 //!
+//! # `no_std` support
+//!
+//! The rope itself (the skip list of gap buffers) only needs an allocator. The `std` feature
+//! (enabled by default) adds the conveniences which need an operating system - seeding ropes from
+//! an entropy source, loading files and implementing `std::error::Error` for the error types.
+//!
+//! Without `std`, jumprope is `#![no_std]` and depends only on `alloc`. [`JumpRope::new`] falls
+//! back to a fixed seed in this configuration, so callers who need DoS protection should pass their
+//! own entropy to [`JumpRope::new_from_seed`].
+//!
+//! The `rand` crate is only needed by the `std`, `ddos_protection` and `testing` features. With
+//! all of them disabled, node heights come from a tiny builtin PRNG instead. Callers can also
+//! supply heights themselves using [`JumpRope::new_with_heights`].
+//!
+//! # API stability
+//!
+//! Jumprope follows semver. Structs which report information about a rope (like [`RopeConfig`],
+//! [`PositionInfo`] and the error types) and enums describing formats (like [`Escaping`]) are
+//! marked `#[non_exhaustive]`, so new fields and variants can be added in minor releases. Types
+//! which callers may need to construct themselves (like [`PosError`] and [`FileInfo`]) have `new`
+//! constructors for this.
+//!
+//! The exceptions are [`GapBuffer`] (exported for advanced use, and tied to the rope's internals),
+//! the `testing` module and the `tuning` binary. These may change in any release. The specific
+//! values reported by [`JumpRope::config`] and the way content is split into chunks can also
+//! change between releases.
+//!
 //!
 //!
 //! # A rant on character lengths
@@ -119,10 +146,61 @@
 //! assert_eq!(rope, "🐻");
 //! ```
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 mod jumprope;
 mod gapbuffer;
 mod utils;
 mod iter;
+mod error;
+mod ops;
+mod history;
+mod memory;
+mod config;
+mod lines;
+mod sort;
+mod search;
+mod pattern;
+mod segmentation;
+mod block;
+mod tabs;
+mod escape;
+mod convert;
+mod cost;
+mod slice;
+mod index;
+mod chunk_data;
+mod cursor;
+mod buffered;
+#[cfg(feature = "wchar_conversion")]
+mod wchar;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "rayon")]
+mod parallel;
 // mod params;
 
-pub use crate::jumprope::JumpRope;
\ No newline at end of file
+pub use crate::jumprope::{Boundary, JumpRope, JumpRopeCustom, OverwriteLimit};
+pub use crate::gapbuffer::{CapacityError, GapBuffer};
+pub use crate::ops::ReversibleOp;
+pub use crate::history::History;
+pub use crate::memory::MemoryEvent;
+pub use crate::config::RopeConfig;
+pub use crate::cost::EditCost;
+pub use crate::slice::RopeSlice;
+pub use crate::index::RopeIndex;
+pub use crate::chunk_data::ChunkData;
+pub use crate::cursor::{Cursor, MutCursor};
+pub use crate::buffered::JumpRopeBuf;
+pub use crate::lines::{Indentation, PositionInfo};
+pub use crate::sort::LineOrder;
+pub use crate::escape::Escaping;
+pub use crate::search::{Matches, RevMatches, Split};
+pub use crate::pattern::RopePattern;
+pub use crate::error::{PosError, RangeError};
+#[cfg(feature = "std")]
+pub use crate::file::{Encoding, FileInfo, LineEnding};
@@ -0,0 +1,819 @@
+use core::ops::Range;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::mem;
+use crate::JumpRope;
+use crate::jumprope::Node;
+use crate::iter::StrContentIter;
+#[cfg(feature = "rand")]
+use rand::RngCore;
+use crate::utils::{count_chars, count_newlines, next_column, str_bytes_to_chars};
+
+/// The leading whitespace on a line. See [`JumpRope::indentation_of_line`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Indentation {
+    /// The character range of the indentation within the rope.
+    pub range: Range<usize>,
+    /// The display width of the indentation in columns, with tabs expanded to the next tab stop.
+    pub width: usize,
+}
+
+/// A position in a rope, expressed in every coordinate system at once. See
+/// [`Cursor::position_info`](crate::Cursor::position_info).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PositionInfo {
+    /// The position in unicode characters from the start of the document.
+    pub char_pos: usize,
+    /// The position in UTF8 bytes from the start of the document.
+    pub byte_pos: usize,
+    /// The (0-based) line containing the position.
+    pub line: usize,
+    /// The number of characters between the start of the line and the position.
+    pub column: usize,
+}
+
+/// An iterator over the indexes of lines which match a predicate. See
+/// [`JumpRope::find_all_lines_matching`].
+pub struct LinesMatching<'a, F: FnMut(&str) -> bool> {
+    chunks: StrContentIter<'a>,
+    // The unprocessed remainder of the current chunk.
+    chunk: &'a str,
+    // Content of the current line, when it spans multiple chunks.
+    buf: String,
+    line: usize,
+    done: bool,
+    pred: F,
+}
+
+impl<'a, F: FnMut(&str) -> bool> Iterator for LinesMatching<'a, F> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        loop {
+            if let Some(i) = self.chunk.find('\n') {
+                let content = &self.chunk[..i];
+                self.chunk = &self.chunk[i + 1..];
+                let line = self.line;
+                self.line += 1;
+
+                // Lines contained within a single chunk are passed to the predicate directly.
+                let matched = if self.buf.is_empty() {
+                    (self.pred)(content)
+                } else {
+                    self.buf.push_str(content);
+                    let matched = (self.pred)(&self.buf);
+                    self.buf.clear();
+                    matched
+                };
+                if matched { return Some(line); }
+            } else {
+                self.buf.push_str(self.chunk);
+                self.chunk = "";
+
+                match self.chunks.next() {
+                    Some(chunk) => self.chunk = chunk,
+                    None if self.done => return None,
+                    None => {
+                        // The last line has no trailing newline (and might be empty).
+                        self.done = true;
+                        let matched = (self.pred)(&self.buf);
+                        self.buf.clear();
+                        return if matched { Some(self.line) } else { None };
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl JumpRope {
+    fn ends_with_newline(&self) -> bool {
+        let len = self.len_chars();
+        len > 0 && self.slice_chars(len - 1..len).next() == Some('\n')
+    }
+
+    /// Get the character position of the start of the specified (0-based) line. Lines are
+    /// numbered the same way as [`len_lines`](Self::len_lines). Lines past the end of the document
+    /// are clamped to the end of the document.
+    ///
+    /// This runs in *O(log n)* time, using the line counts stored in the skip list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε\nabc\n");
+    /// assert_eq!(rope.line_to_char(0), 0);
+    /// assert_eq!(rope.line_to_char(1), 6);
+    /// assert_eq!(rope.line_to_char(2), 10);
+    /// assert_eq!(rope.line_to_char(100), 10);
+    /// ```
+    pub fn line_to_char(&self, line: usize) -> usize {
+        if line == 0 { return 0; }
+        if line > self.len_newlines() { return self.len_chars(); }
+
+        // Find the node containing the line'th newline.
+        let mut e = &self.head;
+        let mut height = self.head.height as usize - 1;
+        let mut newlines_remaining = line;
+        let mut char_pos = 0;
+        loop {
+            let next = e.nexts()[height];
+            if next.skip_lines < newlines_remaining {
+                newlines_remaining -= next.skip_lines;
+                char_pos += next.skip_chars;
+                e = unsafe { &*next.node };
+            } else if height == 0 {
+                break;
+            } else {
+                height -= 1;
+            }
+        }
+
+        let (s1, s2) = (e.str.start_as_str(), e.str.end_as_str());
+        let n = count_newlines(s1);
+        let (s, offset) = if newlines_remaining <= n {
+            (s1, 0)
+        } else {
+            newlines_remaining -= n;
+            (s2, e.str.gap_start_chars as usize)
+        };
+        let (byte, _) = s.match_indices('\n').nth(newlines_remaining - 1).unwrap();
+        char_pos + offset + str_bytes_to_chars(s, byte) + 1
+    }
+
+    /// Get the (0-based) line containing the specified character position. Lines are numbered
+    /// the same way as [`len_lines`](Self::len_lines). Positions past the end of the document are
+    /// clamped to the end of the document.
+    ///
+    /// This runs in *O(log n)* time, using the line counts stored in the skip list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κόσμε\nabc\n");
+    /// assert_eq!(rope.char_to_line(0), 0);
+    /// assert_eq!(rope.char_to_line(5), 0); // The newline is part of the first line
+    /// assert_eq!(rope.char_to_line(6), 1);
+    /// assert_eq!(rope.char_to_line(10), 2);
+    /// assert_eq!(rope.char_to_line(100), 2);
+    /// ```
+    pub fn char_to_line(&self, char_pos: usize) -> usize {
+        let char_pos = char_pos.min(self.len_chars());
+        let cursor = self.cursor_at_char(char_pos, true);
+        let node = unsafe { &*cursor.here_ptr() };
+        cursor.global_lines_before_node(self.head.height)
+            + node.str.count_newlines_in(0..cursor.local_char_pos())
+    }
+
+    /// Count the number of newline (`'\n'`) characters in the specified character range. This is
+    /// useful for converting a character range into a range of lines, eg to display diagnostics.
+    ///
+    /// The range is clamped to the document. This runs in *O(log n)* time, using the line counts
+    /// stored in the skip list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a\nb\nc\n");
+    /// assert_eq!(rope.count_newlines_in_range(0..6), 3);
+    /// assert_eq!(rope.count_newlines_in_range(2..5), 1);
+    /// assert_eq!(rope.count_newlines_in_range(2..100), 2);
+    /// ```
+    pub fn count_newlines_in_range(&self, range: Range<usize>) -> usize {
+        let end = range.end.min(self.len_chars());
+        let start = range.start.min(end);
+        self.char_to_line(end) - self.char_to_line(start)
+    }
+
+    /// Count the number of newline (`'\n'`) characters in the specified range of bytes in the
+    /// UTF8 representation of the rope. This is designed for rendering minimaps and scrollbars,
+    /// where positions are often tracked in bytes and converting them to characters every frame
+    /// is too slow.
+    ///
+    /// The range is clamped to the document. This runs in *O(log n)* time, using the byte and line
+    /// counts stored in the skip list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("κό\nb\nc\n"); // κ and ό take 2 bytes each.
+    /// assert_eq!(rope.lines_count_in_byte_range(0..5), 1);
+    /// assert_eq!(rope.lines_count_in_byte_range(5..100), 2);
+    /// ```
+    pub fn lines_count_in_byte_range(&self, range: Range<usize>) -> usize {
+        let end = range.end.min(self.len_bytes());
+        let start = range.start.min(end);
+        self.byte_to_line(end) - self.byte_to_line(start)
+    }
+
+    // The number of newlines before the specified byte offset.
+    fn byte_to_line(&self, byte_pos: usize) -> usize {
+        let (cursor, _) = self.cursor_at_byte(byte_pos, true);
+        let node = unsafe { &*cursor.here_ptr() };
+        cursor.global_lines_before_node(self.head.height)
+            + node.str.count_newlines_in(0..cursor.local_char_pos())
+    }
+
+    // Get the character range of the content of the specified line, not including its trailing
+    // newline.
+    pub(crate) fn line_content_range(&self, line: usize) -> Range<usize> {
+        let len = self.len_chars();
+        let start = self.line_to_char(line);
+        let end = self.slice_chars(start..len).position(|c| c == '\n').map_or(len, |p| start + p);
+        start..end
+    }
+
+    /// Get the leading whitespace (spaces and tabs) of the specified line. The result contains
+    /// the character range of the whitespace, and its width in columns when tabs are expanded to
+    /// multiples of `tab_width`.
+    ///
+    /// Only the indentation itself is scanned - the rest of the line isn't read. Lines past the
+    /// end of the document have no indentation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("fn x() {\n  \tfoo();\n}");
+    /// let indent = rope.indentation_of_line(1, 4);
+    /// assert_eq!(indent.range, 9..12);
+    /// assert_eq!(indent.width, 4);
+    /// assert_eq!(rope.slice_chars(indent.range).collect::<String>(), "  \t");
+    ///
+    /// assert_eq!(rope.indentation_of_line(2, 4).width, 0);
+    /// ```
+    pub fn indentation_of_line(&self, line: usize, tab_width: usize) -> Indentation {
+        let start = self.line_to_char(line);
+        let mut len = 0;
+        let mut width = 0;
+        for c in self.slice_chars(start..self.len_chars()) {
+            if c != ' ' && c != '\t' { break; }
+            len += 1;
+            width = next_column(width, c, tab_width);
+        }
+
+        Indentation { range: start..start + len, width }
+    }
+
+    /// Find the position `delta_lines` lines above (negative) or below (positive) `pos`, in the
+    /// column closest to `goal_col`. This implements moving the caret up and down in an editor.
+    ///
+    /// Columns are display columns, counted from the start of the line with tabs expanded to the
+    /// next multiple of `tab_width` (like [`indentation_of_line`](Self::indentation_of_line)). Every
+    /// other character takes one column. The returned position is the character boundary on the
+    /// target line whose column is closest to `goal_col` - so a caret moving onto a line starting
+    /// with a tab lands before or after the tab, whichever is nearer. If the target line is shorter
+    /// than `goal_col`, the returned position is at the end of that line (before its newline).
+    ///
+    /// Callers should remember the goal column between vertical moves, rather than using the column
+    /// of the returned position - so moving through a short line doesn't lose the caret's original
+    /// column.
+    ///
+    /// Moving past the first or last line of the document clamps to that line.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("long line\nab\n\tanother line");
+    /// let pos = 6; // "long l|ine"
+    /// let pos = rope.move_vertically(pos, 1, 6, 4);
+    /// assert_eq!(pos, 12); // End of "ab|"
+    /// let pos = rope.move_vertically(pos, 1, 6, 4);
+    /// assert_eq!(pos, 16); // "\tan|other line". The tab takes 4 columns.
+    /// assert_eq!(rope.move_vertically(pos, -100, 6, 4), 6);
+    /// ```
+    pub fn move_vertically(&self, pos: usize, delta_lines: isize, goal_col: usize, tab_width: usize) -> usize {
+        let pos = pos.min(self.len_chars());
+        let line = self.char_to_line(pos);
+        let max_line = self.len_lines() - 1;
+        let target = if delta_lines < 0 {
+            line.saturating_sub(delta_lines.unsigned_abs())
+        } else {
+            line.saturating_add(delta_lines as usize).min(max_line)
+        };
+
+        let range = self.line_content_range(target);
+        let mut pos = range.start;
+        let mut col = 0;
+        for c in self.slice_chars(range) {
+            let next = next_column(col, c, tab_width);
+            if next > goal_col {
+                // goal_col is inside this character. Pick whichever side is closer.
+                if next - goal_col < goal_col - col { pos += 1; }
+                break;
+            }
+            col = next;
+            pos += 1;
+        }
+        pos
+    }
+
+    /// Get the byte offset, line and column of the specified character position in a single call.
+    /// This is useful for status bars, which need every coordinate after each caret movement.
+    ///
+    /// Positions past the end of the document are clamped to the end. This is a shorthand for
+    /// [`Cursor::position_info`](crate::Cursor::position_info), which finds every coordinate from a
+    /// single search of the skip list.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("abc\nκόσμε");
+    /// let info = rope.position_info(7);
+    /// assert_eq!((info.char_pos, info.byte_pos, info.line, info.column), (7, 10, 1, 3));
+    /// ```
+    pub fn position_info(&self, pos: usize) -> PositionInfo {
+        self.cursor(pos).position_info()
+    }
+
+    /// Return the number of lines in the rope, where a trailing newline starts a new (empty)
+    /// line. This is how most editors number lines in their gutter, and it matches ropey.
+    ///
+    /// With this convention there is always at least one line, even in an empty rope. The result
+    /// is always the number of `'\n'` characters in the document + 1.
+    ///
+    /// The rope keeps count of its newlines as it is edited, so this method runs in constant time
+    /// (*O(1)*).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// assert_eq!(JumpRope::new().len_lines(), 1);
+    /// assert_eq!(JumpRope::from("abc").len_lines(), 1);
+    /// assert_eq!(JumpRope::from("abc\n").len_lines(), 2);
+    /// assert_eq!(JumpRope::from("abc\ndef").len_lines(), 2);
+    /// ```
+    pub fn len_lines(&self) -> usize {
+        self.len_newlines() + 1
+    }
+
+    /// Return the number of lines in the rope, where each line *includes* the `'\n'` which
+    /// terminates it. A trailing newline ends the last line rather than starting a new one. This
+    /// matches the POSIX definition of a line, and what `wc -l` and `str::lines()` report.
+    ///
+    /// An empty rope contains no lines.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// assert_eq!(JumpRope::new().len_lines_inclusive(), 0);
+    /// assert_eq!(JumpRope::from("abc").len_lines_inclusive(), 1);
+    /// assert_eq!(JumpRope::from("abc\n").len_lines_inclusive(), 1);
+    /// assert_eq!(JumpRope::from("abc\ndef").len_lines_inclusive(), 2);
+    /// ```
+    pub fn len_lines_inclusive(&self) -> usize {
+        let newlines = self.len_newlines();
+        if self.is_empty() || self.ends_with_newline() { newlines } else { newlines + 1 }
+    }
+
+    /// Iterate over the (0-based) indexes of all lines whose content matches the passed predicate.
+    /// The line content passed to the predicate does not include the trailing newline. Lines are
+    /// numbered the same way as [`len_lines`](Self::len_lines), so if the document ends in a
+    /// newline the predicate is finally called with an empty line.
+    ///
+    /// The lines are not copied out of the rope unless they span multiple internal chunks. This
+    /// makes it cheap to filter large documents (like logs) using literal or regex matching.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("INFO hi\nERROR oh no\nINFO ok\nERROR again");
+    /// let errors = rope.find_all_lines_matching(|line| line.starts_with("ERROR"))
+    ///     .collect::<Vec<usize>>();
+    /// assert_eq!(errors, &[1, 3]);
+    /// ```
+    pub fn find_all_lines_matching<F: FnMut(&str) -> bool>(&self, pred: F) -> LinesMatching<'_, F> {
+        LinesMatching {
+            chunks: self.chunks().strings(),
+            chunk: "",
+            buf: String::new(),
+            line: 0,
+            done: false,
+            pred,
+        }
+    }
+
+    /// Consume the rope, splitting it into one rope per line. The lines don't include their
+    /// trailing `'\n'`, and lines are numbered the same way as [`len_lines`](Self::len_lines). So
+    /// the result is never empty, and a trailing newline produces a final empty line.
+    ///
+    /// The existing nodes are moved into the new ropes. Only the text in nodes containing a newline
+    /// is copied, so this is much faster than copying each line out of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one\ntwo\n");
+    /// assert_eq!(rope.split_into_lines(), ["one", "two", ""]);
+    /// ```
+    pub fn split_into_lines(mut self) -> Vec<JumpRope> {
+        let mut lines = Vec::with_capacity(self.len_lines());
+        // Each line's RNG is seeded from this rope's. JumpRope::new() would read from the OS
+        // entropy source for every line in release builds.
+        let mut seeds = self.child_seeds();
+        let mut line = JumpRope::new_from_seed(seeds.next_u64());
+        let mut cursor = line.cursor_at_start();
+
+        // Append s to the current line, starting a new line after every newline. Returns the
+        // number of bytes up to and including the last newline, which the caller has copied.
+        let mut copy_lines = |line: &mut JumpRope, cursor: &mut _, s: &str| -> usize {
+            let mut copied = 0;
+            while let Some(i) = s[copied..].find('\n') {
+                unsafe { line.insert_at_cursor(cursor, &s[copied..copied + i]); }
+                lines.push(mem::replace(line, JumpRope::new_from_seed(seeds.next_u64())));
+                *cursor = line.cursor_at_start();
+                copied += i + 1;
+            }
+            copied
+        };
+
+        unsafe {
+            // Content in the head node is stored inline, so it always needs to be copied.
+            self.head.str.gap_to_end();
+            let head = self.head.str.start_as_str();
+            let copied = copy_lines(&mut line, &mut cursor, head);
+            line.insert_at_cursor(&mut cursor, &head[copied..]);
+
+            let mut node = self.head.first_next().node;
+            while !node.is_null() {
+                let next = (*node).first_next().node;
+
+                (*node).str.gap_to_end();
+                let copied = copy_lines(&mut line, &mut cursor, (*node).str.start_as_str());
+                (*node).str.remove(0, copied);
+
+                // Whatever is left of the node (after the last newline) moves to the current line.
+                if (*node).str.is_empty() {
+                    Node::free(node);
+                } else {
+                    let num_chars = count_chars((*node).str.end_as_str());
+                    line.link_node_at(&mut cursor, node, num_chars, true, false);
+                }
+                node = next;
+            }
+
+            self.forget_nodes();
+        }
+
+        lines.push(line);
+        lines
+    }
+
+    /// Remove every line for which `f(line_index, line_content)` returns false. The line content
+    /// doesn't include the trailing newline. Lines are numbered the same way as
+    /// [`len_lines`](Self::len_lines), so if the document ends in a newline `f` is finally called
+    /// with an empty line.
+    ///
+    /// The result is the retained lines joined by newlines. So removing the last line also removes
+    /// the newline before it.
+    ///
+    /// The document is scanned once to find the runs of adjacent removed lines, and then the runs
+    /// are deleted in a single forward walk through the rope. Lines are only copied out of the rope
+    /// if they span multiple chunks.
+    ///
+    /// Returns the number of lines which were removed. If this is 0, the rope wasn't modified.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("INFO hi\nERROR oh no\nINFO ok\nERROR again");
+    /// assert_eq!(rope.retain_lines(|_, line| line.starts_with("ERROR")), 2);
+    /// assert_eq!(rope, "ERROR oh no\nERROR again");
+    /// ```
+    pub fn retain_lines<F: FnMut(usize, &str) -> bool>(&mut self, mut f: F) -> usize {
+        // The ranges to remove, in order. Adjacent ranges are merged.
+        let mut ranges: Vec<Range<usize>> = Vec::new();
+        let mut remove = |r: Range<usize>| match ranges.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => ranges.push(r),
+        };
+
+        let mut line = 0;
+        let mut removed = 0;
+        let mut line_start = 0;
+        // The end of the last retained line (not including its newline).
+        let mut kept_end = None;
+        // Content of the current line, when it spans multiple chunks.
+        let mut buf = String::new();
+        for (s, _) in self.chunks() {
+            let mut rest = s;
+            while let Some(i) = rest.find('\n') {
+                let content = &rest[..i];
+                let (keep, len) = if buf.is_empty() {
+                    (f(line, content), count_chars(content))
+                } else {
+                    buf.push_str(content);
+                    let result = (f(line, &buf), count_chars(&buf));
+                    buf.clear();
+                    result
+                };
+
+                let line_end = line_start + len;
+                if keep {
+                    kept_end = Some(line_end);
+                } else {
+                    remove(line_start..line_end + 1);
+                    removed += 1;
+                }
+                line_start = line_end + 1;
+                line += 1;
+                rest = &rest[i + 1..];
+            }
+            buf.push_str(rest);
+        }
+
+        // The last line has no trailing newline. If it's removed, the document should end at the
+        // end of the last retained line instead.
+        if !f(line, &buf) {
+            removed += 1;
+            let start = kept_end.unwrap_or(0);
+            while ranges.last().is_some_and(|r| r.start >= start) { ranges.pop(); }
+            ranges.push(start..line_start + count_chars(&buf));
+        }
+
+        self.remove_sorted_ranges(&ranges);
+        removed
+    }
+
+    /// Remove lines which are identical to the line before them, like the unix `uniq` command.
+    /// This makes a single pass over the document, and returns the number of lines removed. See
+    /// [`retain_lines`](Self::retain_lines).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("a\na\nb\na\na\na");
+    /// assert_eq!(rope.dedup_consecutive_lines(), 3);
+    /// assert_eq!(rope, "a\nb\na");
+    /// ```
+    pub fn dedup_consecutive_lines(&mut self) -> usize {
+        let mut prev = String::new();
+        self.retain_lines(|i, line| {
+            let keep = i == 0 || line != prev;
+            if keep {
+                prev.clear();
+                prev.push_str(line);
+            }
+            keep
+        })
+    }
+
+    /// Collapse each run of blank lines down to at most `max_consecutive` blank lines. Lines
+    /// containing only whitespace count as blank. This makes a single pass over the document, and
+    /// returns the number of lines removed. See [`retain_lines`](Self::retain_lines).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("a\n\n  \n\nb\n\nc");
+    /// assert_eq!(rope.collapse_blank_lines(1), 2);
+    /// assert_eq!(rope, "a\n\nb\n\nc");
+    /// ```
+    pub fn collapse_blank_lines(&mut self, max_consecutive: usize) -> usize {
+        let mut blanks = 0;
+        self.retain_lines(|_, line| {
+            if line.trim().is_empty() {
+                blanks += 1;
+                blanks <= max_consecutive
+            } else {
+                blanks = 0;
+                true
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+    use crate::lines::{Indentation, PositionInfo};
+
+    #[test]
+    fn line_counts_match_str() {
+        for s in ["", "\n", "\n\n", "a", "a\n", "a\nb", "a\r\nb\r\n", "κό\n𝕐𝕆😘\n\nσμε\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\n"] {
+            let rope = JumpRope::from(s);
+            assert_eq!(rope.len_lines(), s.split('\n').count());
+            assert_eq!(rope.len_lines_inclusive(), s.lines().count());
+        }
+    }
+
+    #[test]
+    fn split_into_lines() {
+        let s = "κό\n𝕐𝕆😘\n\nσμε\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\nyyyyyyyyyyyyyyyyy\n\nzz\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx";
+        for prefix in ["", "a", "\n", "xxxxxxxx\n"] {
+            let mut rope = JumpRope::from(s);
+            rope.insert(0, prefix);
+            let expected = format!("{}{}", prefix, s);
+
+            let lines = rope.split_into_lines();
+            assert_eq!(lines.len(), expected.split('\n').count());
+            for (line, expected) in lines.iter().zip(expected.split('\n')) {
+                line.check();
+                assert_eq!(line, expected);
+            }
+        }
+        assert_eq!(JumpRope::new().split_into_lines(), [""]);
+    }
+
+    #[test]
+    fn dedup_and_collapse() {
+        let s = "κό\nκό\nκό\n\n\n \n\t\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\n\nκό\n";
+
+        let mut rope = JumpRope::from(s);
+        assert_eq!(rope.dedup_consecutive_lines(), 4);
+        assert_eq!(rope, "κό\n\n \n\t\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\n\nκό\n");
+        rope.check();
+
+        for max in 0..5 {
+            let mut rope = JumpRope::from(s);
+            rope.collapse_blank_lines(max);
+            let mut blanks = 0;
+            let expected = s.split('\n').filter(|line| {
+                if line.trim().is_empty() { blanks += 1; blanks <= max } else { blanks = 0; true }
+            }).collect::<Vec<_>>().join("\n");
+            assert_eq!(rope, expected);
+            rope.check();
+        }
+    }
+
+    #[test]
+    fn retain_lines() {
+        for s in ["", "\n", "a", "a\n", "a\nb", "a\nb\nc\n", "κό\n\nxxxxxxxxxxxxxxxxxxxxxx\nσμε", "κό\n𝕐𝕆😘\n\nσμε\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\nyyyyyyyyyyyyyyyyy\n\nzz\n"] {
+            let lines = s.split('\n').collect::<Vec<_>>();
+            // Try every subset of the first 8 lines, keeping the rest.
+            for mask in 0..1u32 << lines.len().min(8) {
+                let keep = |i: usize| i >= 8 || mask & (1 << i) != 0;
+
+                let mut rope = JumpRope::from(s);
+                let mut seen = Vec::new();
+                let removed = rope.retain_lines(|i, line| {
+                    seen.push(line.to_string());
+                    keep(i)
+                });
+                assert_eq!(seen, lines);
+                assert_eq!(removed, (0..lines.len()).filter(|i| !keep(*i)).count());
+
+                let expected = lines.iter().enumerate()
+                    .filter(|(i, _)| keep(*i))
+                    .map(|(_, line)| *line)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                assert_eq!(rope, expected);
+                rope.check();
+            }
+        }
+    }
+
+    #[test]
+    fn line_positions() {
+        let s = "κό\n𝕐𝕆😘\n\nσμε\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\n".repeat(3);
+
+        // Building the rope backwards leaves the gap at the start of each node.
+        let mut backwards = JumpRope::new();
+        for c in s.chars().rev() {
+            backwards.insert(0, c.encode_utf8(&mut [0; 4]));
+        }
+
+        for rope in [JumpRope::from(s.as_str()), backwards] {
+            let mut pos = 0;
+            for (line, content) in s.split('\n').enumerate() {
+                let len = content.chars().count();
+                assert_eq!(rope.line_to_char(line), pos);
+                assert_eq!(rope.line_content_range(line), pos..pos + len);
+                for i in pos..=pos + len {
+                    assert_eq!(rope.char_to_line(i), line);
+                }
+                pos += len + 1;
+            }
+            assert_eq!(rope.line_to_char(100), rope.len_chars());
+            assert_eq!(rope.char_to_line(1000), rope.len_lines() - 1);
+        }
+    }
+
+    #[test]
+    fn newlines_in_range() {
+        let s = "κό\n𝕐𝕆😘\n\nσμε\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\n";
+        let rope = JumpRope::from(s);
+        let chars = s.chars().collect::<Vec<_>>();
+        for start in 0..=chars.len() {
+            for end in start..=chars.len() {
+                let expected = chars[start..end].iter().filter(|&&c| c == '\n').count();
+                assert_eq!(rope.count_newlines_in_range(start..end), expected);
+            }
+        }
+        // Backwards ranges are treated as empty.
+        let (start, end) = (10, 5);
+        assert_eq!(rope.count_newlines_in_range(start..end), 0);
+
+        let bytes = s.char_indices().map(|(i, _)| i).chain([s.len()]).collect::<Vec<_>>();
+        for &start in &bytes {
+            for &end in bytes.iter().filter(|&&end| end >= start) {
+                assert_eq!(rope.lines_count_in_byte_range(start..end), s[start..end].matches('\n').count());
+            }
+        }
+        assert_eq!(rope.lines_count_in_byte_range(3..1000), 5);
+        assert_eq!(rope.lines_count_in_byte_range(end..start), 0);
+    }
+
+    #[test]
+    fn position_info() {
+        let s = "κό\n𝕐𝕆😘\n\nσμε\nxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx";
+        let rope = JumpRope::from(s);
+        for (pos, (byte_pos, _)) in s.char_indices().chain([(s.len(), ' ')]).enumerate() {
+            let before = &s[..byte_pos];
+            let line = before.matches('\n').count();
+            let column = before.rsplit('\n').next().unwrap().chars().count();
+            assert_eq!(rope.position_info(pos), PositionInfo { char_pos: pos, byte_pos, line, column });
+        }
+        assert_eq!(rope.position_info(1000), rope.position_info(rope.len_chars()));
+
+        // Long lines span several nodes, and a cursor can sit at either end of a node.
+        let s = format!("{}\n{}", "κό𝕐".repeat(300), "xyz\nσμε\n".repeat(100));
+        let rope = JumpRope::from(s.as_str());
+        let mut cursor = rope.cursor(0);
+        for (pos, (byte_pos, _)) in s.char_indices().chain([(s.len(), ' ')]).enumerate() {
+            let before = &s[..byte_pos];
+            let line = before.matches('\n').count();
+            let column = before.rsplit('\n').next().unwrap().chars().count();
+            let expected = PositionInfo { char_pos: pos, byte_pos, line, column };
+            assert_eq!(cursor.position_info(), expected);
+            assert_eq!(rope.position_info(pos), expected);
+            cursor.next_char();
+        }
+    }
+
+    #[test]
+    fn indentation() {
+        let rope = JumpRope::from("a\n    b\n\t\tc\n \t \n\t");
+        let expected = [(0..0, 0), (2..6, 4), (8..10, 16), (12..15, 9), (16..17, 8), (17..17, 0)];
+        for (line, (range, width)) in expected.into_iter().enumerate() {
+            assert_eq!(rope.indentation_of_line(line, 8), Indentation { range, width });
+        }
+        assert_eq!(rope.indentation_of_line(2, 0).width, 2);
+    }
+
+    #[test]
+    fn move_vertically() {
+        let rope = JumpRope::from("abcdef\nabc\n\nabcdefghijklmnopqrstuvwxyz");
+        assert_eq!(rope.move_vertically(4, 1, 4, 4), 10);
+        assert_eq!(rope.move_vertically(10, 1, 4, 4), 11);
+        assert_eq!(rope.move_vertically(11, 1, 4, 4), 16);
+        assert_eq!(rope.move_vertically(16, 1, 4, 4), 16);
+        assert_eq!(rope.move_vertically(16, -2, 4, 4), 10);
+        assert_eq!(rope.move_vertically(16, -3, 4, 4), 4);
+        assert_eq!(rope.move_vertically(16, isize::MIN, 4, 4), 4);
+        assert_eq!(rope.move_vertically(0, isize::MAX, 0, 4), 12);
+        assert_eq!(rope.move_vertically(3, 0, 1, 4), 1);
+    }
+
+    #[test]
+    fn move_vertically_with_tabs() {
+        // Line 1 starts at 9. Its tabs end at columns 4 and 8, then "ab" takes columns 8 and 9.
+        // Ties (like column 6, halfway through the second tab) go before the character.
+        let rope = JumpRope::from("abcdefgh\n\t\tab\nx\ty");
+        let expected = [(0, 9), (1, 9), (2, 9), (3, 10), (4, 10), (5, 10), (6, 10), (7, 11), (8, 11), (9, 12), (10, 13), (20, 13)];
+        for (goal_col, pos) in expected {
+            assert_eq!(rope.move_vertically(0, 1, goal_col, 4), pos, "goal column {}", goal_col);
+        }
+
+        // Line 2 is "x\ty", with the tab taking columns 1-3 (or 1-7 with 8 column tabs).
+        assert_eq!(rope.move_vertically(0, 2, 3, 4), 16);
+        assert_eq!(rope.move_vertically(0, 2, 4, 4), 16);
+        assert_eq!(rope.move_vertically(0, 2, 5, 4), 17);
+        assert_eq!(rope.move_vertically(0, 2, 4, 8), 15);
+        assert_eq!(rope.move_vertically(0, 2, 5, 8), 16);
+        // A tab width of 0 counts tabs as 1 column, the same as next_column.
+        assert_eq!(rope.move_vertically(0, 2, 2, 0), 16);
+    }
+
+    #[test]
+    fn find_lines_matching() {
+        let s = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaax\n\nbx\n𝕐𝕆😘xxxxxxxxxxxxxxxxxxxxxxxxxxx\nyyy\n";
+        let rope = JumpRope::from(s);
+
+        let mut lines = Vec::new();
+        let matched = rope.find_all_lines_matching(|line| {
+            lines.push(line.to_string());
+            line.contains('x')
+        }).collect::<Vec<_>>();
+        assert_eq!(matched, &[0, 2, 3]);
+        assert_eq!(lines, s.split('\n').collect::<Vec<_>>());
+
+        assert_eq!(JumpRope::new().find_all_lines_matching(|l| l.is_empty()).collect::<Vec<_>>(), &[0]);
+    }
+}
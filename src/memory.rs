@@ -0,0 +1,154 @@
+// Memory budget callbacks. Applications with lots of open documents can use these to notice when a
+// rope gets big (or shrinks again) without polling mem_size().
+
+use core::mem;
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use crate::JumpRopeCustom;
+
+/// Reported to the callback registered with [`JumpRope::set_memory_callback`] when the rope's
+/// memory usage crosses one of the configured thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MemoryEvent {
+    /// The threshold which was crossed, in bytes.
+    pub threshold: usize,
+    /// The approximate number of bytes used by the rope after the change. This is the same value
+    /// returned by [`JumpRope::mem_size`].
+    pub mem_size: usize,
+    /// True if memory usage grew to meet the threshold, false if it shrank back below it.
+    pub grew: bool,
+}
+
+pub(crate) struct MemoryWatch {
+    thresholds: Vec<usize>,
+    callback: Box<dyn FnMut(MemoryEvent)>,
+}
+
+impl<const NODE: usize, const BIAS: u8> JumpRopeCustom<NODE, BIAS> {
+    /// Register a callback which is called whenever the memory used by the rope crosses one of
+    /// the passed thresholds (in bytes). The callback is called once per threshold crossed, with
+    /// `grew` set when the rope grows to meet the threshold and cleared when the rope shrinks back
+    /// below it.
+    ///
+    /// Memory usage is approximate, and only changes when nodes are allocated or freed. It is
+    /// tracked as the rope is edited, so this is cheap to leave enabled. Registering a new callback
+    /// replaces the previous one. Callbacks are not cloned along with the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// use std::rc::Rc;
+    /// use std::cell::Cell;
+    ///
+    /// let big = Rc::new(Cell::new(false));
+    /// let big2 = big.clone();
+    ///
+    /// let mut rope = JumpRope::new();
+    /// let threshold = rope.mem_size() + 10_000;
+    /// rope.set_memory_callback(&[threshold], move |event| big2.set(event.grew));
+    ///
+    /// rope.insert(0, "x".repeat(20_000));
+    /// assert!(big.get());
+    /// rope.remove(0..20_000);
+    /// assert!(!big.get());
+    /// ```
+    pub fn set_memory_callback<F: FnMut(MemoryEvent) + 'static>(&mut self, thresholds: &[usize], callback: F) {
+        self.memory_watch = Some(Box::new(MemoryWatch {
+            thresholds: thresholds.to_vec(),
+            callback: Box::new(callback),
+        }));
+    }
+
+    /// Remove the callback registered with [`set_memory_callback`](Self::set_memory_callback), if
+    /// any.
+    pub fn clear_memory_callback(&mut self) {
+        self.memory_watch = None;
+    }
+
+    pub(crate) fn node_mem_grew(&mut self, bytes: usize) {
+        let old = self.node_mem;
+        self.node_mem += bytes;
+        self.notify_memory(old);
+    }
+
+    pub(crate) fn node_mem_shrank(&mut self, bytes: usize) {
+        let old = self.node_mem;
+        self.node_mem -= bytes;
+        self.notify_memory(old);
+    }
+
+    fn notify_memory(&mut self, old_node_mem: usize) {
+        let Some(watch) = self.memory_watch.as_mut() else { return; };
+        let base = mem::size_of::<Self>();
+        let (old, new) = (base + old_node_mem, base + self.node_mem);
+
+        for &threshold in &watch.thresholds {
+            let grew = old < threshold && threshold <= new;
+            let shrank = new < threshold && threshold <= old;
+            if grew || shrank {
+                (watch.callback)(MemoryEvent { threshold, mem_size: new, grew });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::JumpRope;
+
+    #[test]
+    fn memory_thresholds() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events2 = events.clone();
+
+        let mut rope = JumpRope::new();
+        let base = rope.mem_size();
+        rope.set_memory_callback(&[base + 1, base + 1000, base + 2000], move |e| {
+            events2.borrow_mut().push((e.threshold - base, e.grew));
+        });
+
+        rope.insert(0, "κό𝕐𝕆😘σμε".repeat(200));
+        rope.check();
+        assert_eq!(*events.borrow(), [(1, true), (1000, true), (2000, true)]);
+
+        events.borrow_mut().clear();
+        rope.remove(0..rope.len_chars());
+        rope.check();
+        // The thresholds are crossed again, in some order.
+        let mut e = events.borrow().clone();
+        e.sort();
+        assert_eq!(e, [(1, false), (1000, false), (2000, false)]);
+
+        rope.clear_memory_callback();
+        events.borrow_mut().clear();
+        rope.insert(0, "x".repeat(1000));
+        assert!(events.borrow().is_empty());
+    }
+
+    #[test]
+    fn memory_thresholds_with_reserve() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let events2 = events.clone();
+
+        let mut rope = JumpRope::new();
+        rope.reserve_end(5000);
+        let size = rope.mem_size();
+        rope.set_memory_callback(&[size + 1], move |e| events2.borrow_mut().push(e.grew));
+
+        // Filling up the reserved nodes doesn't allocate, so the threshold is never crossed.
+        for _ in 0..50 {
+            rope.insert(rope.len_chars(), "Another line\n");
+        }
+        rope.check();
+        assert_eq!(rope.mem_size(), size);
+        assert!(events.borrow().is_empty());
+
+        // But allocating past the reservation does cross it.
+        rope.insert(rope.len_chars(), "x".repeat(10_000));
+        assert_eq!(*events.borrow(), [true]);
+    }
+}
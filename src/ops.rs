@@ -0,0 +1,215 @@
+use core::ops::Range;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::JumpRopeCustom;
+use crate::utils::count_chars;
+
+/// A single edit made to a rope, along with the text needed to invert it.
+///
+/// Positions are unicode character offsets, matching the rest of the jumprope API. Operations are
+/// produced by [`JumpRope::with_recording`], and can be replayed against another copy of the
+/// document (eg to broadcast edits to collaborators) or inverted to implement undo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReversibleOp {
+    /// `content` was inserted at character offset `pos`.
+    Insert { pos: usize, content: String },
+    /// `content` was removed from character offset `pos`.
+    Remove { pos: usize, content: String },
+}
+
+impl ReversibleOp {
+    /// The character range this operation touches in the document it was applied to. For inserts
+    /// this is the range of the inserted text *after* the insert, and for removes this is the
+    /// deleted range *before* the remove.
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            ReversibleOp::Insert { pos, content } | ReversibleOp::Remove { pos, content } => {
+                *pos..*pos + count_chars(content)
+            }
+        }
+    }
+
+    /// Return the operation which undoes this operation.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("hi");
+    /// let ops = rope.with_recording(|r| r.insert(2, " there"));
+    /// ops[0].inverse().apply(&mut rope);
+    /// assert_eq!(rope, "hi");
+    /// ```
+    pub fn inverse(&self) -> Self {
+        match self {
+            ReversibleOp::Insert { pos, content } => ReversibleOp::Remove { pos: *pos, content: content.clone() },
+            ReversibleOp::Remove { pos, content } => ReversibleOp::Insert { pos: *pos, content: content.clone() },
+        }
+    }
+
+    /// Apply this operation to the passed rope.
+    ///
+    /// The rope is expected to be in the state the operation was originally recorded against. If
+    /// applying the operation is itself recorded, it produces an equivalent op.
+    pub fn apply<const NODE: usize, const BIAS: u8>(&self, rope: &mut JumpRopeCustom<NODE, BIAS>) {
+        match self {
+            ReversibleOp::Insert { pos, content } => rope.insert(*pos, content),
+            ReversibleOp::Remove { .. } => rope.remove(self.range()),
+        }
+    }
+}
+
+// Puts back the outer recording when with_recording() returns, or if the passed function panics.
+// Otherwise a panic would leave the rope recording every later edit.
+struct RestoreRecording<'a, const NODE: usize, const BIAS: u8> {
+    rope: &'a mut JumpRopeCustom<NODE, BIAS>,
+    outer: Option<Vec<ReversibleOp>>,
+}
+
+impl<const NODE: usize, const BIAS: u8> Drop for RestoreRecording<'_, NODE, BIAS> {
+    fn drop(&mut self) {
+        // After a panic, the edits made before it are still passed on to the outer recording.
+        let ops = self.rope.recording.take().unwrap_or_default();
+        self.rope.recording = self.outer.take().map(|mut outer| {
+            outer.extend(ops);
+            outer
+        });
+    }
+}
+
+impl<const NODE: usize, const BIAS: u8> JumpRopeCustom<NODE, BIAS> {
+    /// Run the passed function against this rope, returning a log of every edit it made.
+    ///
+    /// Each recorded operation contains enough information to invert it, so the log can be used
+    /// both for undo and for sending edits to remote peers. Applying the returned operations in
+    /// order to a copy of the original document produces the edited document. Applying their
+    /// inverses in reverse order restores the original.
+    ///
+    /// Calls may be nested. Any edits made within an inner call are also reported to the outer
+    /// call. If `f` panics, recording stops as if the call had returned, and the edits made before
+    /// the panic are still reported to any outer call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("Hi Mike!");
+    /// let ops = rope.with_recording(|r| {
+    ///     r.replace(3..7, "Duane");
+    ///     r.insert(0, "Oh! ");
+    /// });
+    /// assert_eq!(rope, "Oh! Hi Duane!");
+    ///
+    /// // Undo everything.
+    /// for op in ops.iter().rev() {
+    ///     op.inverse().apply(&mut rope);
+    /// }
+    /// assert_eq!(rope, "Hi Mike!");
+    /// ```
+    pub fn with_recording<F: FnOnce(&mut Self)>(&mut self, f: F) -> Vec<ReversibleOp> {
+        let outer = self.recording.replace(Vec::new());
+        let mut guard = RestoreRecording { rope: self, outer };
+        f(guard.rope);
+
+        let ops = guard.rope.recording.take().unwrap_or_default();
+        if let Some(outer) = guard.outer.as_mut() {
+            outer.extend(ops.iter().cloned());
+        }
+        ops
+    }
+
+    #[inline]
+    pub(crate) fn record_insert(&mut self, pos: usize, content: &str) {
+        if content.is_empty() { return; }
+        if let Some(ops) = self.recording.as_mut() {
+            ops.push(ReversibleOp::Insert { pos, content: content.into() });
+        }
+    }
+
+    /// Must be called *before* the range is removed from the rope.
+    #[inline]
+    pub(crate) fn record_remove(&mut self, range: Range<usize>) {
+        if self.recording.is_none() { return; }
+        let content = self.slice_chars(range.clone()).collect::<String>();
+        if let Some(ops) = self.recording.as_mut() {
+            ops.push(ReversibleOp::Remove { pos: range.start, content });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{JumpRope, ReversibleOp};
+
+    #[test]
+    fn recording_round_trips() {
+        let original = "κόσμε and some ascii text";
+        let mut rope = JumpRope::from(original);
+
+        let ops = rope.with_recording(|r| {
+            r.remove(0..2);
+            r.insert(3, "𝕐𝕆😘");
+            r.replace(5..1000, "xx");
+            r.extend(["a", "", "bc"]);
+            r.remove(100..200); // No-op.
+        });
+        assert_eq!(ops.len(), 6);
+        assert_eq!(ops[0], ReversibleOp::Remove { pos: 0, content: "κό".into() });
+        assert_eq!(rope, "σμε𝕐𝕆xxabc");
+
+        let mut replay = JumpRope::from(original);
+        for op in &ops { op.apply(&mut replay); }
+        assert_eq!(replay, rope);
+
+        for op in ops.iter().rev() { op.inverse().apply(&mut rope); }
+        assert_eq!(rope, original);
+    }
+
+    #[test]
+    fn nested_recording() {
+        let mut rope = JumpRope::new();
+        let outer = rope.with_recording(|r| {
+            r.insert(0, "a");
+            let inner = r.with_recording(|r| r.insert(1, "b"));
+            assert_eq!(inner.len(), 1);
+        });
+        assert_eq!(outer.len(), 2);
+
+        // Edits outside a recording aren't logged anywhere.
+        rope.insert(0, "c");
+        assert!(rope.recording.is_none());
+    }
+
+    #[test]
+    fn recording_after_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut rope = JumpRope::new();
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            rope.with_recording(|r| {
+                r.insert(0, "a");
+                panic!("oh no");
+            });
+        }));
+        assert!(result.is_err());
+        assert!(rope.recording.is_none());
+
+        // Edits made before the panic still reach the outer recording.
+        let outer = rope.with_recording(|r| {
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                r.with_recording(|r| {
+                    r.insert(1, "b");
+                    panic!("oh no");
+                });
+            }));
+            assert!(result.is_err());
+            r.insert(2, "c");
+        });
+        assert_eq!(outer, [
+            ReversibleOp::Insert { pos: 1, content: "b".into() },
+            ReversibleOp::Insert { pos: 2, content: "c".into() },
+        ]);
+        assert_eq!(rope, "abc");
+        assert!(rope.recording.is_none());
+    }
+}
@@ -0,0 +1,123 @@
+// Parallel bulk loading for very large documents. This is only compiled in with the "rayon"
+// feature.
+//
+// The input is split into segments at (roughly) node aligned char boundaries. Each segment is cut
+// into nodes on a separate thread, then the resulting node chains are stitched together into the
+// skip list in order.
+
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use crate::jumprope::{DEFAULT_BIAS, HeightSource, Node, RopeRng, random_height};
+use crate::utils::{count_chars, floor_char_boundary};
+use crate::JumpRope;
+
+// Below this size, splitting work across threads costs more than it saves.
+const MIN_PARALLEL_BYTES: usize = 1 << 20;
+
+// Raw node pointers aren't Send. The nodes aren't linked into anything until the chains are
+// stitched together on the calling thread, so its safe to move them between threads.
+struct NodePtr(*mut Node, usize);
+unsafe impl Send for NodePtr {}
+
+fn build_chain(mut s: &str, seed: u64, node_size: usize) -> Vec<NodePtr> {
+    let mut rng = RopeRng::seed_from_u64(seed);
+    let mut nodes = Vec::with_capacity(s.len() / node_size + 1);
+    while !s.is_empty() {
+        let (next, rem) = s.split_at(floor_char_boundary(s, node_size));
+        nodes.push(NodePtr(Node::alloc_with_height(random_height(&mut rng, DEFAULT_BIAS), next), count_chars(next)));
+        s = rem;
+    }
+    nodes
+}
+
+impl JumpRope {
+    /// Create a new rope from the passed string, using all available threads (via rayon) to build
+    /// the rope.
+    ///
+    /// This is only worth using for very large documents (hundreds of megabytes). Smaller strings
+    /// are loaded on the calling thread, exactly like [`JumpRope::from`].
+    ///
+    /// This method is only available with the `rayon` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let s = "Some large text document".repeat(1000);
+    /// let rope = JumpRope::from_str_parallel(&s);
+    /// assert_eq!(rope, s);
+    /// ```
+    pub fn from_str_parallel(s: &str) -> Self {
+        if s.len() < MIN_PARALLEL_BYTES {
+            Self::from(s)
+        } else {
+            Self::from_str_in_segments(s, rayon::current_num_threads() * 4)
+        }
+    }
+
+    fn from_str_in_segments(s: &str, num_segments: usize) -> Self {
+        let node_size = crate::jumprope::NODE_STR_SIZE;
+        let mut rope = Self::new();
+
+        // Segment lengths are a multiple of the node size, so only the last node in each segment
+        // (give or take a few bytes at the char boundary) will be underfilled.
+        let num_segments = num_segments.max(1);
+        let target_len = (s.len() / num_segments + 1).div_ceil(node_size) * node_size;
+
+        let mut segments = Vec::with_capacity(num_segments);
+        let mut rem = s;
+        while !rem.is_empty() {
+            let mut split = target_len.min(rem.len());
+            while !rem.is_char_boundary(split) { split += 1; }
+            let (seg, r) = rem.split_at(split);
+            // Each segment gets its own RNG, seeded from the rope's RNG.
+            let HeightSource::Rng(rng) = &mut rope.heights else { unreachable!() };
+            segments.push((seg, rng.gen::<u64>()));
+            rem = r;
+        }
+
+        let chains: Vec<Vec<NodePtr>> = segments.into_par_iter()
+            .map(|(seg, seed)| build_chain(seg, seed, node_size))
+            .collect();
+
+        let mut cursor = rope.cursor_at_end();
+        for NodePtr(node, num_chars) in chains.into_iter().flatten() {
+            unsafe { rope.link_node_at(&mut cursor, node, num_chars, true, false); }
+        }
+
+        rope
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    #[test]
+    fn parallel_load_matches_sequential() {
+        let s = "κό𝕐𝕆😘σμε and some ascii text\n".repeat(100);
+        for segments in [1, 2, 3, 7, 64, 10000] {
+            let rope = JumpRope::from_str_in_segments(&s, segments);
+            rope.check();
+            assert_eq!(rope, s);
+        }
+
+        let rope = JumpRope::from_str_in_segments("", 4);
+        rope.check();
+        assert!(rope.is_empty());
+    }
+
+    #[test]
+    fn edit_after_parallel_load() {
+        let s = "abcdefghij".repeat(50);
+        let mut rope = JumpRope::from_str_in_segments(&s, 5);
+        rope.insert(3, "xx");
+        rope.remove(100..250);
+        rope.check();
+
+        let mut expected = JumpRope::from(s.as_str());
+        expected.insert(3, "xx");
+        expected.remove(100..250);
+        assert_eq!(rope, expected);
+    }
+}
@@ -0,0 +1,104 @@
+use core::ops::Range;
+
+/// Something which can be searched for in a rope. This is used by [`find`](crate::JumpRope::find),
+/// [`matches`](crate::JumpRope::matches), [`split`](crate::JumpRope::split),
+/// [`replace_all`](crate::JumpRope::replace_all) and friends, in the same way [`str::find`] accepts
+/// any pattern.
+///
+/// This is implemented for:
+///
+/// - `char`, matching that character
+/// - `&str` and `&String`, matching that substring
+/// - `&[char]` and `[char; N]`, matching any of those characters
+/// - `FnMut(char) -> bool` closures, matching any character for which the closure returns true
+///
+/// Ropes are searched a few chunks at a time, so a pattern can only match text up to
+/// [`max_len`](Self::max_len) bytes long. A pattern which matches the empty string is treated as
+/// matching at every character position.
+pub trait RopePattern {
+    /// Find the first match in `haystack`, returning its byte range.
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>>;
+
+    /// Find the last match in `haystack`, returning its byte range.
+    fn rfind_in(&mut self, haystack: &str) -> Option<Range<usize>>;
+
+    /// The maximum length of a match, in bytes.
+    fn max_len(&self) -> usize;
+}
+
+// The byte range of the character at byte offset i.
+fn char_at(haystack: &str, i: usize) -> Range<usize> {
+    i..i + haystack[i..].chars().next().unwrap().len_utf8()
+}
+
+impl RopePattern for char {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.find(*self).map(|i| i..i + self.len_utf8())
+    }
+
+    fn rfind_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.rfind(*self).map(|i| i..i + self.len_utf8())
+    }
+
+    fn max_len(&self) -> usize { self.len_utf8() }
+}
+
+impl RopePattern for &str {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.find(*self).map(|i| i..i + self.len())
+    }
+
+    fn rfind_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.rfind(*self).map(|i| i..i + self.len())
+    }
+
+    fn max_len(&self) -> usize { self.len() }
+}
+
+impl RopePattern for &alloc::string::String {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        self.as_str().find_in(haystack)
+    }
+
+    fn rfind_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        self.as_str().rfind_in(haystack)
+    }
+
+    fn max_len(&self) -> usize { self.len() }
+}
+
+impl RopePattern for &[char] {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.find(*self).map(|i| char_at(haystack, i))
+    }
+
+    fn rfind_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.rfind(*self).map(|i| char_at(haystack, i))
+    }
+
+    fn max_len(&self) -> usize { 4 }
+}
+
+impl<const N: usize> RopePattern for [char; N] {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn rfind_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        self.as_slice().rfind_in(haystack)
+    }
+
+    fn max_len(&self) -> usize { 4 }
+}
+
+impl<F: FnMut(char) -> bool> RopePattern for F {
+    fn find_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.find(&mut *self).map(|i| char_at(haystack, i))
+    }
+
+    fn rfind_in(&mut self, haystack: &str) -> Option<Range<usize>> {
+        haystack.rfind(&mut *self).map(|i| char_at(haystack, i))
+    }
+
+    fn max_len(&self) -> usize { 4 }
+}
@@ -0,0 +1,461 @@
+use core::ops::{ControlFlow, Range, RangeInclusive};
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::JumpRope;
+use crate::iter::{ContentRangeIter, RevChunks};
+use crate::pattern::RopePattern;
+use crate::utils::{count_chars, floor_char_boundary};
+
+/// An iterator over the positions of a pattern in a rope. See [`JumpRope::matches`].
+pub struct Matches<'a, P> {
+    chunks: ContentRangeIter<'a>,
+    pattern: P,
+    // Text after the previous match (or the start of the search) which hasn't been searched yet.
+    buf: String,
+    // The character position of the start of buf.
+    buf_pos: usize,
+    // Patterns matching the empty string match at every position. These are the remaining ones.
+    empty: Option<RangeInclusive<usize>>,
+}
+
+impl<'a, P: RopePattern> Matches<'a, P> {
+    fn next_range(&mut self) -> Option<Range<usize>> {
+        if let Some(empty) = self.empty.as_mut() {
+            return empty.next().map(|pos| pos..pos);
+        }
+
+        loop {
+            if let Some(r) = self.pattern.find_in(&self.buf) {
+                let start = self.buf_pos + count_chars(&self.buf[..r.start]);
+                let end = start + count_chars(&self.buf[r.clone()]);
+                // Matches don't overlap, so the next match must start after this one ends.
+                self.buf.drain(..r.end);
+                self.buf_pos = end;
+                return Some(start..end);
+            }
+
+            let (s, _) = self.chunks.next()?;
+            // Only the end of the buffer can still be part of a match spanning into the new chunk.
+            let keep = self.pattern.max_len() - 1;
+            let mut keep_from = self.buf.len() - keep.min(self.buf.len());
+            while !self.buf.is_char_boundary(keep_from) { keep_from += 1; }
+            self.buf_pos += count_chars(&self.buf[..keep_from]);
+            self.buf.drain(..keep_from);
+            self.buf.push_str(s);
+        }
+    }
+}
+
+impl<'a, P: RopePattern> Iterator for Matches<'a, P> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        self.next_range().map(|r| r.start)
+    }
+}
+
+/// An iterator over the positions of a pattern in a rope, from the end of the document backwards.
+/// See [`JumpRope::rmatches`].
+pub struct RevMatches<'a, P> {
+    chunks: RevChunks<'a>,
+    pattern: P,
+    // Text before the previous match (or the end of the search) which hasn't been searched yet.
+    buf: String,
+    // The character position of the start of buf.
+    buf_pos: usize,
+    // Patterns matching the empty string match at every position. These are the remaining ones.
+    empty: Option<RangeInclusive<usize>>,
+}
+
+impl<'a, P: RopePattern> RevMatches<'a, P> {
+    fn refill(&mut self) -> bool {
+        let Some((s, char_len)) = self.chunks.next() else { return false; };
+        // Only the start of the buffer can still be part of a match spanning into the new chunk.
+        let keep = floor_char_boundary(&self.buf, self.pattern.max_len() - 1);
+        self.buf.truncate(keep);
+        self.buf.insert_str(0, s);
+        self.buf_pos -= char_len;
+        true
+    }
+}
+
+impl<'a, P: RopePattern> Iterator for RevMatches<'a, P> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if let Some(empty) = self.empty.as_mut() {
+            return empty.next_back();
+        }
+
+        loop {
+            if let Some(r) = self.pattern.rfind_in(&self.buf) {
+                let pos = self.buf_pos + count_chars(&self.buf[..r.start]);
+                // Matches don't overlap, so the next match must end before this one starts.
+                self.buf.truncate(r.start);
+                return Some(pos);
+            }
+            if !self.refill() { return None; }
+        }
+    }
+}
+
+/// An iterator over the pieces of a rope separated by a pattern. See [`JumpRope::split`].
+pub struct Split<'a, P> {
+    rope: &'a JumpRope,
+    matches: Matches<'a, P>,
+    // The start of the next piece, or None once the last piece has been returned.
+    pos: Option<usize>,
+}
+
+impl<'a, P: RopePattern> Iterator for Split<'a, P> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let pos = self.pos?;
+        let end = match self.matches.next_range() {
+            Some(r) => {
+                self.pos = Some(r.end);
+                r.start
+            }
+            None => {
+                self.pos = None;
+                self.rope.len_chars()
+            }
+        };
+        Some(self.rope.slice_chars(pos..end).collect())
+    }
+}
+
+impl JumpRope {
+    /// Find the first match of `pattern` at or after character position `from`, returning its
+    /// character position. The pattern can be a `char`, a string, a slice of chars or a closure.
+    /// See [`RopePattern`].
+    ///
+    /// The document is scanned a chunk at a time from `from`, and only the content up to the
+    /// match is read.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one two one two");
+    /// assert_eq!(rope.find("two", 0), Some(4));
+    /// assert_eq!(rope.find("two", 5), Some(12));
+    /// assert_eq!(rope.find("three", 0), None);
+    /// assert_eq!(rope.find('w', 0), Some(5));
+    /// assert_eq!(rope.find(char::is_whitespace, 4), Some(7));
+    /// ```
+    pub fn find<P: RopePattern>(&self, pattern: P, from: usize) -> Option<usize> {
+        self.matches(pattern, from).next()
+    }
+
+    /// Find the last match of `pattern` which ends at or before character position `before`,
+    /// returning its character position. This implements "find previous".
+    ///
+    /// The document is scanned backwards a chunk at a time from `before`, so only the content
+    /// after the match is read. Positions past the end of the document are clamped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("one two one two");
+    /// assert_eq!(rope.rfind("one", 15), Some(8));
+    /// assert_eq!(rope.rfind("one", 10), Some(0));
+    /// assert_eq!(rope.rfind("three", 15), None);
+    /// assert_eq!(rope.rfind(['e', 'o'], 15), Some(14));
+    /// ```
+    pub fn rfind<P: RopePattern>(&self, pattern: P, before: usize) -> Option<usize> {
+        self.rmatches(pattern, before).next()
+    }
+
+    /// Find the first occurrence of `c` at or after character position `from`, returning its
+    /// character position. This gives the same result as [`find`](Self::find) with a `char`
+    /// pattern, but it's faster - which matters for hot loops like scanning for the next newline,
+    /// quote or delimiter.
+    ///
+    /// Each chunk is searched in place with [`str::find`], which uses `memchr` to skip to
+    /// candidate bytes, so nothing is copied and characters are only counted up to the match.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("let s = \"κόσμε\";\n");
+    /// assert_eq!(rope.find_char('"', 0), Some(8));
+    /// assert_eq!(rope.find_char('"', 9), Some(14));
+    /// assert_eq!(rope.find_char('σ', 0), Some(11));
+    /// assert_eq!(rope.find_char('x', 0), None);
+    /// ```
+    pub fn find_char(&self, c: char, from: usize) -> Option<usize> {
+        if from >= self.len_chars() { return None; }
+        let mut pos = from;
+        let result = self.visit_chunks_from(from, |s, len| match s.find(c) {
+            Some(i) => ControlFlow::Break(pos + count_chars(&s[..i])),
+            None => {
+                pos += len;
+                ControlFlow::Continue(())
+            }
+        });
+        match result {
+            ControlFlow::Break(pos) => Some(pos),
+            ControlFlow::Continue(()) => None,
+        }
+    }
+
+    /// Iterate through the positions of every match of `pattern` at or after character position
+    /// `from`. Like [`str::match_indices`], the matches don't overlap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("aaa b aaa");
+    /// assert_eq!(rope.matches("aa", 0).collect::<Vec<_>>(), vec![0, 6]);
+    /// assert_eq!(rope.matches('a', 5).collect::<Vec<_>>(), vec![6, 7, 8]);
+    /// ```
+    pub fn matches<P: RopePattern>(&self, mut pattern: P, from: usize) -> Matches<'_, P> {
+        let len = self.len_chars();
+        let from = from.min(len + 1);
+        let empty = pattern.find_in("").is_some();
+        Matches {
+            chunks: self.slice_chunks(from.min(len)..len),
+            pattern,
+            buf: String::new(),
+            buf_pos: from,
+            empty: if empty { Some(from..=len) } else { None },
+        }
+    }
+
+    /// Iterate backwards through the positions of every match of `pattern` which ends at or
+    /// before character position `before`. Like [`str::rmatch_indices`], the matches don't
+    /// overlap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("aaa b aaa");
+    /// assert_eq!(rope.rmatches("aa", 100).collect::<Vec<_>>(), vec![7, 1]);
+    /// assert_eq!(rope.rmatches('a', 5).collect::<Vec<_>>(), vec![2, 1, 0]);
+    /// ```
+    pub fn rmatches<P: RopePattern>(&self, mut pattern: P, before: usize) -> RevMatches<'_, P> {
+        let end = before.min(self.len_chars());
+        let empty = pattern.find_in("").is_some();
+        RevMatches {
+            chunks: self.rev_chunks_before(end),
+            pattern,
+            buf: String::new(),
+            buf_pos: end,
+            empty: if empty { Some(0..=end) } else { None },
+        }
+    }
+
+    /// Iterate over the pieces of the rope separated by matches of `pattern`, like [`str::split`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a, b,c");
+    /// assert_eq!(rope.split(", ").collect::<Vec<_>>(), vec!["a", "b,c"]);
+    /// assert_eq!(rope.split([',', ' ']).collect::<Vec<_>>(), vec!["a", "", "b", "c"]);
+    /// ```
+    pub fn split<P: RopePattern>(&self, pattern: P) -> Split<'_, P> {
+        Split {
+            rope: self,
+            matches: self.matches(pattern, 0),
+            pos: Some(0),
+        }
+    }
+
+    /// Replace every match of `pattern` in the rope with `content`. Returns the number of matches
+    /// which were replaced.
+    ///
+    /// If nothing matches, the rope isn't modified at all - so callers can skip re-rendering or
+    /// re-parsing the document when the result is 0. Chunk IDs (see
+    /// [`chunks_with_ids`](Self::chunks_with_ids)) are unchanged in this case too.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("one two one two");
+    /// assert_eq!(rope.replace_all("one", "three"), 2);
+    /// assert_eq!(rope, "three two three two");
+    /// assert_eq!(rope.replace_all(char::is_whitespace, "_"), 3);
+    /// assert_eq!(rope, "three_two_three_two");
+    /// assert_eq!(rope.replace_all("four", "five"), 0);
+    /// ```
+    pub fn replace_all<P: RopePattern, S: AsRef<str>>(&mut self, pattern: P, content: S) -> usize {
+        let mut matches = self.matches(pattern, 0);
+        let ranges = core::iter::from_fn(|| matches.next_range()).collect::<Vec<_>>();
+        let count = ranges.len();
+        // Replacing from the end backwards keeps the earlier ranges valid.
+        for range in ranges.into_iter().rev() {
+            self.replace(range, content.as_ref());
+        }
+        count
+    }
+
+    /// Find the bracket matching the one at `pos`, taking nesting into account.
+    ///
+    /// If the character at `pos` is `open`, this scans forwards for the matching `close`. If it is
+    /// `close`, this scans backwards for the matching `open`. Returns the character offset of the
+    /// matching bracket, or `None` if there is no bracket at `pos` or it is unbalanced.
+    ///
+    /// Only the content between the brackets is scanned, and it is not copied out of the rope.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("fn x() { if y { z } }");
+    /// assert_eq!(rope.find_matching(7, '{', '}'), Some(20));
+    /// assert_eq!(rope.find_matching(20, '{', '}'), Some(7));
+    /// assert_eq!(rope.find_matching(14, '{', '}'), Some(18));
+    /// assert_eq!(rope.find_matching(0, '{', '}'), None);
+    /// ```
+    pub fn find_matching(&self, pos: usize, open: char, close: char) -> Option<usize> {
+        let len = self.len_chars();
+        if pos >= len || open == close { return None; }
+
+        let c = self.slice_chars(pos..pos + 1).next()?;
+        let mut depth = 0usize;
+
+        let mut scan = |ch: char, inc: char, dec: char| -> bool {
+            if ch == inc {
+                depth += 1;
+            } else if ch == dec {
+                if depth == 0 { return true; }
+                depth -= 1;
+            }
+            false
+        };
+
+        if c == open {
+            self.slice_chars(pos + 1..len)
+                .position(|ch| scan(ch, open, close))
+                .map(|offset| pos + 1 + offset)
+        } else if c == close {
+            self.rev_chunks_before(pos)
+                .flat_map(|(s, _)| s.chars().rev())
+                .position(|ch| scan(ch, close, open))
+                .map(|offset| pos - 1 - offset)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    #[test]
+    fn matching_brackets() {
+        let s = "(κό(𝕐𝕆)😘(σ(μ)ε)xxxxxxxxxxxxxxxxxxxxxx) )(";
+        let rope = JumpRope::from(s);
+        let chars = s.chars().collect::<Vec<_>>();
+
+        for (pos, &c) in chars.iter().enumerate() {
+            let result = rope.find_matching(pos, '(', ')');
+            let expected = match c {
+                '(' => {
+                    let mut depth = 0;
+                    (pos + 1..chars.len()).find(|&i| {
+                        if chars[i] == '(' { depth += 1; }
+                        if chars[i] == ')' { if depth == 0 { return true; } depth -= 1; }
+                        false
+                    })
+                },
+                ')' => {
+                    let mut depth = 0;
+                    (0..pos).rev().find(|&i| {
+                        if chars[i] == ')' { depth += 1; }
+                        if chars[i] == '(' { if depth == 0 { return true; } depth -= 1; }
+                        false
+                    })
+                },
+                _ => None
+            };
+            assert_eq!(result, expected, "pos {}", pos);
+        }
+        assert_eq!(rope.find_matching(0, '(', ')'), Some(chars.len() - 4));
+        assert_eq!(rope.find_matching(1000, '(', ')'), None);
+    }
+
+    #[test]
+    fn find_and_rfind() {
+        let s = "aκόaκόaκ𝕐𝕆😘aκaκόaκόxxxxxxxxxxxxxxxxxaκό";
+        let rope = JumpRope::from(s);
+        let len = rope.len_chars();
+        let char_pos = |b: usize| s[..b].chars().count();
+
+        for needle in ["", "a", "aκό", "κόa", "xx", "😘aκ", "nope"] {
+            for pos in 0..=len + 1 {
+                let byte_pos = s.char_indices().nth(pos).map_or(s.len(), |(b, _)| b);
+                let expected = if pos > len { None } else {
+                    s[byte_pos..].find(needle).map(|b| char_pos(byte_pos + b))
+                };
+                assert_eq!(rope.find(needle, pos), expected);
+
+                let expected = if pos > len { vec![] } else {
+                    s[byte_pos..].match_indices(needle).map(|(b, _)| char_pos(byte_pos + b)).collect()
+                };
+                assert_eq!(rope.matches(needle, pos).collect::<Vec<_>>(), expected);
+
+                let expected = s[..byte_pos].rmatch_indices(needle)
+                    .map(|(b, _)| char_pos(b))
+                    .collect::<Vec<_>>();
+                assert_eq!(rope.rmatches(needle, pos).collect::<Vec<_>>(), expected);
+                assert_eq!(rope.rfind(needle, pos), expected.first().copied());
+            }
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        let s = "aκόaκόaκ𝕐𝕆😘aκaκόaκόxxxxxxxxxxxxxxxxxaκό";
+        let rope = JumpRope::from(s);
+        let char_pos = |b: usize| s[..b].chars().count();
+
+        let expected = s.match_indices('κ').map(|(b, _)| char_pos(b)).collect::<Vec<_>>();
+        assert_eq!(rope.matches('κ', 0).collect::<Vec<_>>(), expected);
+        let expected = s.match_indices(['😘', 'ό']).map(|(b, _)| char_pos(b)).collect::<Vec<_>>();
+        assert_eq!(rope.matches(['😘', 'ό'], 0).collect::<Vec<_>>(), expected);
+        assert_eq!(rope.matches(&['😘', 'ό'][..], 0).collect::<Vec<_>>(), expected);
+        assert_eq!(rope.rmatches(|c: char| c == '😘' || c == 'ό', 100).collect::<Vec<_>>(),
+            expected.iter().rev().copied().collect::<Vec<_>>());
+        assert_eq!(rope.find(&String::from("😘a"), 0), Some(10));
+
+        for pattern in ["", "a", "aκό", "xx", "nope"] {
+            assert_eq!(rope.split(pattern).collect::<Vec<_>>(), s.split(pattern).collect::<Vec<_>>());
+
+            let mut r = rope.clone();
+            let ids = r.chunks_with_ids().map(|(id, _)| id).collect::<Vec<_>>();
+            let count = r.replace_all(pattern, "𝕐!");
+            assert_eq!(count, s.matches(pattern).count());
+            assert_eq!(r, s.replace(pattern, "𝕐!"));
+            r.check();
+            if count == 0 {
+                // Nothing was touched.
+                assert!(r.chunks_with_ids().map(|(id, _)| id).eq(ids));
+            }
+        }
+        assert_eq!(rope.split('κ').collect::<Vec<_>>(), s.split('κ').collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn find_char() {
+        let s = "aκόaκόaκ𝕐𝕆😘a\nκaκόaκόxxxxxxxxxxxxxxxxxaκό\n".repeat(10);
+        let rope = JumpRope::from(s.as_str());
+        let len = rope.len_chars();
+        for c in ['a', 'κ', 'ό', '😘', '\n', 'x', 'z'] {
+            for from in 0..=len + 1 {
+                assert_eq!(rope.find_char(c, from), rope.find(c, from));
+            }
+        }
+        assert_eq!(JumpRope::new().find_char('a', 0), None);
+    }
+}
@@ -0,0 +1,503 @@
+use core::ops::Range;
+use alloc::string::String;
+use crate::JumpRopeCustom;
+use crate::jumprope::NODE_STR_SIZE;
+use crate::iter::Chars;
+#[cfg(feature = "unicode-segmentation")]
+use alloc::collections::VecDeque;
+#[cfg(feature = "unicode-segmentation")]
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete, UnicodeSegmentation};
+#[cfg(feature = "unicode-segmentation")]
+use crate::utils::count_chars;
+
+/// An iterator over the character ranges of paragraphs in a rope. See
+/// [`JumpRope::paragraphs`].
+pub struct Paragraphs<'a, const N: usize = NODE_STR_SIZE> {
+    chars: Chars<'a, N>,
+    pos: usize,
+}
+
+impl<'a, const N: usize> Iterator for Paragraphs<'a, N> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        let mut start = None;
+        let mut end = 0;
+        let mut newlines = 0;
+
+        for c in self.chars.by_ref() {
+            let pos = self.pos;
+            self.pos += 1;
+
+            if c == '\n' {
+                newlines += 1;
+                if newlines == 2 && start.is_some() { break; }
+            } else {
+                if start.is_none() { start = Some(pos); }
+                newlines = 0;
+                end = pos + 1;
+            }
+        }
+
+        start.map(|start| start..end)
+    }
+}
+
+/// An iterator over the character ranges of sentences in a rope. See [`JumpRope::sentences`].
+#[cfg(feature = "unicode-segmentation")]
+pub struct Sentences<'a, const N: usize = NODE_STR_SIZE> {
+    chars: Chars<'a, N>,
+    // The position of the next character in chars.
+    pos: usize,
+    // Content of the line currently being segmented.
+    line: String,
+    pending: VecDeque<Range<usize>>,
+}
+
+#[cfg(feature = "unicode-segmentation")]
+impl<'a, const N: usize> Iterator for Sentences<'a, N> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        // Line breaks always end sentences in UAX #29, so we only ever need to look at one line at
+        // a time.
+        while self.pending.is_empty() {
+            let line_start = self.pos;
+            let mut at_end = true;
+            self.line.clear();
+            for c in self.chars.by_ref() {
+                self.pos += 1;
+                if c == '\n' {
+                    at_end = false;
+                    break;
+                }
+                self.line.push(c);
+            }
+
+            let mut pos = line_start;
+            for sentence in self.line.split_sentence_bounds() {
+                let len = count_chars(sentence);
+                self.pending.push_back(pos..pos + len);
+                pos += len;
+            }
+
+            if at_end { break; }
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+/// An iterator over the character ranges of grapheme clusters in a rope. See
+/// [`JumpRope::graphemes`].
+#[cfg(feature = "unicode-segmentation")]
+pub struct Graphemes<'a, const N: usize = NODE_STR_SIZE> {
+    chars: Chars<'a, N>,
+    // The position of the next character in chars.
+    pos: usize,
+    // Content of the line currently being segmented, including its trailing newline.
+    line: String,
+    pending: VecDeque<Range<usize>>,
+}
+
+#[cfg(feature = "unicode-segmentation")]
+impl<'a, const N: usize> Iterator for Graphemes<'a, N> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        // There is always a grapheme boundary after '\n', so like sentences we can segment the
+        // document one line at a time.
+        if self.pending.is_empty() {
+            let line_start = self.pos;
+            self.line.clear();
+            for c in self.chars.by_ref() {
+                self.pos += 1;
+                self.line.push(c);
+                if c == '\n' { break; }
+            }
+
+            let mut pos = line_start;
+            for grapheme in self.line.graphemes(true) {
+                let len = count_chars(grapheme);
+                self.pending.push_back(pos..pos + len);
+                pos += len;
+            }
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+impl<const NODE: usize, const BIAS: u8> JumpRopeCustom<NODE, BIAS> {
+    // The chunk of content starting at the given byte offset, and the offset itself.
+    #[cfg(feature = "unicode-segmentation")]
+    fn chunk_from_byte(&self, byte: usize) -> (&str, usize) {
+        let pos = self.byte_to_char(byte);
+        let (s, _) = self.slice_chunks(pos..self.len_chars()).next().unwrap();
+        (s, byte)
+    }
+
+    // The chunk of content ending at the given byte offset, and the byte offset of its start.
+    #[cfg(feature = "unicode-segmentation")]
+    fn chunk_before_byte(&self, byte: usize) -> (&str, usize) {
+        let pos = self.byte_to_char(byte);
+        let (s, _) = self.rev_chunks_before(pos).next().unwrap();
+        (s, byte - s.len())
+    }
+
+    /// Iterate over the character ranges of paragraphs in the rope. Paragraphs are separated by
+    /// one or more blank lines (ie, 2 or more consecutive `'\n'` characters). The returned ranges
+    /// don't include the newlines around each paragraph, and empty paragraphs are skipped.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("Para one\nstill one\n\n\nPara two\n");
+    /// let paragraphs = rope.paragraphs()
+    ///     .map(|r| rope.slice_chars(r).collect::<String>())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(paragraphs, &["Para one\nstill one", "Para two"]);
+    /// ```
+    pub fn paragraphs(&self) -> Paragraphs<'_, NODE> {
+        Paragraphs { chars: self.chars(), pos: 0 }
+    }
+
+    /// Iterate over the character ranges of sentences in the rope, using the sentence boundary
+    /// rules from [UAX #29](https://www.unicode.org/reports/tr29/). Each range includes any
+    /// whitespace following the sentence, but not line breaks. (Line breaks always end a
+    /// sentence.)
+    ///
+    /// Each line is copied into a temporary buffer for segmentation.
+    ///
+    /// This method is only available with the `unicode-segmentation` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("Hi there. How are you?\nGood!");
+    /// let sentences = rope.sentences()
+    ///     .map(|r| rope.slice_chars(r).collect::<String>())
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(sentences, &["Hi there. ", "How are you?", "Good!"]);
+    /// ```
+    #[cfg(feature = "unicode-segmentation")]
+    pub fn sentences(&self) -> Sentences<'_, NODE> {
+        Sentences {
+            chars: self.chars(),
+            pos: 0,
+            line: String::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Iterate over the character ranges of the (extended) grapheme clusters in the rope, using
+    /// the rules from [UAX #29](https://www.unicode.org/reports/tr29/). A grapheme cluster is what
+    /// a user thinks of as a single character - like an emoji made of several code points, or a
+    /// letter followed by combining accents.
+    ///
+    /// Each line is copied into a temporary buffer for segmentation.
+    ///
+    /// This method is only available with the `unicode-segmentation` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("e\u{301}👨‍👩‍👧!\r\n");
+    /// assert_eq!(rope.graphemes().collect::<Vec<_>>(), [0..2, 2..7, 7..8, 8..10]);
+    /// ```
+    #[cfg(feature = "unicode-segmentation")]
+    pub fn graphemes(&self) -> Graphemes<'_, NODE> {
+        Graphemes {
+            chars: self.chars(),
+            pos: 0,
+            line: String::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Find the character position of the next grapheme cluster boundary after `pos`. This is
+    /// where the cursor should move when the user presses the right arrow key. Positions at or
+    /// past the end of the rope return [`len_chars`](Self::len_chars).
+    ///
+    /// Only the chunks around `pos` are read, so this runs in *O(log n)* time.
+    ///
+    /// This method is only available with the `unicode-segmentation` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("ae\u{301}b");
+    /// assert_eq!(rope.next_grapheme_boundary(0), 1);
+    /// assert_eq!(rope.next_grapheme_boundary(1), 3);
+    /// assert_eq!(rope.next_grapheme_boundary(2), 3); // Inside a cluster
+    /// ```
+    #[cfg(feature = "unicode-segmentation")]
+    pub fn next_grapheme_boundary(&self, pos: usize) -> usize {
+        let len = self.len_chars();
+        if pos >= len { return len; }
+
+        let byte = self.char_to_byte(pos);
+        let mut cursor = GraphemeCursor::new(byte, self.len_bytes(), true);
+        let (mut chunk, mut chunk_start) = self.chunk_from_byte(byte);
+        loop {
+            match cursor.next_boundary(chunk, chunk_start) {
+                Ok(boundary) => {
+                    let boundary = boundary.unwrap_or(self.len_bytes());
+                    return self.byte_to_char(boundary);
+                }
+                Err(GraphemeIncomplete::NextChunk) => {
+                    (chunk, chunk_start) = self.chunk_from_byte(chunk_start + chunk.len());
+                }
+                Err(GraphemeIncomplete::PreContext(end)) => {
+                    let (s, start) = self.chunk_before_byte(end);
+                    cursor.provide_context(s, start);
+                }
+                Err(e) => unreachable!("Unexpected grapheme cursor state: {:?}", e),
+            }
+        }
+    }
+
+    /// Find the character position of the previous grapheme cluster boundary before `pos`. This
+    /// is where the cursor should move when the user presses the left arrow key, and the start of
+    /// the range to delete when they press backspace. Position 0 returns 0, and positions past
+    /// the end of the rope are clamped.
+    ///
+    /// Only the chunks around `pos` are read, so this runs in *O(log n)* time.
+    ///
+    /// This method is only available with the `unicode-segmentation` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("a👍🏽b");
+    /// // Backspace after the emoji deletes the whole cluster.
+    /// let start = rope.prev_grapheme_boundary(3);
+    /// rope.remove(start..3);
+    /// assert_eq!(rope, "ab");
+    /// ```
+    #[cfg(feature = "unicode-segmentation")]
+    pub fn prev_grapheme_boundary(&self, pos: usize) -> usize {
+        let pos = pos.min(self.len_chars());
+        if pos == 0 { return 0; }
+
+        let byte = self.char_to_byte(pos);
+        let mut cursor = GraphemeCursor::new(byte, self.len_bytes(), true);
+        let (mut chunk, mut chunk_start) = self.chunk_before_byte(byte);
+        loop {
+            match cursor.prev_boundary(chunk, chunk_start) {
+                Ok(boundary) => return self.byte_to_char(boundary.unwrap_or(0)),
+                Err(GraphemeIncomplete::PrevChunk) => {
+                    (chunk, chunk_start) = self.chunk_before_byte(chunk_start);
+                }
+                Err(GraphemeIncomplete::PreContext(end)) => {
+                    let (s, start) = self.chunk_before_byte(end);
+                    cursor.provide_context(s, start);
+                }
+                Err(e) => unreachable!("Unexpected grapheme cursor state: {:?}", e),
+            }
+        }
+    }
+
+    /// Call `f` with the character range and content of every word in the specified range of the
+    /// rope. This is designed for spellcheckers.
+    ///
+    /// A word is a run of alphanumeric characters. Apostrophes (`'` and `’`) between two
+    /// alphanumeric characters are included, so "don't" is a single word. Words which are cut by
+    /// the ends of the range are cut short.
+    ///
+    /// Words are passed straight out of the rope's chunks. Only words which straddle a chunk
+    /// boundary are copied into a temporary buffer.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("Don't  panic, 42 times!");
+    /// let mut words = Vec::new();
+    /// rope.tokens(0..rope.len_chars(), |range, word| words.push((range, word.to_string())));
+    /// assert_eq!(words, [(0..5, "Don't".into()), (7..12, "panic".into()), (14..16, "42".into()), (17..22, "times".into())]);
+    /// ```
+    pub fn tokens<F: FnMut(Range<usize>, &str)>(&self, range: Range<usize>, mut f: F) {
+        let end = range.end.min(self.len_chars());
+        let start = range.start.min(end);
+
+        // The character position of the next character.
+        let mut pos = start;
+        let mut in_word = false;
+        let mut word_start = 0;
+        // The content of the current word, if it started in an earlier chunk.
+        let mut buf = String::new();
+        // If the current word is followed by an apostrophe, its byte offset within the word. The
+        // apostrophe is only part of the word if it's followed by another alphanumeric character.
+        let mut apos: Option<usize> = None;
+
+        for (s, _) in self.slice_chunks(start..end) {
+            // Whether the current word started in an earlier chunk (and is in buf).
+            let mut buffered = in_word;
+            let mut start_byte = 0;
+
+            for (i, c) in s.char_indices() {
+                let word_offset = if buffered { buf.len() + i } else { i - start_byte };
+
+                if c.is_alphanumeric() {
+                    if !in_word {
+                        in_word = true;
+                        word_start = pos;
+                        start_byte = i;
+                    }
+                    apos = None;
+                } else if in_word && apos.is_none() && (c == '\'' || c == '’') {
+                    apos = Some(word_offset);
+                } else if in_word {
+                    let word_end = apos.unwrap_or(word_offset);
+                    let char_end = if apos.is_some() { pos - 1 } else { pos };
+                    if buffered {
+                        buf.push_str(&s[..i]);
+                        buf.truncate(word_end);
+                        f(word_start..char_end, &buf);
+                        buf.clear();
+                        buffered = false;
+                    } else {
+                        f(word_start..char_end, &s[start_byte..start_byte + word_end]);
+                    }
+                    in_word = false;
+                    apos = None;
+                }
+                pos += 1;
+            }
+
+            if in_word {
+                if buffered {
+                    buf.push_str(s);
+                } else {
+                    buf.clear();
+                    buf.push_str(&s[start_byte..]);
+                }
+            }
+        }
+
+        if in_word {
+            if let Some(a) = apos {
+                buf.truncate(a);
+                pos -= 1;
+            }
+            f(word_start..pos, &buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    fn paragraphs(s: &str) -> Vec<String> {
+        let rope = JumpRope::from(s);
+        rope.paragraphs().map(|r| rope.slice_chars(r).collect::<String>()).collect()
+    }
+
+    #[test]
+    fn paragraph_ranges() {
+        assert!(paragraphs("").is_empty());
+        assert!(paragraphs("\n\n\n").is_empty());
+        assert_eq!(paragraphs("a"), &["a"]);
+        assert_eq!(paragraphs("\n\nκό\n𝕐𝕆\n\nσμε\n"), &["κό\n𝕐𝕆", "σμε"]);
+        assert_eq!(paragraphs("a\n \nb"), &["a\n \nb"]);
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn sentence_ranges() {
+        let rope = JumpRope::from("One. Two?\n\nκόσμε! Four xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx.");
+        let sentences = rope.sentences()
+            .map(|r| rope.slice_chars(r).collect::<String>())
+            .collect::<Vec<_>>();
+        assert_eq!(sentences, &["One. ", "Two?", "κόσμε! ", "Four xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx."]);
+
+        assert_eq!(JumpRope::new().sentences().count(), 0);
+    }
+
+    #[cfg(feature = "unicode-segmentation")]
+    #[test]
+    fn grapheme_boundaries() {
+        use unicode_segmentation::UnicodeSegmentation;
+        use crate::Boundary;
+        use crate::utils::count_chars;
+
+        // Long clusters and regional indicators (flags) span several nodes in debug builds.
+        let s = "ae\u{301}\u{302}\u{303}\u{304}x👨‍👩‍👧‍👦\r\n\n🇦🇺🇳🇿🇬🇧z👍🏽 κό𝕐";
+        let rope = JumpRope::from(s);
+
+        let mut expected = Vec::new();
+        let mut pos = 0;
+        for g in s.graphemes(true) {
+            let len = count_chars(g);
+            expected.push(pos..pos + len);
+            pos += len;
+        }
+        assert_eq!(rope.graphemes().collect::<Vec<_>>(), expected);
+        assert_eq!(JumpRope::new().graphemes().count(), 0);
+
+        for range in &expected {
+            for pos in range.clone() {
+                assert_eq!(rope.next_grapheme_boundary(pos), range.end);
+                assert_eq!(rope.prev_grapheme_boundary(pos + 1), range.start);
+            }
+        }
+        assert_eq!(rope.next_grapheme_boundary(1000), rope.len_chars());
+        assert_eq!(rope.prev_grapheme_boundary(0), 0);
+        assert_eq!(rope.prev_grapheme_boundary(1000), expected.last().unwrap().start);
+
+        // Sanitized ranges are expanded to cover whole clusters.
+        let family = expected[3].clone();
+        assert_eq!(rope.sanitize_range(family.start + 1..family.start + 2, Boundary::Grapheme), (family.clone(), true));
+        assert_eq!(rope.sanitize_range(family.clone(), Boundary::Grapheme), (family.clone(), false));
+        assert_eq!(rope.sanitize_range(family.start + 2..family.start + 2, Boundary::Grapheme), (family.start..family.start, true));
+        let len = rope.len_chars();
+        assert_eq!(rope.sanitize_range(len - 1..len + 10, Boundary::Grapheme), (len - 1..len, true));
+        assert_eq!(rope.sanitize_range(len + 5..len + 10, Boundary::Grapheme), (len..len, true));
+    }
+
+    #[test]
+    fn word_tokens() {
+        let s = "Don't  panic, κόσμε'  xxxxxxxxxxxxxxxxxxxxxxxxx 42 dogs' o'clock 'quoted' rock’n’roll x";
+        let rope = JumpRope::from(s);
+        let len = rope.len_chars();
+
+        let mut words = Vec::new();
+        rope.tokens(0..len, |range, word| {
+            assert_eq!(rope.slice_chars(range.clone()).collect::<String>(), word);
+            words.push(word.to_string());
+        });
+        assert_eq!(words, ["Don't", "panic", "κόσμε", "xxxxxxxxxxxxxxxxxxxxxxxxx", "42", "dogs", "o'clock", "quoted", "rock’n’roll", "x"]);
+
+        // Check every sub range against a simple implementation.
+        let chars = s.chars().collect::<Vec<_>>();
+        for start in 0..=len {
+            for end in start..=len {
+                let mut expected = Vec::new();
+                let mut i = start;
+                while i < end {
+                    if !chars[i].is_alphanumeric() { i += 1; continue; }
+                    let word_start = i;
+                    while i < end && (chars[i].is_alphanumeric()
+                        || (['\'', '’'].contains(&chars[i]) && i + 1 < end && chars[i + 1].is_alphanumeric())) {
+                        i += 1;
+                    }
+                    expected.push(word_start..i);
+                }
+
+                let mut actual = Vec::new();
+                rope.tokens(start..end, |range, word| {
+                    assert_eq!(chars[range.clone()].iter().collect::<String>(), word);
+                    actual.push(range);
+                });
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// A borrowed view of part of a rope.
+
+use core::fmt::{Debug, Display, Formatter};
+use core::ops::Range;
+use alloc::string::String;
+use crate::JumpRope;
+use crate::iter::{CharsSlice, ContentRangeIter};
+
+/// A read only view of a range of characters in a [`JumpRope`]. Slices borrow the rope's nodes
+/// directly, so making one doesn't copy any content. See [`JumpRope::slice`].
+#[derive(Clone, Copy)]
+pub struct RopeSlice<'a> {
+    rope: &'a JumpRope,
+    start: usize,
+    end: usize,
+}
+
+impl JumpRope {
+    /// Get a read only view of the specified range of characters in the rope, without copying
+    /// them. The range is clamped to the document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("xxxκόσμεxxx");
+    /// let slice = rope.slice(3..8);
+    /// assert_eq!(slice.len_chars(), 5);
+    /// assert_eq!(slice.len_bytes(), 10);
+    /// assert_eq!(slice, "κόσμε");
+    /// ```
+    pub fn slice(&self, range: Range<usize>) -> RopeSlice<'_> {
+        let end = range.end.min(self.len_chars());
+        RopeSlice { rope: self, start: range.start.min(end), end }
+    }
+}
+
+impl<'a> RopeSlice<'a> {
+    /// The range of characters in the rope which this slice covers.
+    pub fn char_range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The number of unicode characters in the slice.
+    pub fn len_chars(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// The number of bytes the slice's content takes up in UTF8. This runs in *O(log n)* time.
+    pub fn len_bytes(&self) -> usize {
+        self.rope.char_to_byte(self.end) - self.rope.char_to_byte(self.start)
+    }
+
+    /// Returns `true` if the slice contains no characters.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Get a smaller slice of this slice. The range is relative to the start of this slice, and it
+    /// is clamped to this slice.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("Hello world!");
+    /// let slice = rope.slice(6..12);
+    /// assert_eq!(slice.slice(0..5), "world");
+    /// assert_eq!(slice.slice(0..5).char_range(), 6..11);
+    /// ```
+    pub fn slice(&self, range: Range<usize>) -> RopeSlice<'a> {
+        let end = self.start + range.end.min(self.len_chars());
+        RopeSlice { rope: self.rope, start: (self.start + range.start).min(end), end }
+    }
+
+    /// Iterate over the slice's content in chunks, as `(str, char_len)` pairs. See
+    /// [`JumpRope::chunks`].
+    pub fn chunks(&self) -> ContentRangeIter<'a> {
+        self.rope.slice_chunks(self.start..self.end)
+    }
+
+    /// Iterate over the characters in the slice.
+    pub fn chars(&self) -> CharsSlice<'a> {
+        self.chunks().chars()
+    }
+
+    /// Copy the slice's content into a new `String`. The string is allocated once, with exactly
+    /// enough capacity for the content.
+    // The result is identical to the Display implementation. It's just faster.
+    #[allow(clippy::inherent_to_string_shadow_display)]
+    pub fn to_string(&self) -> String {
+        let mut s = String::with_capacity(self.len_bytes());
+        for (chunk, _) in self.chunks() {
+            s.push_str(chunk);
+        }
+        s
+    }
+
+    fn eq_str(&self, mut other: &str) -> bool {
+        for (s, _) in self.chunks() {
+            match other.strip_prefix(s) {
+                Some(rest) => other = rest,
+                None => return false,
+            }
+        }
+        other.is_empty()
+    }
+}
+
+impl Debug for RopeSlice<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_list()
+            .entries(self.chunks().strings())
+            .finish()
+    }
+}
+
+impl Display for RopeSlice<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for (s, _) in self.chunks() {
+            f.write_str(s)?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq for RopeSlice<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len_chars() == other.len_chars() && self.chars().eq(other.chars())
+    }
+}
+
+impl Eq for RopeSlice<'_> {}
+
+impl PartialEq<str> for RopeSlice<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl PartialEq<&str> for RopeSlice<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl PartialEq<String> for RopeSlice<'_> {
+    fn eq(&self, other: &String) -> bool {
+        self.eq_str(other.as_str())
+    }
+}
+
+impl<'a> From<RopeSlice<'a>> for String {
+    fn from(slice: RopeSlice<'a>) -> Self {
+        slice.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    #[test]
+    fn slices() {
+        let s = "κό𝕐𝕆😘σμε and some ascii text which spans several nodes";
+        let rope = JumpRope::from(s);
+        let chars = s.chars().collect::<Vec<_>>();
+
+        for start in 0..chars.len() {
+            for end in (start..chars.len() + 2).step_by(3) {
+                let slice = rope.slice(start..end);
+                let end = end.min(chars.len());
+                let expected = chars[start..end].iter().collect::<String>();
+                assert_eq!(slice, expected);
+                assert_eq!(slice.to_string(), expected);
+                assert_eq!(format!("{}", slice), expected);
+                assert_eq!(slice.len_chars(), end - start);
+                assert_eq!(slice.len_bytes(), expected.len());
+                assert_eq!(slice.is_empty(), expected.is_empty());
+                assert_eq!(slice.chars().collect::<String>(), expected);
+            }
+        }
+
+        let slice = rope.slice(2..10);
+        assert_eq!(slice.slice(1..3), rope.slice(3..5));
+        assert_eq!(slice.slice(5..100), rope.slice(7..10));
+        assert_ne!(slice, "𝕐𝕆😘");
+        assert!(rope.slice(100..200).is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+use core::cmp::Ordering;
+use core::ops::Range;
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::JumpRope;
+
+/// Common orderings for [`JumpRope::sort_lines`]. Call [`compare`](Self::compare) to use one as a
+/// comparator.
+///
+/// Lines which compare equal under an ordering are ordered by their raw content instead, so
+/// sorting with any of these is deterministic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LineOrder {
+    /// Order lines by their content, byte by byte.
+    Lexical,
+    /// Order lines by their content, ignoring case.
+    CaseInsensitive,
+    /// Order lines by the number at the start of each line (after any leading whitespace), like
+    /// `sort -n`. Lines which don't start with a number are treated as 0.
+    Numeric,
+    /// Order lines by their content, but compare runs of digits by their numeric value. So
+    /// `"file2"` sorts before `"file10"`.
+    Natural,
+}
+
+impl LineOrder {
+    /// Compare two lines using this ordering.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// # use std::cmp::Ordering;
+    /// assert_eq!(LineOrder::Lexical.compare("file10", "file2"), Ordering::Less);
+    /// assert_eq!(LineOrder::Natural.compare("file10", "file2"), Ordering::Greater);
+    /// ```
+    pub fn compare(self, a: &str, b: &str) -> Ordering {
+        let ord = match self {
+            LineOrder::Lexical => Ordering::Equal,
+            LineOrder::CaseInsensitive => {
+                a.chars().flat_map(char::to_lowercase)
+                    .cmp(b.chars().flat_map(char::to_lowercase))
+            }
+            LineOrder::Numeric => leading_number(a).total_cmp(&leading_number(b)),
+            LineOrder::Natural => natural_cmp(a, b),
+        };
+        ord.then_with(|| a.cmp(b))
+    }
+}
+
+// Parse the number at the start of s, ignoring leading whitespace. Returns 0 if there isn't one.
+fn leading_number(s: &str) -> f64 {
+    let s = s.trim_start();
+    let bytes = s.as_bytes();
+    let mut end = 0;
+    if matches!(bytes.first(), Some(b'-' | b'+')) { end += 1; }
+    let digits_start = end;
+    while end < bytes.len() && bytes[end].is_ascii_digit() { end += 1; }
+    if end < bytes.len() && bytes[end] == b'.' {
+        end += 1;
+        while end < bytes.len() && bytes[end].is_ascii_digit() { end += 1; }
+    }
+
+    // A lone sign or "." isn't a number.
+    if !bytes[digits_start..end].iter().any(u8::is_ascii_digit) { return 0.0; }
+    s[..end].parse().unwrap_or(0.0)
+}
+
+fn trim_zeros(digits: &[u8]) -> &[u8] {
+    let zeros = digits.iter().take_while(|&&c| c == b'0').count();
+    &digits[zeros..]
+}
+
+// Compare strings, treating each run of ASCII digits as a single number.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.as_bytes(), b.as_bytes());
+    let digit_run = |s: &[u8]| s.iter().position(|c| !c.is_ascii_digit()).unwrap_or(s.len());
+
+    loop {
+        match (a.first(), b.first()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (a_len, b_len) = (digit_run(a), digit_run(b));
+                // Leading zeros don't change the value. Compare by length, then digit by digit.
+                let (x, y) = (trim_zeros(&a[..a_len]), trim_zeros(&b[..b_len]));
+                let ord = x.len().cmp(&y.len()).then_with(|| x.cmp(y));
+                if ord != Ordering::Equal { return ord; }
+                a = &a[a_len..];
+                b = &b[b_len..];
+            }
+            (Some(x), Some(y)) => {
+                // Comparing UTF8 byte by byte gives the same order as comparing characters.
+                if x != y { return x.cmp(y); }
+                a = &a[1..];
+                b = &b[1..];
+            }
+        }
+    }
+}
+
+impl JumpRope {
+    /// Sort a range of lines in place, using a comparator function. The sort is stable. `lines` is
+    /// a range of (0-based) line indexes, which is clamped to the lines in the document.
+    ///
+    /// Line endings stay where they are. Only the content of the lines is reordered - so if the
+    /// last line in the document is sorted, it stays without a trailing newline. See
+    /// [`LineOrder`] for some common comparators.
+    ///
+    /// The lines are read once and sorted by index. If they're already in order the rope isn't
+    /// modified. Otherwise the whole region is replaced in a single edit, which is much faster than
+    /// moving lines around one by one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("header\nfile10\nFile2\nfile1\n");
+    /// rope.sort_lines(1..4, |a, b| LineOrder::Natural.compare(a, b));
+    /// assert_eq!(rope, "header\nFile2\nfile1\nfile10\n");
+    ///
+    /// rope.sort_lines(1..4, |a, b| LineOrder::CaseInsensitive.compare(a, b));
+    /// assert_eq!(rope, "header\nfile1\nfile10\nFile2\n");
+    /// ```
+    pub fn sort_lines<F: FnMut(&str, &str) -> Ordering>(&mut self, lines: Range<usize>, mut compare: F) {
+        let end_line = lines.end.min(self.len_lines());
+        if lines.start + 1 >= end_line { return; }
+
+        let start = self.line_to_char(lines.start);
+        let end = self.line_content_range(end_line - 1).end;
+        let content = self.slice_chars(start..end).collect::<String>();
+        let content_lines = content.split('\n').collect::<Vec<_>>();
+
+        let mut order = (0..content_lines.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| compare(content_lines[a], content_lines[b]));
+        if order.iter().enumerate().all(|(i, &idx)| i == idx) { return; }
+
+        let mut sorted = String::with_capacity(content.len());
+        for (i, &idx) in order.iter().enumerate() {
+            if i > 0 { sorted.push('\n'); }
+            sorted.push_str(content_lines[idx]);
+        }
+        self.replace(start..end, &sorted);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use crate::{JumpRope, LineOrder};
+
+    #[test]
+    fn line_orders() {
+        let cmp = |order: LineOrder, a, b| order.compare(a, b);
+        assert_eq!(cmp(LineOrder::Lexical, "B", "a"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::CaseInsensitive, "B", "a"), Ordering::Greater);
+        assert_eq!(cmp(LineOrder::CaseInsensitive, "A", "a"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::CaseInsensitive, "Éa", "éb"), Ordering::Less);
+
+        assert_eq!(cmp(LineOrder::Numeric, "  10 x", "9"), Ordering::Greater);
+        assert_eq!(cmp(LineOrder::Numeric, "-1.5", "-1.25"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::Numeric, "x", "0.5"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::Numeric, "-", "1"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::Numeric, "2", "2."), Ordering::Less);
+
+        assert_eq!(cmp(LineOrder::Natural, "a2b", "a10b"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::Natural, "a002", "a10"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::Natural, "a02", "a2"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::Natural, "a2", "a2b"), Ordering::Less);
+        assert_eq!(cmp(LineOrder::Natural, "ab", "a2"), Ordering::Greater);
+        assert_eq!(cmp(LineOrder::Natural, "é1", "é01"), Ordering::Greater);
+    }
+
+    #[test]
+    fn sort_lines() {
+        let text = "d\n𝕐\nb\n\nc\nκ\na";
+        let all_lines = text.split('\n').collect::<Vec<_>>();
+
+        for start in 0..=all_lines.len() {
+            for end in start..=all_lines.len() + 1 {
+                let mut rope = JumpRope::from(text);
+                rope.sort_lines(start..end, |a, b| a.cmp(b));
+                rope.check();
+
+                let mut expected = all_lines.clone();
+                let end = end.min(all_lines.len());
+                expected[start..end.max(start)].sort();
+                assert_eq!(rope, expected.join("\n"));
+            }
+        }
+
+        let mut rope = JumpRope::from("b\na\n");
+        rope.sort_lines(0..100, |a, b| a.cmp(b));
+        assert_eq!(rope, "\na\nb");
+
+        // The sort is stable.
+        let mut rope = JumpRope::from("b2\na1\nb1\na2");
+        rope.sort_lines(0..4, |a, b| a[..1].cmp(&b[..1]));
+        assert_eq!(rope, "a1\na2\nb2\nb1");
+    }
+}
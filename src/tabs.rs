@@ -0,0 +1,219 @@
+// Converting between tabs and spaces.
+
+use core::ops::Range;
+use alloc::vec::Vec;
+use crate::JumpRope;
+use crate::utils::{count_chars, next_column};
+
+impl JumpRope {
+    /// Replace every tab with spaces, up to the next multiple of `tab_width` columns. Columns are
+    /// counted in characters from the start of each line, so text after the tabs stays aligned.
+    /// Nothing happens if `tab_width` is 0.
+    ///
+    /// The document is scanned once, and each run of tabs is replaced with a single edit. Chunks
+    /// which don't contain tabs aren't modified. Returns the number of tabs which were replaced.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("a\tb\n\t\tc\nabcd\te");
+    /// assert_eq!(rope.expand_tabs(4), 4);
+    /// assert_eq!(rope, "a   b\n        c\nabcd    e");
+    /// ```
+    pub fn expand_tabs(&mut self, tab_width: usize) -> usize {
+        if tab_width == 0 { return 0; }
+
+        // Each run of tabs, and the number of spaces it expands to.
+        let mut edits: Vec<(Range<usize>, usize)> = Vec::new();
+        let mut pos = 0;
+        let mut col = 0;
+        let mut run: Option<(usize, usize)> = None; // The start position and column of a run.
+
+        for (s, char_len) in self.chunks() {
+            if run.is_none() && !s.contains('\t') {
+                // Fast path. We only need to know the column at the end of the chunk.
+                col = match s.rfind('\n') {
+                    Some(i) => count_chars(&s[i + 1..]),
+                    None => col + char_len,
+                };
+                pos += char_len;
+                continue;
+            }
+
+            for c in s.chars() {
+                if c == '\t' {
+                    run.get_or_insert((pos, col));
+                } else if let Some((start, start_col)) = run.take() {
+                    edits.push((start..pos, col - start_col));
+                }
+                col = if c == '\n' { 0 } else { next_column(col, c, tab_width) };
+                pos += 1;
+            }
+        }
+        if let Some((start, start_col)) = run {
+            edits.push((start..pos, col - start_col));
+        }
+
+        // Edit from the end, so the earlier positions stay valid.
+        let mut tabs = 0;
+        for (range, spaces) in edits.into_iter().rev() {
+            tabs += range.len();
+            self.replace(range, " ".repeat(spaces));
+        }
+        tabs
+    }
+
+    /// Rewrite the indentation at the start of each line using as many tabs as possible, followed
+    /// by spaces. Tab stops are every `tab_width` columns. Like the `unexpand` command, only
+    /// leading whitespace is converted - spaces after the first non-whitespace character on a line
+    /// are left alone. Nothing happens if `tab_width` is 0.
+    ///
+    /// The document is scanned once, and only lines whose indentation changes are edited. Returns
+    /// the number of lines which were edited.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rope = JumpRope::from("    a    b\n      c\n \td");
+    /// assert_eq!(rope.unexpand_tabs(4), 3);
+    /// assert_eq!(rope, "\ta    b\n\t  c\n\td");
+    /// assert_eq!(rope.unexpand_tabs(4), 0);
+    /// ```
+    pub fn unexpand_tabs(&mut self, tab_width: usize) -> usize {
+        if tab_width == 0 { return 0; }
+
+        // The range and width of each line's indentation which needs rewriting.
+        let mut edits: Vec<(Range<usize>, usize)> = Vec::new();
+        let mut pos = 0;
+        let mut in_indent = true;
+        let mut indent_start = 0;
+        let mut width = 0;
+        // Indentation is already in the right form if it's tabs followed by fewer than tab_width
+        // spaces.
+        let mut spaces = 0;
+        let mut tab_after_space = false;
+
+        let finish_indent = |edits: &mut Vec<_>, range, width, spaces, tab_after_space| {
+            if tab_after_space || spaces >= tab_width {
+                edits.push((range, width));
+            }
+        };
+
+        for (mut s, _) in self.chunks() {
+            while !s.is_empty() {
+                if !in_indent {
+                    // Skip to the start of the next line.
+                    match s.find('\n') {
+                        Some(i) => {
+                            pos += count_chars(&s[..i]) + 1;
+                            s = &s[i + 1..];
+                            in_indent = true;
+                            indent_start = pos;
+                            (width, spaces, tab_after_space) = (0, 0, false);
+                        }
+                        None => {
+                            pos += count_chars(s);
+                            s = "";
+                        }
+                    }
+                    continue;
+                }
+
+                let c = s.chars().next().unwrap();
+                match c {
+                    ' ' => spaces += 1,
+                    '\t' => tab_after_space |= spaces > 0,
+                    _ => {
+                        // Leave the character to be scanned on the next iteration.
+                        finish_indent(&mut edits, indent_start..pos, width, spaces, tab_after_space);
+                        in_indent = false;
+                        continue;
+                    }
+                }
+                width = next_column(width, c, tab_width);
+                pos += 1;
+                s = &s[1..];
+            }
+        }
+        if in_indent {
+            finish_indent(&mut edits, indent_start..pos, width, spaces, tab_after_space);
+        }
+
+        let count = edits.len();
+        for (range, width) in edits.into_iter().rev() {
+            let mut indent = "\t".repeat(width / tab_width);
+            indent.push_str(&" ".repeat(width % tab_width));
+            self.replace(range, indent);
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    fn expand(s: &str, tab_width: usize) -> String {
+        let mut result = String::new();
+        for line in s.split('\n') {
+            let mut col = 0;
+            for c in line.chars() {
+                if c == '\t' {
+                    let next = (col / tab_width + 1) * tab_width;
+                    result.push_str(&" ".repeat(next - col));
+                    col = next;
+                } else {
+                    result.push(c);
+                    col += 1;
+                }
+            }
+            result.push('\n');
+        }
+        result.pop();
+        result
+    }
+
+    #[test]
+    fn expand_tabs() {
+        let s = "\tκό\t𝕐\n\t\t x\t\n\n  \t\tabcdefgh\t".repeat(3);
+        for tab_width in 1..6 {
+            let mut rope = JumpRope::from(s.as_str());
+            assert_eq!(rope.expand_tabs(tab_width), s.matches('\t').count());
+            rope.check();
+            assert_eq!(rope, expand(&s, tab_width));
+        }
+
+        let mut rope = JumpRope::from(s.as_str());
+        assert_eq!(rope.expand_tabs(0), 0);
+        assert_eq!(rope, s);
+    }
+
+    #[test]
+    fn unexpand_tabs() {
+        let s = "\tκό  \t𝕐\n   \t\t x\t\n\n  \t\tabcdefgh\t\n\t  \n     ";
+        for tab_width in 1..6 {
+            let mut rope = JumpRope::from(s);
+            rope.unexpand_tabs(tab_width);
+            rope.check();
+
+            let expected = s.split('\n').map(|line| {
+                let content = line.trim_start_matches([' ', '\t']);
+                let indent = expand(&line[..line.len() - content.len()], tab_width).len();
+                "\t".repeat(indent / tab_width) + &" ".repeat(indent % tab_width) + content
+            }).collect::<Vec<_>>().join("\n");
+            assert_eq!(rope, expected);
+
+            assert_eq!(rope.unexpand_tabs(tab_width), 0);
+
+            // Expanding the result gets back to fully expanded indentation.
+            rope.expand_tabs(tab_width);
+            assert_eq!(rope, expand(s, tab_width));
+        }
+
+        let mut rope = JumpRope::from("\t  x");
+        assert_eq!(rope.unexpand_tabs(4), 0);
+        assert_eq!(rope, "\t  x");
+    }
+}
@@ -0,0 +1,411 @@
+//! Helpers for testing and fuzzing code built on top of jumprope.
+//!
+//! This module is only available with the `testing` feature enabled.
+
+use core::ops::Range;
+use alloc::string::String;
+use rand::Rng;
+use crate::{JumpRope, ReversibleOp};
+
+/// A trivially correct reference model of a [`JumpRope`], backed by a `String`. It has the same
+/// editing API as `JumpRope` (with the same clamping rules for out of range positions), so tests
+/// and fuzzers can make the same edits to both and check they agree with
+/// [`assert_matches`](Self::assert_matches).
+///
+/// Every operation is *O(n)*, so this is only suitable for testing.
+///
+/// # Example
+///
+/// ```
+/// # use jumprope::*;
+/// use jumprope::testing::Oracle;
+/// let mut rope = JumpRope::new();
+/// let mut oracle = Oracle::new();
+/// for (pos, content) in [(0, "hello"), (100, " world\n"), (5, ",")] {
+///     rope.insert(pos, content);
+///     oracle.insert(pos, content);
+/// }
+/// rope.remove(0..1);
+/// oracle.remove(0..1);
+/// oracle.assert_matches(&rope);
+/// assert_eq!(oracle.as_str(), "ello, world\n");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Oracle {
+    content: String,
+}
+
+impl Oracle {
+    /// Create a new, empty oracle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The expected content of the rope.
+    pub fn as_str(&self) -> &str {
+        &self.content
+    }
+
+    /// The number of unicode characters in the content.
+    pub fn len_chars(&self) -> usize {
+        self.content.chars().count()
+    }
+
+    /// The number of bytes in the content.
+    pub fn len_bytes(&self) -> usize {
+        self.content.len()
+    }
+
+    /// The number of lines in the content, counted like [`JumpRope::len_lines`].
+    pub fn len_lines(&self) -> usize {
+        self.content.matches('\n').count() + 1
+    }
+
+    // Convert a character position to a byte offset, clamping it to the end of the content.
+    fn byte_pos(&self, pos: usize) -> usize {
+        self.content.char_indices().nth(pos).map_or(self.content.len(), |(b, _)| b)
+    }
+
+    /// Insert content at a character position. See [`JumpRope::insert`].
+    pub fn insert<S: AsRef<str>>(&mut self, pos: usize, contents: S) {
+        let byte = self.byte_pos(pos);
+        self.content.insert_str(byte, contents.as_ref());
+    }
+
+    /// Remove a range of characters. See [`JumpRope::remove`].
+    pub fn remove(&mut self, range: Range<usize>) {
+        let end = self.byte_pos(range.end);
+        let start = self.byte_pos(range.start).min(end);
+        self.content.replace_range(start..end, "");
+    }
+
+    /// Replace a range of characters with new content. See [`JumpRope::replace`].
+    pub fn replace<S: AsRef<str>>(&mut self, range: Range<usize>, contents: S) {
+        let end = self.byte_pos(range.end);
+        let start = self.byte_pos(range.start).min(end);
+        self.content.replace_range(start..end, contents.as_ref());
+    }
+
+    /// Apply an operation recorded with [`JumpRope::with_recording`].
+    pub fn apply(&mut self, op: &ReversibleOp) {
+        match op {
+            ReversibleOp::Insert { pos, content } => self.insert(*pos, content),
+            ReversibleOp::Remove { .. } => self.remove(op.range()),
+        }
+    }
+
+    /// Check that the rope's content matches the oracle, along with its lengths and its line,
+    /// byte and (with the `wchar_conversion` feature) UTF-16 position conversions. The rope's
+    /// internal invariants are checked too.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the rope doesn't match.
+    pub fn assert_matches(&self, rope: &JumpRope) {
+        rope.check();
+        let content = self.content.as_str();
+        assert_eq!(rope.len_bytes(), content.len(), "Rope byte length doesn't match");
+        assert_eq!(rope.len_chars(), self.len_chars(), "Rope char length doesn't match");
+        assert_eq!(rope.len_lines(), self.len_lines(), "Rope line count doesn't match");
+        assert!(rope == content, "Rope content doesn't match. Expected {:?}, got {:?}", content, rope);
+
+        let mut line = 0;
+        #[cfg(feature = "wchar_conversion")]
+        let mut wchars = 0;
+        for (pos, (byte, c)) in content.char_indices().enumerate() {
+            assert_eq!(rope.char_to_byte(pos), byte, "char_to_byte({}) doesn't match", pos);
+            assert_eq!(rope.byte_to_char(byte), pos, "byte_to_char({}) doesn't match", byte);
+            assert_eq!(rope.char_to_line(pos), line, "char_to_line({}) doesn't match", pos);
+            if c == '\n' {
+                line += 1;
+                assert_eq!(rope.line_to_char(line), pos + 1, "line_to_char({}) doesn't match", line);
+            }
+            #[cfg(feature = "wchar_conversion")] {
+                assert_eq!(rope.chars_to_wchars(pos), wchars, "chars_to_wchars({}) doesn't match", pos);
+                wchars += c.len_utf16();
+            }
+        }
+    }
+}
+
+impl JumpRope {
+    /// Pick a random character position in the rope, suitable for an insert. Every position from
+    /// 0 to [`len_chars`](Self::len_chars) (inclusive) is equally likely.
+    ///
+    /// This method is only available with the `testing` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rng = rand::thread_rng();
+    /// let mut rope = JumpRope::from("hi there");
+    /// let pos = rope.random_char_pos(&mut rng);
+    /// assert!(pos <= rope.len_chars());
+    /// rope.insert(pos, "x");
+    /// ```
+    pub fn random_char_pos<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        rng.gen_range(0..=self.len_chars())
+    }
+
+    /// Pick a random range of characters in the rope, suitable for a delete or replace. The range
+    /// starts at a uniformly chosen position, and its length is uniformly chosen from 0 to
+    /// `max_len` (clamped so the range stays within the rope).
+    ///
+    /// This method is only available with the `testing` feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let mut rng = rand::thread_rng();
+    /// let mut rope = JumpRope::from("hi there");
+    /// let range = rope.random_range(&mut rng, 3);
+    /// assert!(range.end <= rope.len_chars());
+    /// assert!(range.len() <= 3);
+    /// rope.remove(range);
+    /// ```
+    pub fn random_range<R: Rng + ?Sized>(&self, rng: &mut R, max_len: usize) -> Range<usize> {
+        let start = self.random_char_pos(rng);
+        let len = rng.gen_range(0..=max_len.min(self.len_chars() - start));
+        start..start + len
+    }
+}
+
+/// A standard editing workload, for measuring rope performance with [`measure_ns_per_op`].
+///
+/// This is only available with the `perf-assert` feature enabled.
+#[cfg(feature = "perf-assert")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Trace {
+    /// Single character inserts and backspaces near one position, which occasionally jumps
+    /// somewhere else. This is what a person typing looks like.
+    Typing,
+    /// Short inserts at random positions in a 100kb document.
+    RandomInserts,
+    /// Short removes at random positions in a document, until it's almost empty.
+    RandomRemoves,
+    /// Short inserts at the end of the document, like loading a file in pieces.
+    Append,
+}
+
+/// The timing of a [`Trace`], from [`measure_ns_per_op`].
+///
+/// This is only available with the `perf-assert` feature enabled.
+#[cfg(feature = "perf-assert")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub struct PerfSample {
+    /// The trace which was measured.
+    pub trace: Trace,
+    /// The number of operations in the trace.
+    pub ops: usize,
+    /// The average time taken by each operation, in nanoseconds.
+    pub ns_per_op: f64,
+}
+
+#[cfg(feature = "perf-assert")]
+enum TraceOp {
+    Insert(usize, &'static str),
+    Remove(Range<usize>),
+}
+
+// Generate the operations for a trace, along with the document they're applied to. The operations
+// are generated up front, so only the rope's work is timed.
+#[cfg(feature = "perf-assert")]
+fn trace_ops(trace: Trace, ops: usize) -> (String, alloc::vec::Vec<TraceOp>) {
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+    const CONTENT: [&str; 4] = ["a", "xy", "κό", "𝕐😘\n"];
+
+    let mut rng = SmallRng::seed_from_u64(321);
+    let doc = match trace {
+        Trace::RandomInserts => "abcdefghi\n".repeat(10_000),
+        Trace::RandomRemoves => "abcdefghi\n".repeat(ops / 2 + 1),
+        _ => String::new(),
+    };
+    let mut len = doc.len();
+    let mut pos = 0;
+    let ops = (0..ops).map(|_| {
+        let content = CONTENT[rng.gen_range(0..CONTENT.len())];
+        let op = match trace {
+            Trace::Typing => {
+                if rng.gen_bool(0.02) { pos = rng.gen_range(0..=len); }
+                if pos > 0 && rng.gen_bool(0.2) {
+                    pos -= 1;
+                    TraceOp::Remove(pos..pos + 1)
+                } else {
+                    pos += 1;
+                    TraceOp::Insert(pos - 1, "a")
+                }
+            }
+            Trace::RandomInserts => TraceOp::Insert(rng.gen_range(0..=len), content),
+            Trace::RandomRemoves => {
+                let start = rng.gen_range(0..len - 5);
+                TraceOp::Remove(start..start + 5)
+            }
+            Trace::Append => TraceOp::Insert(len, content),
+        };
+        match &op {
+            TraceOp::Insert(_, content) => len += content.chars().count(),
+            TraceOp::Remove(range) => len -= range.len(),
+        }
+        op
+    }).collect();
+    (doc, ops)
+}
+
+/// Measure the average time taken by each insert or remove in a standard trace of `ops`
+/// operations. The trace is run 3 times, and the fastest run is reported to reduce noise.
+///
+/// This is meant for downstream CI to catch performance regressions - for example from enabling
+/// a feature (like `wchar_conversion`) which makes the rope do more work. Timings depend on the
+/// machine and build profile, so compare them against a baseline measured in the same setup. Use
+/// a release build.
+///
+/// This is only available with the `perf-assert` feature enabled.
+///
+/// # Example
+///
+/// ```
+/// use jumprope::testing::{measure_ns_per_op, Trace};
+/// let sample = measure_ns_per_op(Trace::Typing, 10_000);
+/// assert_eq!(sample.ops, 10_000);
+/// println!("{:.1}ns per keystroke", sample.ns_per_op);
+/// ```
+#[cfg(feature = "perf-assert")]
+pub fn measure_ns_per_op(trace: Trace, ops: usize) -> PerfSample {
+    use std::time::Instant;
+    let (doc, trace_ops) = trace_ops(trace, ops);
+
+    let ns_per_op = (0..3).map(|_| {
+        let mut rope = JumpRope::from(doc.as_str());
+        let start = Instant::now();
+        for op in &trace_ops {
+            match op {
+                TraceOp::Insert(pos, content) => rope.insert(*pos, content),
+                TraceOp::Remove(range) => rope.remove(range.clone()),
+            }
+        }
+        let elapsed = start.elapsed();
+        std::hint::black_box(&rope);
+        elapsed.as_nanos() as f64 / ops.max(1) as f64
+    }).fold(f64::INFINITY, f64::min);
+
+    PerfSample { trace, ops, ns_per_op }
+}
+
+/// Measure a trace with [`measure_ns_per_op`], and panic if its operations take more than
+/// `max_ns` nanoseconds each on average. The panic message includes the measured time and the
+/// rope's [`config`](JumpRope::config).
+///
+/// This is only available with the `perf-assert` feature enabled.
+///
+/// # Example
+///
+/// ```
+/// use jumprope::testing::{assert_ns_per_op, Trace};
+/// // A very generous limit, so this passes in debug builds too.
+/// assert_ns_per_op(Trace::Append, 10_000, 1_000_000.0);
+/// ```
+#[cfg(feature = "perf-assert")]
+pub fn assert_ns_per_op(trace: Trace, ops: usize, max_ns: f64) -> PerfSample {
+    let sample = measure_ns_per_op(trace, ops);
+    assert!(sample.ns_per_op <= max_ns,
+        "{:?} trace took {:.1}ns per op, over the limit of {:.1}ns. Rope config: {:?}",
+        trace, sample.ns_per_op, max_ns, JumpRope::config());
+    sample
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+    use crate::JumpRope;
+    use super::Oracle;
+
+    #[test]
+    fn random_positions_in_bounds() {
+        let mut rng = SmallRng::seed_from_u64(10);
+        let mut rope = JumpRope::new();
+        let mut seen_end = false;
+
+        for _ in 0..500 {
+            let pos = rope.random_char_pos(&mut rng);
+            assert!(pos <= rope.len_chars());
+            seen_end |= pos == rope.len_chars() && pos > 0;
+            rope.insert(pos, "κόx");
+
+            let range = rope.random_range(&mut rng, 4);
+            assert!(range.start <= range.end && range.end <= rope.len_chars());
+            assert!(range.len() <= 4);
+            rope.remove(range);
+        }
+        assert!(seen_end);
+        rope.check();
+    }
+
+    #[test]
+    fn oracle_matches_rope() {
+        let mut rng = SmallRng::seed_from_u64(11);
+        let mut rope = JumpRope::new();
+        let mut oracle = Oracle::new();
+
+        for i in 0..300 {
+            let content = ["κό", "x\n", "𝕐𝕆😘", "", "abcdefghijklmn\n"][i % 5];
+            let pos = rope.random_char_pos(&mut rng) + i % 3;
+            let range = rope.random_range(&mut rng, 10);
+            match i % 4 {
+                0 | 1 => { rope.insert(pos, content); oracle.insert(pos, content); }
+                2 => { rope.remove(range.start..range.end + 5); oracle.remove(range.start..range.end + 5); }
+                _ => {
+                    let ops = rope.with_recording(|r| r.replace(range.clone(), content));
+                    for op in &ops { oracle.apply(op); }
+                }
+            }
+            oracle.assert_matches(&rope);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "content doesn't match")]
+    fn oracle_catches_mismatch() {
+        let mut oracle = Oracle::new();
+        oracle.insert(0, "abc");
+        oracle.assert_matches(&JumpRope::from("abd"));
+    }
+
+    #[cfg(feature = "perf-assert")]
+    #[test]
+    fn perf_traces() {
+        use super::*;
+        for trace in [Trace::Typing, Trace::RandomInserts, Trace::RandomRemoves, Trace::Append] {
+            // Every trace stays within the document.
+            let (doc, ops) = trace_ops(trace, 2000);
+            let mut rope = JumpRope::from(doc.as_str());
+            for op in &ops {
+                match op {
+                    TraceOp::Insert(pos, content) => {
+                        assert!(*pos <= rope.len_chars());
+                        rope.insert(*pos, content);
+                    }
+                    TraceOp::Remove(range) => {
+                        assert!(range.end <= rope.len_chars());
+                        rope.remove(range.clone());
+                    }
+                }
+            }
+
+            let sample = assert_ns_per_op(trace, 2000, f64::INFINITY);
+            assert_eq!(sample.ops, 2000);
+            assert!(sample.ns_per_op > 0.0);
+        }
+    }
+
+    #[cfg(feature = "perf-assert")]
+    #[test]
+    #[should_panic(expected = "Typing trace took")]
+    fn perf_assert_fails() {
+        super::assert_ns_per_op(super::Trace::Typing, 1000, 0.0);
+    }
+}
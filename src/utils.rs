@@ -1,20 +1,101 @@
 
-// Get the byte offset after char_pos utf8 characters
+#[inline]
+fn is_char_start(b: u8) -> bool {
+    // Continuation bytes have the form 0b10xxxxxx.
+    (b as i8) >= -0x40
+}
+
+// Get the byte offset after char_pos utf8 characters. Positions past the end of the string return
+// the length of the string.
 pub(crate) fn str_chars_to_bytes(s: &str, char_pos: usize) -> usize {
-    // s.char_indices().nth(char_pos).map_or_else(
-    //     || s.len(),
-    //     |(i, _)| i
-    // )
+    let bytes = s.as_bytes();
+    // Fast path for ASCII, where characters and bytes line up.
+    if char_pos <= bytes.len() && bytes[..char_pos].is_ascii() { return char_pos; }
 
-    ropey::str_utils::char_to_byte_idx(s, char_pos)
+    let mut chars = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        if is_char_start(b) {
+            if chars == char_pos { return i; }
+            chars += 1;
+        }
+    }
+    bytes.len()
 }
 
+// Get the index of the character containing the passed byte offset. Offsets past the end of the
+// string return the number of characters in the string.
 pub(crate) fn str_bytes_to_chars(s: &str, bytes: usize) -> usize {
-    ropey::str_utils::byte_to_char_idx(s, bytes)
+    let b = s.as_bytes();
+    if bytes >= b.len() { return count_start_bytes(b); }
+    // Count the character starts up to and including the passed byte.
+    count_start_bytes(&b[..=bytes]) - 1
+}
+
+#[inline]
+fn count_start_bytes(b: &[u8]) -> usize {
+    // This gets vectorized nicely by the compiler.
+    b.iter().filter(|&&b| is_char_start(b)).count()
 }
 
 pub(crate) fn count_chars(s: &str) -> usize {
-    str_bytes_to_chars(s, s.len())
+    count_start_bytes(s.as_bytes())
+}
+
+/// A tiny PRNG used to pick node heights when jumprope is compiled without rand.
+#[cfg(not(feature = "rand"))]
+pub(crate) struct SplitMix64(u64);
+
+#[cfg(not(feature = "rand"))]
+impl SplitMix64 {
+    pub(crate) fn seed_from_u64(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+}
+
+/// Get the number of bytes in the UTF8 character starting with the passed byte.
+pub(crate) fn utf8_char_width(first_byte: u8) -> usize {
+    match first_byte {
+        0..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    }
+}
+
+/// Find the largest byte offset <= max_bytes which lies on a character boundary in s.
+pub(crate) fn floor_char_boundary(s: &str, max_bytes: usize) -> usize {
+    if max_bytes >= s.len() { return s.len(); }
+    let mut i = max_bytes;
+    while !s.is_char_boundary(i) { i -= 1; }
+    i
+}
+
+/// Get the column after c, when c is drawn at col. Tabs advance to the next tab stop.
+pub(crate) fn next_column(col: usize, c: char, tab_width: usize) -> usize {
+    if c == '\t' && tab_width > 0 { (col / tab_width + 1) * tab_width } else { col + 1 }
+}
+
+pub(crate) fn count_newlines(s: &str) -> usize {
+    s.as_bytes().iter().filter(|&&b| b == b'\n').count()
+}
+
+// Count the characters which take 2 UTF-16 code units (a surrogate pair). These are the characters
+// outside the basic multilingual plane, which are also the characters taking 4 bytes in UTF-8.
+#[cfg(feature = "wchar_conversion")]
+pub(crate) fn count_surrogate_pairs(s: &str) -> usize {
+    s.as_bytes().iter().filter(|&&b| b >= 0xf0).count()
 }
 
 pub(crate) fn str_chars_to_bytes_rev(s: &str, char_len: usize) -> usize {
@@ -46,6 +127,13 @@ mod tests {
             let end_offset = str_chars_to_bytes_rev(s, num_chars - i);
             assert_eq!(end_offset, s.len() - byte_offset);
         }
+
+        for b in 0..=s.len() + 1 {
+            // Offsets in the middle of a character map to that character.
+            let expected = s.char_indices().filter(|&(i, _)| i <= b).count();
+            let expected = if b >= s.len() { num_chars } else { expected - 1 };
+            assert_eq!(str_bytes_to_chars(s, b), expected);
+        }
     }
 
     #[test]
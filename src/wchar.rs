@@ -0,0 +1,126 @@
+// Converting between unicode character positions and UTF-16 code unit ("wchar") positions. This
+// is only available with the wchar_conversion feature.
+
+use crate::JumpRope;
+
+impl JumpRope {
+    /// Return the length of the rope in UTF-16 code units. This is the `length` of the string in
+    /// JavaScript, and the default position encoding used by LSP.
+    ///
+    /// The rope keeps count of its surrogate pairs as it is edited, so this method runs in
+    /// constant time (*O(1)*). This method is only available with the `wchar_conversion` feature
+    /// enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐c"); // 𝕐 is a surrogate pair in UTF-16.
+    /// assert_eq!(rope.len_chars(), 3);
+    /// assert_eq!(rope.len_utf16(), 4);
+    /// ```
+    pub fn len_utf16(&self) -> usize {
+        let entry = &self.head.nexts()[self.head.height as usize - 1];
+        entry.skip_chars + entry.skip_pairs
+    }
+
+    /// Convert a character position to the equivalent position in UTF-16 code units. Positions
+    /// past the end of the rope return [`len_utf16`](Self::len_utf16).
+    ///
+    /// This runs in *O(log n)* time. This method is only available with the `wchar_conversion`
+    /// feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐c");
+    /// assert_eq!(rope.chars_to_wchars(1), 1);
+    /// assert_eq!(rope.chars_to_wchars(2), 3);
+    /// assert_eq!(rope.chars_to_wchars(100), 4);
+    /// ```
+    pub fn chars_to_wchars(&self, chars: usize) -> usize {
+        if chars >= self.len_chars() { return self.len_utf16(); }
+
+        let cursor = self.cursor_at_char(chars, true);
+        let here = unsafe { &*cursor.here_ptr() };
+        let local_pairs = here.str.count_pairs_in(0..cursor.local_char_pos());
+        chars + cursor.global_pairs_before_node(self.head.height) + local_pairs
+    }
+
+    /// Convert a position in UTF-16 code units to the equivalent character position. If the
+    /// position is in the middle of a surrogate pair, the position of that character is returned.
+    /// Positions past the end of the rope return [`len_chars`](Self::len_chars).
+    ///
+    /// This runs in *O(log n)* time. This method is only available with the `wchar_conversion`
+    /// feature enabled.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use jumprope::*;
+    /// let rope = JumpRope::from("a𝕐c");
+    /// assert_eq!(rope.wchars_to_chars(1), 1);
+    /// assert_eq!(rope.wchars_to_chars(2), 1); // Inside 𝕐
+    /// assert_eq!(rope.wchars_to_chars(3), 2);
+    /// assert_eq!(rope.wchars_to_chars(100), 3);
+    /// ```
+    pub fn wchars_to_chars(&self, wchars: usize) -> usize {
+        if wchars >= self.len_utf16() { return self.len_chars(); }
+
+        // Find the node containing the requested position.
+        let mut node = &self.head;
+        let mut height = self.head.height as usize - 1;
+        let mut offset = wchars;
+        let mut chars = 0;
+        loop {
+            let next = node.nexts()[height];
+            let skip = next.skip_chars + next.skip_pairs;
+            if offset >= skip {
+                offset -= skip;
+                chars += next.skip_chars;
+                node = unsafe { &*next.node };
+            } else if height == 0 {
+                break;
+            } else {
+                height -= 1;
+            }
+        }
+
+        for c in node.str.chars() {
+            let len = c.len_utf16();
+            if offset < len { break; }
+            offset -= len;
+            chars += 1;
+        }
+        chars
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::JumpRope;
+
+    #[test]
+    fn wchar_conversion() {
+        let s = "κό𝕐𝕆😘σμε and some text\n".repeat(10);
+        let mut rope = JumpRope::from(s.as_str());
+        rope.remove(3..30);
+        rope.insert(10, "😘😘");
+        let s = rope.to_string();
+        rope.check();
+        assert_eq!(rope.len_utf16(), s.encode_utf16().count());
+
+        let mut wchars = 0;
+        for (i, c) in s.chars().enumerate() {
+            assert_eq!(rope.chars_to_wchars(i), wchars);
+            assert_eq!(rope.wchars_to_chars(wchars), i);
+            if c.len_utf16() == 2 {
+                assert_eq!(rope.wchars_to_chars(wchars + 1), i);
+            }
+            wchars += c.len_utf16();
+        }
+        assert_eq!(rope.chars_to_wchars(rope.len_chars()), wchars);
+        assert_eq!(rope.wchars_to_chars(wchars), rope.len_chars());
+    }
+}
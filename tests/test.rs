@@ -43,6 +43,9 @@ mod test {
         assert_eq!(r.to_string(), expected);
         assert_eq!(r.len_bytes(), expected.len());
         assert_eq!(r.len_chars(), expected.chars().count());
+        assert_eq!(r.len_lines(), expected.matches('\n').count() + 1);
+        #[cfg(feature = "wchar_conversion")]
+        assert_eq!(r.len_utf16(), expected.encode_utf16().count());
         assert!(*r == JumpRope::from(expected), "Rope comparison fails");
 
         let clone = r.clone();
@@ -137,8 +140,185 @@ mod test {
     }
 
 
+    #[test]
+    fn replace_char() {
+        let mut r = JumpRope::from("aaaa κόσμε 𐆐𐆔𐆘𐆚 bbbbbbbbbbbbbbbbbbbbbbbbbb");
+        let mut s = r.to_string();
+        let replacements = ['x', 'Δ', '𐆐', '←', 'y', '\n'];
+
+        let mut rng = SmallRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let len = s.chars().count();
+            let pos = rng.gen_range(0..len + 1);
+            let c = replacements[rng.gen_range(0..replacements.len())];
+
+            r.replace_char(pos, c);
+            string_del_at(&mut s, pos, 1);
+            string_insert_at(&mut s, pos, c.encode_utf8(&mut [0; 4]));
+            check(&r, &s);
+        }
+    }
+
+    #[test]
+    fn overwrite() {
+        let mut r = JumpRope::from("κόσμε\nabc");
+        r.overwrite(0, "xy");
+        check(&r, "xyσμε\nabc");
+
+        r.overwrite(3, "𝕐𝕆😘");
+        check(&r, "xyσ𝕐𝕆😘\nabc");
+
+        r.overwrite(7, "0123");
+        check(&r, "xyσ𝕐𝕆😘\n0123");
+
+        r.overwrite(100, "!");
+        check(&r, "xyσ𝕐𝕆😘\n0123!");
+
+        r.overwrite_with_limit(5, "ABCD", OverwriteLimit::Document);
+        check(&r, "xyσ𝕐𝕆ABCD23!");
+    }
+
+    #[test]
+    fn chars_at_or_before_byte() {
+        let s = "aaaa κόσμε 𐆐𐆔𐆘𐆚 bbbbbbbbbbbbbbbbbbbbbbbbbb";
+        let r = JumpRope::from(s);
+        for byte in 0..=s.len() + 2 {
+            let expected = s.char_indices().take_while(|(i, c)| i + c.len_utf8() <= byte).count();
+            assert_eq!(r.len_chars_at_or_before_byte(byte), expected);
+        }
+    }
+
+    #[test]
+    fn insert_string_types() {
+        let mut r = JumpRope::new();
+        r.insert(0, String::from("c"));
+        r.insert(0, std::borrow::Cow::from("b"));
+        r.insert(0, std::sync::Arc::<str>::from("a"));
+        r.replace(3..3, String::from("d"));
+        r.overwrite(4, Box::<str>::from("e"));
+        check(&r, "abcde");
+    }
+
+    #[test]
+    fn from_fragments() {
+        let fragments = ["κό𝕐", "", "𝕆😘σμε", "some ascii text which spans a few nodes"];
+        let expected = fragments.concat();
+        check(&JumpRope::from(&fragments[..]), &expected);
+        check(&JumpRope::from(fragments.map(String::from).to_vec()), &expected);
+        check(&JumpRope::from_lines(fragments), &fragments.join("\n"));
+        check(&JumpRope::from_lines(Vec::<String>::new()), "");
+        check(&JumpRope::join("", fragments), &expected);
+        check(&JumpRope::join("𝕐, ", fragments), &fragments.join("𝕐, "));
+    }
+
+    #[test]
+    fn try_from_bytes() {
+        // Long enough that multi-byte characters straddle the blocks validated at a time.
+        let s = "κό𝕐𝕆😘σμε and some ascii\n".repeat(2000);
+        check(&JumpRope::try_from(s.as_bytes()).unwrap(), &s);
+        check(&JumpRope::try_from(&b""[..]).unwrap(), "");
+
+        let mut bytes = s.clone().into_bytes();
+        for pos in [0, 20_000, bytes.len() - 1] {
+            let saved = bytes[pos];
+            bytes[pos] = 0xff;
+            let err = JumpRope::try_from(&bytes[..]).unwrap_err();
+            assert_eq!(err, std::str::from_utf8(&bytes).unwrap_err());
+            bytes[pos] = saved;
+        }
+
+        // A character cut off at the end is an error too.
+        let err = JumpRope::try_from(&s.as_bytes()[..3]).unwrap_err();
+        assert_eq!(err.valid_up_to(), 2);
+    }
+
+    #[test]
+    fn map_bytes_in_place() {
+        let s = "κό𝕐𝕆😘σμε and some ascii text which spans several nodes 𝕐𝕆😘";
+        let chars = s.chars().collect::<Vec<_>>();
+        for start in 0..chars.len() {
+            for end in start..=chars.len() {
+                let mut r = JumpRope::from(s);
+                r.map_bytes_in_place(start..end, |b| b.to_ascii_uppercase());
+                let expected = chars[..start].iter()
+                    .chain(chars[start..end].iter().map(|c| c.to_ascii_uppercase()).collect::<Vec<_>>().iter())
+                    .chain(chars[end..].iter())
+                    .collect::<String>();
+                check(&r, &expected);
+
+                // Mapping to and from newlines changes the number of lines.
+                r.map_bytes_in_place(start..end, |b| if b == b' ' { b'\n' } else { b });
+                let with_newlines = expected.chars().enumerate()
+                    .map(|(i, c)| if (start..end).contains(&i) && c == ' ' { '\n' } else { c })
+                    .collect::<String>();
+                check(&r, &with_newlines);
+                r.map_bytes_in_place(0..end, |b| if b == b'\n' { b' ' } else { b });
+                check(&r, &expected);
+            }
+        }
+    }
+
+    #[test]
+    fn map_bytes_in_place_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        // Newlines written before the panic are still counted, and nothing is recorded.
+        let mut r = JumpRope::from("a b a b c");
+        let ops = r.with_recording(|r| {
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                r.map_bytes_in_place(0..9, |b| match b {
+                    b' ' => b'\n',
+                    b'c' => 0xff,
+                    b => b,
+                });
+            }));
+            assert!(result.is_err());
+        });
+        assert!(ops.is_empty());
+        check(&r, "a\nb\na\nb\nc");
+
+        // A panic in f part way through a multi-node range leaves the earlier bytes mapped.
+        let s = "a b ".repeat(100);
+        let mut r = JumpRope::from(s.as_str());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let mut n = 0;
+            r.map_bytes_in_place(0..400, |b| {
+                n += 1;
+                if n == 300 { panic!("oh no"); }
+                if b == b' ' { b'\n' } else { b }
+            });
+        }));
+        assert!(result.is_err());
+        check(&r, &(s[..299].replace(' ', "\n") + &s[299..]));
+    }
+
+    #[test]
+    fn custom_heights() {
+        // Out of range heights are clamped.
+        for h in [0, 1, 3, 255] {
+            let mut r = JumpRope::new_with_heights(move || h);
+            r.insert(0, "a bunch of text which spans several nodes");
+            r.remove(2..8);
+            r.insert(5, "κό𝕐𝕆😘σμε");
+            check(&r, "a of κό𝕐𝕆😘σμεtext which spans several nodes");
+        }
+    }
+
+    #[test]
+    fn replace_strict() {
+        let mut r = JumpRope::from("κό𝕐𝕆😘σμε");
+        let (start, end) = (4, 2);
+        assert_eq!(r.replace_strict(start..end, "x"), Err(RangeError::new(start..end, 8)));
+        assert!(r.replace_strict(2..9, "x").is_err());
+        check(&r, "κό𝕐𝕆😘σμε");
+
+        r.replace_strict(8..8, "!").unwrap();
+        r.replace_strict(0..2, "").unwrap();
+        check(&r, "𝕐𝕆😘σμε!");
+    }
+
     use std::ptr;
-    use jumprope::JumpRope;
+    use jumprope::{JumpRope, JumpRopeCustom, OverwriteLimit, RangeError};
 
     fn string_insert_at(s: &mut String, char_pos: usize, contents: &str) {
         // If you try to write past the end of the string for now I'll just write at the end.
@@ -186,6 +366,149 @@ mod test {
     }
 
 
+    #[test]
+    fn byte_edits() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut r = JumpRope::new();
+        let mut s = String::new();
+
+        for _ in 0..1000 {
+            // Pick a random character boundary.
+            let boundaries = (0..=s.len()).filter(|&i| s.is_char_boundary(i)).collect::<Vec<_>>();
+            let pos = boundaries[rng.gen_range(0..boundaries.len())];
+            if s.is_empty() || (s.len() < 1000 && rng.gen::<f32>() < 0.5) {
+                let text = random_unicode_string(rng.gen_range(0..20));
+                r.insert_at_byte(pos, &text);
+                s.insert_str(pos, &text);
+            } else {
+                let end = *boundaries.iter().find(|&&b| b >= pos + rng.gen_range(0..20)).unwrap_or(&s.len());
+                r.remove_byte_range(pos..end);
+                s.replace_range(pos..end, "");
+            }
+            check(&r, &s);
+            for (c, (i, _)) in s.char_indices().enumerate().step_by(7) {
+                assert_eq!(r.len_chars_at_or_before_byte(i), c);
+                assert_eq!(r.byte_to_char(i), c);
+                assert_eq!(r.char_to_byte(c), i);
+            }
+            assert_eq!(r.char_to_byte(r.len_chars()), s.len());
+
+            // Range conversions agree with converting each end separately.
+            let len = r.len_chars();
+            for start in (0..=len).step_by(5) {
+                for end in [start, start + 1, start + 9, start + 40, len + 3] {
+                    let bytes = r.byte_range_of_char_range(start..end);
+                    assert_eq!(bytes, r.char_to_byte(start)..r.char_to_byte(end.max(start)));
+                    assert_eq!(r.char_range_of_byte_range(bytes), start..end.max(start).min(len));
+                }
+            }
+        }
+
+        #[allow(clippy::reversed_empty_ranges)]
+        r.remove_byte_range(5..3);
+        r.remove_byte_range(1_000_000..2_000_000);
+        r.insert_at_byte(1_000_000, "!");
+        s.push('!');
+        check(&r, &s);
+    }
+
+    #[test]
+    fn remove_merges_nodes() {
+        // Deleting the middle of a document leaves a little content on each side, which should
+        // end up in a single node.
+        let mut r = JumpRope::from("κ".repeat(1000));
+        r.remove(2..997);
+        check(&r, "κκκκκ");
+        assert_eq!(r.chunks().count(), 1);
+
+        let mut r = JumpRope::from("a\n".repeat(1000));
+        r.replace(3..1997, "xyz");
+        check(&r, "a\naxyz\na\n");
+    }
+
+    #[test]
+    fn reserve_end() {
+        let mut r = JumpRope::from("hi");
+        r.reserve_end(0);
+        r.check();
+        r.reserve_end(10_000);
+        assert!(r.end_capacity() >= 10_000);
+        r.check();
+
+        // Edits anywhere can use the reserved nodes, and the rope stays consistent.
+        let size = r.mem_size();
+        let mut s = String::from("hi");
+        for i in 0..40 {
+            r.insert(i % 3, "κό𝕐𝕆");
+            s.insert_str(s.char_indices().nth(i % 3).map_or(s.len(), |(b, _)| b), "κό𝕐𝕆");
+            r.insert(r.len_chars(), "abc\n");
+            s.push_str("abc\n");
+        }
+        check(&r, &s);
+        assert_eq!(r.mem_size(), size);
+
+        // Reserved nodes stay with the rope when its content is moved elsewhere.
+        let mut r = JumpRope::new();
+        r.reserve_end(100);
+        r.insert(0, "a\nb");
+        assert_eq!(r.split_into_lines(), ["a", "b"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "inside a character")]
+    fn insert_inside_char() {
+        JumpRope::from("κόσμε").insert_at_byte(3, "x");
+    }
+
+    #[test]
+    fn unchecked_edits() {
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut r = JumpRope::new();
+        let mut s = String::new();
+
+        for _ in 0..500 {
+            let len = r.len_chars();
+            let pos = rng.gen_range(0..=len);
+            if len > 0 && rng.gen_bool(0.3) {
+                let end = min(pos + rng.gen_range(0..10), len);
+                unsafe { r.remove_unchecked(pos..end); }
+                let to_byte = |c: usize| s.char_indices().nth(c).map_or(s.len(), |(b, _)| b);
+                s.replace_range(to_byte(pos)..to_byte(end), "");
+            } else {
+                let content = if rng.gen_bool(0.5) { random_ascii_string(rng.gen_range(0..30)) } else { random_unicode_string(rng.gen_range(0..30)) };
+                unsafe { r.insert_unchecked(pos, &content, content.chars().count()); }
+                let byte_pos = s.char_indices().nth(pos).map_or(s.len(), |(b, _)| b);
+                s.insert_str(byte_pos, &content);
+            }
+        }
+        check(&r, &s);
+    }
+
+    #[test]
+    fn chunk_ids() {
+        // A chunk ID always refers to the same content, even after other edits.
+        let mut seen = std::collections::HashMap::<u64, String>::new();
+        let mut rng = SmallRng::seed_from_u64(5);
+        let mut r = JumpRope::from(random_unicode_string(100).as_str());
+
+        for i in 0..500 {
+            let mut ids = std::collections::HashSet::new();
+            for (id, s) in r.chunks_with_ids() {
+                assert!(ids.insert(id));
+                assert_eq!(seen.entry(id).or_insert_with(|| s.to_string()), s);
+            }
+
+            let len = r.len_chars();
+            let pos = rng.gen_range(0..=len);
+            match i % 4 {
+                0 => r.insert(pos, random_unicode_string(rng.gen_range(0..20))),
+                1 => r.remove(pos..min(pos + rng.gen_range(0..10), len)),
+                2 => r.replace_char(pos, 'x'),
+                _ => r.map_bytes_in_place(pos..pos + 5, |b| b.to_ascii_uppercase()),
+            }
+        }
+    }
+
     #[test]
     fn random_edits() {
         let mut r = JumpRope::new();
@@ -220,4 +543,340 @@ mod test {
             }
         }
     }
+
+    #[test]
+    fn edits_near_last_edit() {
+        // Runs of edits at nearby positions reuse the cursor from the previous edit. Mix them with
+        // other kinds of edits, and with moving and cloning the rope, to make sure the cached
+        // cursor is thrown away when it goes stale.
+        let mut rng = SmallRng::seed_from_u64(9);
+        let mut ropes = vec![JumpRope::from(random_unicode_string(50).as_str())];
+        let mut s = ropes[0].to_string();
+        let mut pos: usize = 0;
+
+        for i in 0..2000 {
+            let r = ropes.last_mut().unwrap();
+            let len = r.len_chars();
+            pos = (pos + rng.gen_range(0..5)).saturating_sub(2).min(len);
+
+            match rng.gen_range(0..10) {
+                0..=3 => {
+                    let text = random_unicode_string(rng.gen_range(0..4));
+                    r.insert(pos, text.as_str());
+                    string_insert_at(&mut s, pos, text.as_str());
+                    pos += text.chars().count();
+                }
+                4..=6 => {
+                    let dlen = min(rng.gen_range(0..4), len - pos);
+                    r.remove(pos..pos + dlen);
+                    string_del_at(&mut s, pos, dlen);
+                }
+                7 if pos < len => {
+                    r.replace_char(pos, 'x');
+                    string_del_at(&mut s, pos, 1);
+                    string_insert_at(&mut s, pos, "x");
+                }
+                8 => {
+                    let dlen = min(rng.gen_range(0..30), len - pos);
+                    r.replace(pos..pos + dlen, "");
+                    string_del_at(&mut s, pos, dlen);
+                }
+                _ => {
+                    // Moving the rope moves its inline head node.
+                    let r = if i % 2 == 0 { ropes.pop().unwrap() } else { r.clone() };
+                    ropes.push(r);
+                }
+            }
+            check(ropes.last().unwrap(), &s);
+        }
+    }
+
+    #[test]
+    fn edits_after_freeing_cached_node() {
+        // Deleting a whole node (without going through remove) must invalidate the cursor cached
+        // by the previous edit, which may point at the freed node.
+        let n = JumpRope::node_capacity();
+        let s = "a".repeat(n * 4);
+        let mut expected = s.clone();
+
+        let mut rope = JumpRope::from(s.as_str());
+        rope.remove(n + 5..n + 6);
+        rope.replace(n..2 * n - 1, "");
+        rope.insert(n + 2, "Z");
+        string_del_at(&mut expected, n, n);
+        string_insert_at(&mut expected, n + 2, "Z");
+        check(&rope, &expected);
+
+        let mut rope = JumpRope::from(s.as_str());
+        rope.remove(n + 5..n + 6);
+        rope.mut_cursor(n).delete(n - 1);
+        rope.insert(n + 2, "Z");
+        check(&rope, &expected);
+
+        let mut rope = JumpRope::from(s.as_str());
+        rope.remove(n + 5..n + 6);
+        rope.remove_byte_range(n..2 * n - 1);
+        rope.insert(n + 2, "Z");
+        check(&rope, &expected);
+    }
+
+    #[test]
+    fn custom_tunings() {
+        fn random_edits<const NODE: usize, const BIAS: u8>() {
+            let mut rng = SmallRng::seed_from_u64(4);
+            let mut r = JumpRopeCustom::<NODE, BIAS>::new();
+            let mut s = String::new();
+
+            for _ in 0..500 {
+                let len = s.chars().count();
+                if len == 0 || rng.gen_bool(0.6) {
+                    let pos = rng.gen_range(0..=len);
+                    let text = random_unicode_string(rng.gen_range(0..20));
+                    r.insert(pos, text.as_str());
+                    string_insert_at(&mut s, pos, text.as_str());
+                } else {
+                    let pos = rng.gen_range(0..len);
+                    let dlen = min(rng.gen_range(0..10), len - pos);
+                    r.remove(pos..pos + dlen);
+                    string_del_at(&mut s, pos, dlen);
+                }
+            }
+
+            r.check();
+            assert_eq!(r, s.as_str());
+            assert_eq!(r.len_chars(), s.chars().count());
+            assert_eq!(r.clone(), r);
+            assert!(r.chunks().all(|(chunk, _)| chunk.len() <= NODE));
+            assert_eq!(JumpRopeCustom::<NODE, BIAS>::config().bias, BIAS);
+        }
+
+        // Nodes which only fit one character, and a skip list which is just a linked list.
+        random_edits::<4, 0>();
+        random_edits::<37, 200>();
+        random_edits::<4096, 65>();
+    }
+
+    #[test]
+    fn append_ropes() {
+        let mut rng = SmallRng::seed_from_u64(9);
+        for _ in 0..100 {
+            let a = random_unicode_string(rng.gen_range(0..1000));
+            let b = random_unicode_string(rng.gen_range(0..1000));
+            let mut rope = JumpRope::from(a.as_str());
+            let other = JumpRope::from(b.as_str());
+            let mem = rope.mem_size() + other.mem_size();
+
+            rope.append(other);
+            rope.check();
+            let expected = a + &b;
+            assert_eq!(rope, expected.as_str());
+            assert!(rope.mem_size() <= mem);
+
+            // Chunk IDs stay unique, and the rope is still editable after the splice.
+            let mut ids = rope.chunks_with_ids().map(|(id, _)| id).collect::<Vec<_>>();
+            ids.sort_unstable();
+            ids.dedup();
+            assert_eq!(ids.len(), rope.chunks_with_ids().count());
+            rope.insert(rope.len_chars() / 2, "x\n");
+            rope.check();
+        }
+
+        let mut rope = JumpRope::from("abc");
+        let ops = rope.with_recording(|r| r.append(JumpRope::from("def".repeat(1000))));
+        assert_eq!(ops.len(), 1);
+        let mut replay = JumpRope::from("abc");
+        for op in &ops { op.apply(&mut replay); }
+        assert_eq!(replay, rope);
+    }
+
+    #[test]
+    fn split_off_ropes() {
+        let mut rng = SmallRng::seed_from_u64(10);
+        for _ in 0..100 {
+            let s = random_unicode_string(rng.gen_range(0..1000));
+            let len = s.chars().count();
+            let pos = rng.gen_range(0..=len + 2);
+            let mut rope = JumpRope::from(s.as_str());
+            let mem = rope.mem_size();
+
+            let tail = rope.split_off(pos);
+            rope.check();
+            tail.check();
+            let split = s.char_indices().nth(pos).map_or(s.len(), |(b, _)| b);
+            assert_eq!(rope, &s[..split]);
+            assert_eq!(tail, &s[split..]);
+            assert!(rope.mem_size() + tail.mem_size() <= mem + 2 * std::mem::size_of::<JumpRope>());
+
+            // Both halves can still be edited, and joined back together.
+            rope.insert(rope.len_chars(), "x");
+            rope.remove(rope.len_chars() - 1..rope.len_chars());
+            rope.append(tail);
+            rope.check();
+            assert_eq!(rope, s.as_str());
+        }
+
+        let mut rope = JumpRope::from("abc".repeat(1000));
+        let ops = rope.with_recording(|r| { r.split_off(10); });
+        let mut replay = JumpRope::from("abc".repeat(1000));
+        for op in &ops { op.apply(&mut replay); }
+        assert_eq!(replay, rope);
+    }
+
+    #[test]
+    fn extend_lines() {
+        let mut rng = SmallRng::seed_from_u64(11);
+        let mut rope = JumpRope::from("κό");
+        let mut expected = String::from("κό");
+        for _ in 0..20 {
+            let lines = (0..rng.gen_range(0..30))
+                .map(|_| random_unicode_string(rng.gen_range(0..40)))
+                .collect::<Vec<_>>();
+            let ops = rope.with_recording(|r| r.extend_lines(&lines));
+            for line in &lines {
+                expected.push_str(line);
+                expected.push('\n');
+            }
+            rope.check();
+            assert_eq!(rope, expected.as_str());
+
+            let mut replay = JumpRope::from(expected.as_str());
+            for op in ops.iter().rev() { op.inverse().apply(&mut replay); }
+            replay.extend_lines(lines.iter().map(String::as_str));
+            assert_eq!(replay, rope);
+        }
+    }
+
+    #[test]
+    fn retain_lines_across_nodes() {
+        let mut rng = SmallRng::seed_from_u64(13);
+        for _ in 0..50 {
+            let lines = (0..rng.gen_range(0..100))
+                .map(|_| random_unicode_string(rng.gen_range(0..60)))
+                .collect::<Vec<_>>();
+            let s = lines.join("\n");
+            let keep = lines.iter().map(|_| rng.gen_bool(0.3)).collect::<Vec<_>>();
+
+            let mut rope = JumpRope::from(s.as_str());
+            let ops = rope.with_recording(|r| {
+                let removed = r.retain_lines(|i, _| keep[i]);
+                assert_eq!(removed, keep.iter().filter(|k| !**k).count());
+            });
+            rope.check();
+            let expected = lines.iter().zip(&keep)
+                .filter(|(_, k)| **k)
+                .map(|(line, _)| line.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            assert_eq!(rope, expected.as_str());
+
+            let mut replay = JumpRope::from(s.as_str());
+            for op in &ops { op.apply(&mut replay); }
+            assert_eq!(replay, rope);
+
+            // The cursor cached by the removal is still usable.
+            rope.insert(rope.len_chars() / 2, "x\n");
+            rope.check();
+        }
+    }
+
+    #[test]
+    fn insert_rope() {
+        let mut rng = SmallRng::seed_from_u64(12);
+        let mut rope = JumpRope::new();
+        let mut expected = String::new();
+        for _ in 0..100 {
+            let content = random_unicode_string(rng.gen_range(0..300));
+            let len = expected.chars().count();
+            let pos = rng.gen_range(0..=len + 1);
+            if rng.gen_bool(0.5) {
+                rope.insert_rope(pos, &JumpRope::from(content.as_str()));
+            } else {
+                rope.insert_rope(pos, &JumpRopeCustom::<4, 100>::from(content.as_str()));
+            }
+            string_insert_at(&mut expected, pos.min(len), &content);
+            rope.check();
+            assert_eq!(rope, expected.as_str());
+        }
+
+        let paste = JumpRope::from("κό𝕐".repeat(100));
+        let ops = rope.with_recording(|r| r.insert_rope(10, &paste));
+        assert_eq!(ops.len(), 1);
+        ops[0].inverse().apply(&mut rope);
+        assert_eq!(rope, expected.as_str());
+    }
+
+    #[test]
+    fn add_ropes() {
+        let mut rope = JumpRope::from("κό") + "𝕐";
+        rope += "😘";
+        rope += JumpRope::from("abc".repeat(100));
+        rope += &JumpRope::from("\n");
+        let rope = rope.clone() + rope + &JumpRope::from("!");
+
+        let expected = format!("κό𝕐😘{}\n", "abc".repeat(100)).repeat(2) + "!";
+        rope.check();
+        assert_eq!(rope, expected);
+    }
+
+    #[test]
+    fn defragment_range() {
+        let mut rng = SmallRng::seed_from_u64(13);
+        let mut rope = JumpRope::new();
+        let mut expected = String::new();
+        // Typing in random places leaves lots of partly full nodes.
+        for _ in 0..5000 {
+            let pos = rng.gen_range(0..=expected.chars().count());
+            let text = random_unicode_string(rng.gen_range(1..3));
+            rope.insert(pos, &text);
+            string_insert_at(&mut expected, pos, &text);
+        }
+
+        for _ in 0..50 {
+            let len = rope.len_chars();
+            let start = rng.gen_range(0..=len);
+            let end = rng.gen_range(start..=len + 10);
+            let ops = rope.with_recording(|r| r.defragment_range(start..end));
+            assert!(ops.is_empty());
+            rope.check();
+            assert_eq!(rope, expected.as_str());
+
+            let end = end.min(len);
+            let bytes = rope.slice_chunks(start..end).map(|(s, _)| s.len()).sum::<usize>();
+            let max_chunks = bytes.div_ceil(JumpRope::node_capacity() - 3) + 3;
+            assert!(rope.slice_chunks(start..end).count() <= max_chunks);
+        }
+    }
+
+    #[test]
+    fn truncate_clear_pop() {
+        let s = "κό𝕐𝕆😘σμε and some ascii\n".repeat(50);
+        let mut rope = JumpRope::from(s.as_str());
+        let mut expected = s.clone();
+
+        rope.truncate(10_000);
+        assert_eq!(rope, expected.as_str());
+        for len in [1000, 500, 37, 36, 1] {
+            rope.truncate(len);
+            expected = expected.chars().take(len).collect();
+            rope.check();
+            assert_eq!(rope, expected.as_str());
+        }
+
+        let mut rope = JumpRope::from(s.as_str());
+        let ops = rope.with_recording(|r| {
+            for c in s.chars().rev().take(100) {
+                assert_eq!(r.pop_char(), Some(c));
+            }
+        });
+        assert_eq!(ops.len(), 100);
+        rope.check();
+        assert_eq!(rope.len_chars(), s.chars().count() - 100);
+
+        rope.clear();
+        rope.check();
+        assert!(rope.is_empty());
+        assert_eq!(rope.pop_char(), None);
+        rope.insert(0, "x");
+        assert_eq!(rope, "x");
+    }
 }